@@ -0,0 +1,236 @@
+use crate::{
+    commands::error::CommandError,
+    database::access::CommandAccess,
+    util::context::Context
+};
+use std::sync::Arc;
+use twilight_embed_builder::{EmbedBuilder, EmbedFooterBuilder};
+use twilight_interactions::command::{CommandModel, CommandOption, CreateCommand, CreateOption};
+use twilight_model::{
+    application::{callback::InteractionResponse, interaction::ApplicationCommand},
+    guild::Permissions,
+    id::{Id, marker::RoleMarker}
+};
+use twilight_util::builder::CallbackDataBuilder;
+
+const PERMISSION_NAMES: [(Permissions, &str); 6] = [
+    (Permissions::BAN_MEMBERS, "Ban Members"),
+    (Permissions::KICK_MEMBERS, "Kick Members"),
+    (Permissions::MANAGE_CHANNELS, "Manage Channels"),
+    (Permissions::MANAGE_GUILD, "Manage Server"),
+    (Permissions::MANAGE_MESSAGES, "Manage Messages"),
+    (Permissions::MANAGE_ROLES, "Manage Roles")
+];
+
+#[derive(Clone, Copy, CommandOption, CreateOption, Debug)]
+pub enum AccessibleCommand {
+    #[option(name = "category", value = "category")]
+    Category,
+    #[option(name = "check", value = "check")]
+    Check,
+    #[option(name = "history", value = "history")]
+    History,
+    #[option(name = "ignore", value = "ignore")]
+    Ignore,
+    #[option(name = "ping", value = "ping")]
+    Ping,
+    #[option(name = "set", value = "set")]
+    Set,
+    #[option(name = "settings", value = "settings")]
+    Settings,
+    #[option(name = "stats", value = "stats")]
+    Stats
+}
+
+#[derive(Clone, Copy, CommandOption, CreateOption, Debug)]
+pub enum PermissionChoice {
+    #[option(name = "None", value = "none")]
+    None,
+    #[option(name = "Ban Members", value = "ban_members")]
+    BanMembers,
+    #[option(name = "Kick Members", value = "kick_members")]
+    KickMembers,
+    #[option(name = "Manage Channels", value = "manage_channels")]
+    ManageChannels,
+    #[option(name = "Manage Server", value = "manage_guild")]
+    ManageGuild,
+    #[option(name = "Manage Messages", value = "manage_messages")]
+    ManageMessages,
+    #[option(name = "Manage Roles", value = "manage_roles")]
+    ManageRoles
+}
+
+impl PermissionChoice {
+    fn permissions(self) -> Permissions {
+        match self {
+            Self::None => Permissions::empty(),
+            Self::BanMembers => Permissions::BAN_MEMBERS,
+            Self::KickMembers => Permissions::KICK_MEMBERS,
+            Self::ManageChannels => Permissions::MANAGE_CHANNELS,
+            Self::ManageGuild => Permissions::MANAGE_GUILD,
+            Self::ManageMessages => Permissions::MANAGE_MESSAGES,
+            Self::ManageRoles => Permissions::MANAGE_ROLES
+        }
+    }
+}
+
+fn describe_permissions(permissions: Permissions) -> String {
+    PERMISSION_NAMES
+        .iter()
+        .filter(|(permission, _)| permissions.contains(*permission))
+        .map(|(_, name)| format!("**{name}**"))
+        .collect::<Vec<String>>()
+        .join(" + ")
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    desc = "Controls which roles may use Sakura's commands",
+    name = "access"
+)]
+pub enum AccessCommand {
+    #[command(name = "add-role")]
+    AddRole(AccessAddRole),
+    #[command(name = "remove-role")]
+    RemoveRole(AccessRemoveRole),
+    #[command(name = "permission")]
+    Permission(AccessPermission),
+    #[command(name = "reset")]
+    Reset(AccessReset),
+    #[command(name = "view")]
+    View(AccessView)
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(desc = "Lets a role use a command", name = "add-role")]
+pub struct AccessAddRole {
+    #[command(desc = "The command")]
+    command: AccessibleCommand,
+    #[command(desc = "The role to allow")]
+    role: Id<RoleMarker>
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(desc = "Stops a role from using a command", name = "remove-role")]
+pub struct AccessRemoveRole {
+    #[command(desc = "The command")]
+    command: AccessibleCommand,
+    #[command(desc = "The role to remove")]
+    role: Id<RoleMarker>
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(desc = "Lets anyone with a permission use a command", name = "permission")]
+pub struct AccessPermission {
+    #[command(desc = "The command")]
+    command: AccessibleCommand,
+    #[command(desc = "The permission members need, or none")]
+    permission: PermissionChoice
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(desc = "Limits a command to administrators again", name = "reset")]
+pub struct AccessReset {
+    #[command(desc = "The command")]
+    command: AccessibleCommand
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(desc = "Shows who may use each command", name = "view")]
+pub struct AccessView;
+
+impl AccessCommand {
+    pub async fn run(command: ApplicationCommand, context: Arc<Context>) -> Result<(), CommandError> {
+        let guild_id = command.guild_id.unwrap();
+        let options = AccessCommand::from_interaction(command.data.into())?;
+        let rules = context.database.read_command_access(guild_id).await?;
+        let rule = |name: &str| rules
+            .iter()
+            .find(|access| access.command == name)
+            .cloned()
+            .unwrap_or_else(|| CommandAccess::new(guild_id, name.to_string()));
+        let mut embed = EmbedBuilder::new().color(0xF8F8FF);
+
+        embed = match options {
+            AccessCommand::AddRole(AccessAddRole { command, role }) => {
+                let access = rule(command.value());
+
+                if access.role_ids.insert(role) {
+                    save(&context, access).await?;
+                    embed.description(format!("<@&{}> can now use `/{}`.", role, command.value()))
+                } else {
+                    embed.description(format!("<@&{}> can already use `/{}`.", role, command.value()))
+                }
+            },
+            AccessCommand::RemoveRole(AccessRemoveRole { command, role }) => {
+                let access = rule(command.value());
+
+                if access.role_ids.remove(&role).is_some() {
+                    save(&context, access).await?;
+                    embed.description(format!("<@&{}> can no longer use `/{}`.", role, command.value()))
+                } else {
+                    embed.description(format!("<@&{}> was not allowed to use `/{}`.", role, command.value()))
+                }
+            },
+            AccessCommand::Permission(AccessPermission { command, permission }) => {
+                let access = CommandAccess { permissions: permission.permissions(), ..rule(command.value()) };
+
+                save(&context, access).await?;
+
+                match permission {
+                    PermissionChoice::None => embed.description(format!("`/{}` no longer grants access by permission.", command.value())),
+                    _ => embed.description(format!("Members with {} can now use `/{}`.", describe_permissions(permission.permissions()), command.value()))
+                }
+            },
+            AccessCommand::Reset(AccessReset { command }) => {
+                context.database.delete_command_access(guild_id, command.value().to_string()).await?;
+                embed.description(format!("`/{}` is limited to administrators again.", command.value()))
+            },
+            AccessCommand::View(_) => {
+                let lines = rules.iter().map(|access| {
+                    let mut allowed = access.role_ids.iter().map(|role_id| format!("<@&{}>", *role_id)).collect::<Vec<String>>();
+
+                    if !access.permissions.is_empty() {
+                        allowed.push(describe_permissions(access.permissions));
+                    }
+
+                    format!("`/{}`: {}", access.command, allowed.join(", "))
+                }).collect::<Vec<String>>();
+                let description = if lines.is_empty() {
+                    "Every command is limited to administrators.".to_string()
+                } else {
+                    lines.join("\n")
+                };
+
+                embed
+                    .description(description)
+                    .footer(EmbedFooterBuilder::new("Administrators can always use every command. Other commands are administrator-only."))
+            }
+        };
+
+        context
+            .get_interaction_client()
+            .interaction_callback(
+                command.id,
+                &command.token,
+                &InteractionResponse::ChannelMessageWithSource(
+                    CallbackDataBuilder::new().embeds(embed.build()).build()
+                )
+            )
+            .exec()
+            .await?;
+
+        Ok(())
+    }
+}
+
+// A rule with nothing left in it is the same as no rule, so drop it instead of keeping an empty row
+async fn save(context: &Context, access: CommandAccess) -> Result<(), CommandError> {
+    if access.is_empty() {
+        context.database.delete_command_access(access.guild_id, access.command).await?;
+    } else {
+        context.database.upsert_command_access(access).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use crate::{
+    commands::error::CommandError,
+    util::context::Context
+};
+use std::sync::Arc;
+use twilight_embed_builder::EmbedBuilder;
+use twilight_interactions::command::{CommandModel, CreateCommand};
+use twilight_model::application::{callback::InteractionResponse, interaction::ApplicationCommand};
+use twilight_util::builder::CallbackDataBuilder;
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    desc = "Stops the invite check in progress after the channel it is on",
+    name = "cancel-check"
+)]
+pub struct CancelCheckCommand;
+
+impl CancelCheckCommand {
+    // The check notices on its own between channels, and clears in_check when it stops
+    pub async fn run(command: ApplicationCommand, context: Arc<Context>) -> Result<(), CommandError> {
+        let guild_id = command.guild_id.unwrap();
+        let setting = match context.database.read_setting(guild_id).await? {
+            Some(setting) => setting,
+            None => return Err(CommandError::Validation("No settings found. Please kick and reinvite Sakura.".to_string()))
+        };
+
+        if !context.shutdown.cancel_check(guild_id) {
+            return Err(CommandError::Validation("There is no invite check in progress for this guild.".to_string()))
+        }
+
+        let embed = EmbedBuilder::new()
+            .color(setting.embed_color)
+            .description("Sakura will stop the invite check after the channel it is on. Your cooldown will not be used.");
+
+        context
+            .get_interaction_client()
+            .interaction_callback(
+                command.id,
+                &command.token,
+                &InteractionResponse::ChannelMessageWithSource(
+                    CallbackDataBuilder::new().embeds(embed.build()).build()
+                )
+            )
+            .exec()
+            .await?;
+
+        Ok(())
+    }
+}
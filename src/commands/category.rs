@@ -1,4 +1,7 @@
-use crate::util::{context::Context, invite::extract_codes_from_category};
+use crate::{
+    commands::error::CommandError,
+    util::{context::Context, invite::extract_codes_from_category}
+};
 use std::sync::Arc;
 use twilight_embed_builder::EmbedBuilder; 
 use twilight_interactions::command::{CommandModel, CreateCommand};
@@ -35,7 +38,7 @@ pub struct CategoryRemove {
 }
 
 impl CategoryCommand {
-    pub async fn run(command: ApplicationCommand, context: Arc<Context>) {
+    pub async fn run(command: ApplicationCommand, context: Arc<Context>) -> Result<(), CommandError> {
         context
             .get_interaction_client()
             .interaction_callback(
@@ -44,12 +47,11 @@ impl CategoryCommand {
                 &InteractionResponse::DeferredChannelMessageWithSource(CallbackDataBuilder::new().build())
             )
             .exec()
-            .await
-            .unwrap();
+            .await?;
 
         let guild_id = command.guild_id.unwrap();
-        let options = CategoryCommand::from_interaction(command.data.into()).unwrap();
-        let category_channel_ids = context.database.read_category_channel_ids(guild_id).await;
+        let options = CategoryCommand::from_interaction(command.data.into())?;
+        let category_channel_ids = context.database.read_category_channel_ids(guild_id).await?;
         let mut embed = EmbedBuilder::new().color(0xF8F8FF);
     
         embed = match options {
@@ -57,10 +59,15 @@ impl CategoryCommand {
                 if category_channel_ids.contains(&category.id) {
                     embed.description("This category has already been added.")
                 } else {
-                    extract_codes_from_category(guild_id, category.id, context.clone()).await;
+                    let report = extract_codes_from_category(guild_id, category.id, context.clone()).await?;
                     category_channel_ids.insert(category.id);
-                    context.database.update_category_channel_ids(guild_id, category_channel_ids).await;
-                    embed.description(format!("<#{}> will now be checked during invite checks.", category.id))
+                    context.database.update_category_channel_ids(guild_id, category_channel_ids).await?;
+                    embed.description(format!(
+                        "<#{}> will now be checked during invite checks.\n**{}** new invite(s) found, **{}** already known.",
+                        category.id,
+                        report.new,
+                        report.known
+                    ))
                 }
             },
             CategoryCommand::Remove(CategoryRemove { category }) => {
@@ -68,7 +75,7 @@ impl CategoryCommand {
                     embed.description("This channel is not in the \"category\" list.")
                 } else {
                     category_channel_ids.remove(&category.id);
-                    context.database.update_category_channel_ids(guild_id, category_channel_ids).await;
+                    context.database.update_category_channel_ids(guild_id, category_channel_ids).await?;
                     embed.description(format!("<#{}> will no longer be checked during invite checks.", category.id))
                 }
             },
@@ -77,10 +84,10 @@ impl CategoryCommand {
         context
             .get_interaction_client()
             .update_interaction_original(&command.token)
-            .embeds(Some(&[embed.build().unwrap()]))
-            .unwrap()
+            .embeds(Some(&[embed.build()?]))?
             .exec()
-            .await
-            .unwrap();
+            .await?;
+
+        Ok(())
     }
 }
\ No newline at end of file
@@ -1,31 +1,40 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use crate::{
-    constants::CLIENT_ID,
-    database::setting::Setting,
+    commands::error::{CommandError, SHUTDOWN_MESSAGE},
+    database::{
+        check::{CategoryResult, ChannelResult, CheckDiff, CheckRun, CheckStatus, CheckTotals, InviteResult, InviteStatus},
+        invite::Invite,
+        setting::Setting
+    },
     util::{
         context::Context,
-        invite::extract_codes_from_message,
-        random::{add_commas, humanize}
+        export,
+        invite::{extract_codes_from_message, read_messages, InviteOutcome},
+        random::{add_commas, humanize},
+        resolver::Priority
     }
 };
+use futures_util::future;
 use std::{
     collections::{HashMap, HashSet},
     cmp,
     fmt,
-    error::Error,
-    sync::Arc
+    sync::Arc,
+    time::{Duration, Instant}
 };
+use tracing::{error, warn};
 use twilight_embed_builder::{
     EmbedBuilder,
     EmbedFieldBuilder,
     EmbedFooterBuilder
 };
+use twilight_http::request::AttachmentFile;
 use twilight_interactions::command::{CommandModel, CreateCommand};
 use twilight_model::{
     application::{callback::InteractionResponse, interaction::ApplicationCommand},
-    channel::{embed::Embed, GuildChannel, message::MessageFlags},
+    channel::{embed::Embed, GuildChannel},
     datetime::Timestamp,
-    id::{Id, marker::{ChannelMarker, MessageMarker}},
+    id::{Id, marker::{ChannelMarker, GuildMarker, MessageMarker, UserMarker}},
     guild::Permissions, 
 };
 use twilight_util::builder::CallbackDataBuilder;
@@ -35,244 +44,462 @@ use twilight_util::builder::CallbackDataBuilder;
     desc = "Runs an invite check",
     name = "check"
 )]
-pub struct CheckCommand;
-
-pub struct ChannelResult {
-    bad: u32,
-    channel_id: Id<ChannelMarker>,
-    good: u32
+pub struct CheckCommand {
+    #[command(desc = "Also attach the results as JSON and CSV files")]
+    export: Option<bool>
 }
 
-impl ChannelResult {
-    fn new(channel_id: Id<ChannelMarker>) -> Self {
-        Self {
-            bad: 0,
-            channel_id,
-            good: 0
-        }
-    }
-}
+const IN_CHECK_MESSAGE: &str = "Sakura is still checking categories for this guild. Please try again at a later time.";
+// Discord rate limits message edits, so the progress message is updated at most this often
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+type ChildChannel = (Id<ChannelMarker>, Option<Id<MessageMarker>>, i64);
 
 impl fmt::Display for ChannelResult {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let (emoji, description) = if self.bad > 0 { ('🔴', format!(" (**{}** bad)", self.bad)) } else { ('🟢', "".to_string()) };
-        let total = self.bad + self.good;
+        let emoji = if self.bad > 0 { '🔴' } else if self.unresolved > 0 { '🟡' } else { '🟢' };
+        let mut description = String::new();
+        let total = self.bad + self.good + self.unresolved;
+
+        if self.bad > 0 {
+            description.push_str(&format!(" (**{}** bad)", self.bad));
+        }
+        if self.unresolved > 0 {
+            description.push_str(&format!(" (**{}** could not be verified)", self.unresolved));
+        }
 
         write!(f, "{emoji} <#{}> - **{total}** total{description}", self.channel_id)
     }
 }
 
-pub struct CategoryResult {
-    channel_results: Vec<ChannelResult>,
-    issues: u32,
-    manual: Vec<Id<ChannelMarker>>,
-    name: String
-}
-
 impl CategoryResult {
-    fn new(name: String) -> Self {
-        Self {
-            channel_results: vec![],
-            issues: 0,
-            manual: vec![],
-            name
-        }
-    }
-
-    fn embed(&self, color: u32) -> Embed {        
-        let (description, footer) = if self.channel_results.len() > 0 {
-            (
-                self.channel_results.iter().map(|channel_result| format!("{}", channel_result)).collect::<Vec<String>>().join("\n"),
-                EmbedFooterBuilder::new("Checked 15 messages")
-            )
+    pub fn embed(&self, color: u32, checked_at: NaiveDateTime) -> Result<Embed, CommandError> {
+        let description = if !self.channel_results.is_empty() {
+            self.channel_results.iter().map(|channel_result| format!("{}", channel_result)).collect::<Vec<String>>().join("\n")
         } else {
-            (
-                "No channels to check in this category.".to_string(),
-                EmbedFooterBuilder::new("Checked 0 messages")
-            )
+            "No channels to check in this category.".to_string()
         };
+        let messages = self.channel_results.iter().map(|channel_result| channel_result.messages).sum::<u32>();
+        let footer = EmbedFooterBuilder::new(format!("Checked {} messages", add_commas(&messages.to_string())));
         let mut embed = EmbedBuilder::new()
             .color(color)
             .description(description)
             .footer(footer)
-            .timestamp(Timestamp::from_secs(Utc::now().timestamp()).unwrap())
+            .timestamp(Timestamp::from_secs(checked_at.and_utc().timestamp())?)
             .title(format!("The \'{}\" category", self.name));
 
-        if self.issues > 0 {
+        if !self.issue_channel_ids.is_empty() {
             embed = embed.field(EmbedFieldBuilder::new(
                 "Issues",
-                format!("- {} channel(s) could not be checked", self.channel_results.len())
+                format!("- {} channel(s) could not be checked", self.issue_channel_ids.len())
             ).build());
         }
-        if self.manual.len() > 0 {
+        if !self.manual_channel_ids.is_empty() {
             embed = embed.field(EmbedFieldBuilder::new(
                 "Manual check(s) required",
-                self.manual.iter().map(|channel_id| format!("- <#{}>", channel_id)).collect::<Vec<String>>().join("\n")
+                self.manual_channel_ids.iter().map(|channel_id| format!("- <#{}>", channel_id)).collect::<Vec<String>>().join("\n")
                 
             ).build());
         }
         
-        embed.build().unwrap()
+        Ok(embed.build()?)
+    }
+}
+
+// The changes since the guild's last completed check, or None if this is its first
+pub async fn read_diff(context: &Context, run: &CheckRun, category_results: &[CategoryResult]) -> Result<Option<CheckDiff>, CommandError> {
+    let previous_run = match context.database.read_previous_check_run(run.guild_id, run.id).await? {
+        Some(previous_run) => previous_run,
+        None => return Ok(None)
+    };
+    let previous_results = context.database.read_category_results(previous_run.id).await?;
+
+    Ok(Some(CheckDiff::new(previous_run.id, &previous_results, category_results)))
+}
+
+// Cuts at the last whole item that fits, since embed field values are capped
+fn truncate_list(value: String, limit: usize) -> String {
+    if value.chars().count() <= limit {
+        return value
+    }
+
+    let end = value.char_indices().nth(limit - 3).map_or(value.len(), |(index, _)| index);
+    let cut = value[..end].rfind([',', '\n']).unwrap_or(end);
+
+    format!("{}...", &value[..cut])
+}
+
+fn describe_diff(diff: &CheckDiff) -> String {
+    if diff.is_empty() {
+        return format!("Nothing changed since check #{}.", diff.previous_run_id)
+    }
+
+    let mentions = |channel_ids: &[Id<ChannelMarker>]| channel_ids
+        .iter()
+        .map(|channel_id| format!("<#{channel_id}>"))
+        .collect::<Vec<String>>()
+        .join(", ");
+    let sections = [
+        ("Now has bad invites", mentions(&diff.broken_channel_ids)),
+        ("Fixed", mentions(&diff.fixed_channel_ids)),
+        ("New channels", mentions(&diff.new_channel_ids)),
+        ("Removed channels", mentions(&diff.removed_channel_ids)),
+        (
+            "Expired invites",
+            diff.expired_codes.iter().map(|(channel_id, code)| format!("`{code}` in <#{channel_id}>")).collect::<Vec<String>>().join(", ")
+        )
+    ];
+    let description = sections
+        .iter()
+        .filter(|(_, list)| !list.is_empty())
+        .map(|(label, list)| format!("- **{label}:** {list}"))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    truncate_list(description, 1024)
+}
+
+// Built from the stored run rather than the in-flight check, so a past run renders the same way
+pub fn summary_embed(run: &CheckRun, category_results: &[CategoryResult], diff: Option<&CheckDiff>, color: u32) -> Result<Embed, CommandError> {
+    let finished_at = run.finished_at.unwrap_or_else(|| Utc::now().naive_utc());
+    let elapsed_time = humanize((finished_at - run.started_at).num_milliseconds().max(0) as u64, true);
+    let totals = CheckTotals::from(category_results);
+    let total_invites = cmp::max(totals.invites(), 1);
+    let mut stats = vec![
+        format!("- **{}** channel(s) checked", add_commas(&totals.channels.to_string())),
+        format!("- **{}** invite(s) checked", add_commas(&total_invites.to_string())),
+        format!("- **{}** ({:.2}%) invalid invite(s)", totals.bad, totals.invalid_percentage()),
+        format!("- **{}** ({:.2}%) valid invite(s)", totals.good, (totals.good * 100) as f32 / total_invites as f32)
+    ];
+
+    if totals.unresolved > 0 {
+        stats.push(format!("- **{}** invite(s) could not be verified", totals.unresolved));
+    }
+
+    let stats = stats.join("\n");
+    let (title, description) = match run.status {
+        CheckStatus::Cancelled => (
+            "Invite check results (cancelled)",
+            format!("Run by <@{}>\nThis check was cancelled, so it only covers the channels Sakura reached before it stopped.", run.user_id)
+        ),
+        _ => ("Invite check results", format!("Run by <@{}>", run.user_id))
+    };
+    let mut embed = EmbedBuilder::new()
+        .color(color)
+        .description(description)
+        .field(EmbedFieldBuilder::new("Elapsed time", elapsed_time).build())
+        .field(EmbedFieldBuilder::new("Stats", stats).build());
+
+    if let Some(diff) = diff {
+        embed = embed.field(EmbedFieldBuilder::new(format!("Changes since check #{}", diff.previous_run_id), describe_diff(diff)).build());
     }
+    
+    Ok(embed
+        .timestamp(Timestamp::from_secs(finished_at.and_utc().timestamp())?)
+        .footer(EmbedFooterBuilder::new(format!("Check #{}", run.id)))
+        .title(title)
+        .build()?)
+}
+
+// Holds a guild's in_check flag and clears it however the check ends, including on errors, panics and cancellation
+struct CheckLock {
+    context: Arc<Context>,
+    guild_id: Id<GuildMarker>,
+    is_released: bool
 }
 
+impl CheckLock {
+    async fn acquire(context: Arc<Context>, guild_id: Id<GuildMarker>, results_channel_id: Id<ChannelMarker>) -> Result<Self, CommandError> {
+        if context.shutdown.is_started() {
+            return Err(CommandError::Validation(SHUTDOWN_MESSAGE.to_string()))
+        }
+
+        // prepare looked at in_check on an earlier read, so a check that started since then only shows up here
+        if !context.database.update_in_check(guild_id, true).await? {
+            return Err(CommandError::Validation(IN_CHECK_MESSAGE.to_string()))
+        }
+
+        context.shutdown.track_check(guild_id, results_channel_id);
+
+        Ok(Self {
+            context,
+            guild_id,
+            is_released: false
+        })
+    }
+
+    async fn release(mut self) -> Result<(), CommandError> {
+        self.is_released = true;
+        self.context.database.update_in_check(self.guild_id, false).await?;
+        self.context.shutdown.finish_check(self.guild_id);
 
-pub struct InviteCheck {
-    category_results: Vec<CategoryResult>,
-    start_time: DateTime<Utc>
+        Ok(())
+    }
 }
 
-impl InviteCheck {
-    fn new() -> Self {
+impl Drop for CheckLock {
+    fn drop(&mut self) {
+        if !self.is_released {
+            self.context.shutdown.finish_check(self.guild_id);
+
+            let database = self.context.database.clone();
+            let guild_id = self.guild_id;
+
+            tokio::spawn(async move {
+                if let Err(error) = database.update_in_check(guild_id, false).await {
+                    error!(%guild_id, %error, "could not reset in_check");
+                }
+            });
+        }
+    }
+}
+
+// The message that announced the check, a command's response or a scheduled check's post in the results channel
+enum ProgressMessage {
+    Interaction(String),
+    Channel(Id<ChannelMarker>, Id<MessageMarker>)
+}
+
+// Edited in place as the check goes, then finalised once the summary is posted
+struct CheckProgress {
+    message: ProgressMessage,
+    color: u32,
+    description: &'static str,
+    started_at: Instant,
+    edited_at: Instant,
+    categories: usize,
+    categories_done: usize,
+    channels: usize,
+    channels_done: usize,
+    invites: usize
+}
+
+impl CheckProgress {
+    fn new(message: ProgressMessage, color: u32, description: &'static str, categories: usize, channels: usize) -> Self {
+        let now = Instant::now();
+
         Self {
-            category_results: vec![],
-            start_time: Utc::now()
+            message,
+            color,
+            description,
+            started_at: now,
+            edited_at: now,
+            categories,
+            categories_done: 0,
+            channels,
+            channels_done: 0,
+            invites: 0
         }
     }
 
-    fn end_and_show_results(&self, color: u32) -> Embed {
-        let end_time = Utc::now();
-        let elapsed_time = humanize((end_time.timestamp_millis() - self.start_time.timestamp_millis()) as u64, true);
-        let mut total_channels = 0;
-        let mut total_bad = 0;
-        let mut total_good = 0;
+    // None while the check is running, which shows the rate and ETA instead of the elapsed time
+    fn embed(&self, elapsed: Duration, status: Option<CheckStatus>) -> Result<Embed, CommandError> {
+        let description = match status {
+            None => self.description,
+            Some(CheckStatus::Cancelled) => "Sakura stopped this invite check early. The partial results are below.",
+            Some(_) => "Sakura finished this invite check. The results are below."
+        };
+        let embed = EmbedBuilder::new()
+            .color(self.color)
+            .description(description)
+            .field(EmbedFieldBuilder::new("Categories", format!("{}/{}", self.categories_done, self.categories)).inline().build())
+            .field(EmbedFieldBuilder::new(
+                "Channels",
+                format!("{}/{}", add_commas(&self.channels_done.to_string()), add_commas(&self.channels.to_string()))
+            ).inline().build())
+            .field(EmbedFieldBuilder::new("Invites resolved", add_commas(&self.invites.to_string())).inline().build());
+        let embed = match status {
+            None => {
+                let minutes = elapsed.as_secs_f64() / 60.0;
+                let rate = if minutes > 0.0 { self.channels_done as f64 / minutes } else { 0.0 };
+                let eta = if rate > 0.0 {
+                    humanize((self.channels.saturating_sub(self.channels_done) as f64 / rate * 60_000.0) as u64, false)
+                } else {
+                    "Calculating...".to_string()
+                };
 
-        for CategoryResult { channel_results, issues, manual, .. } in &self.category_results {
-            total_channels += channel_results.len() as u32 + issues + manual.len() as u32;
+                embed
+                    .field(EmbedFieldBuilder::new("Rate", format!("{rate:.1} channels/min")).inline().build())
+                    .field(EmbedFieldBuilder::new("ETA", eta).inline().build())
+            },
+            Some(_) => embed.field(EmbedFieldBuilder::new("Elapsed time", humanize(elapsed.as_millis() as u64, true)).build())
+        };
 
-            if channel_results.len() == 0 {
-                continue
-            }
+        Ok(embed.build()?)
+    }
 
-            for ChannelResult { bad, good, .. } in channel_results {
-                total_bad += bad;
-                total_good += good;
+    // Progress is cosmetic, so a failed edit is logged rather than failing the check
+    async fn edit(&mut self, context: &Context, status: Option<CheckStatus>) {
+        self.edited_at = Instant::now();
+
+        let embeds = match self.embed(self.started_at.elapsed(), status) {
+            Ok(embed) => [embed],
+            Err(error) => {
+                warn!(%error, "could not build the check progress");
+                return
+            }
+        };
+        let result = match &self.message {
+            ProgressMessage::Interaction(token) => match context.get_interaction_client().update_interaction_original(token).embeds(Some(&embeds)) {
+                Ok(request) => request.exec().await.map(|_| ()),
+                Err(_) => return
+            },
+            ProgressMessage::Channel(channel_id, message_id) => match context.client.update_message(*channel_id, *message_id).embeds(&embeds) {
+                Ok(request) => request.exec().await.map(|_| ()),
+                Err(_) => return
             }
+        };
+
+        if let Err(error) = result {
+            warn!(%error, "could not update the check progress");
         }
+    }
 
-        let total_invites = cmp::max(total_bad + total_good, 1);
-        let stats = vec![
-            format!("- **{}** channel(s) checked", add_commas(&total_channels.to_string())),
-            format!("- **{}** invite(s) checked", add_commas(&total_invites.to_string())),
-            format!("- **{total_bad}** ({:.2}%) invalid invite(s)", (total_bad * 100) as f32 / total_invites as f32),
-            format!("- **{total_good}** ({:.2}%) valid invite(s)", (total_good * 100) as f32 / total_invites as f32)
-        ].join("\n");
-        
-        EmbedBuilder::new()
-            .color(color)
-            .field(EmbedFieldBuilder::new("Elapsed time", elapsed_time).build())
-            .field(EmbedFieldBuilder::new("Stats", stats).build())
-            .timestamp(Timestamp::from_secs(Utc::now().timestamp()).unwrap())
-            .title("Invite check results")
-            .build()
-            .unwrap()
+    async fn update(&mut self, context: &Context) {
+        if self.edited_at.elapsed() >= PROGRESS_INTERVAL {
+            self.edit(context, None).await;
+        }
+    }
+
+    async fn finish(&mut self, context: &Context, status: CheckStatus) {
+        self.edit(context, Some(status)).await;
     }
 }
 
 impl CheckCommand {
-    pub async fn run(command: ApplicationCommand, context: Arc<Context>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    pub async fn run(command: ApplicationCommand, context: Arc<Context>) -> Result<(), CommandError> {
         let guild_id = command.guild_id.unwrap();
-        let setting = context.database.read_setting(guild_id).await;
-        let error_embed = EmbedBuilder::new().color(0xF8F8FF);
+        let options = CheckCommand::from_interaction(command.data.clone().into())?;
+        let setting = context.database.read_setting(guild_id).await?;
         let now = Utc::now();
-        let setting_error_description = match &setting {
-            Some(setting) => {
-                let remaining_seconds = match setting.last_check {
-                    Some(ndt) => (((ndt.timestamp_millis() - now.timestamp_millis() + 86_400_000) as f64) / 1000f64).floor() as i64,
-                    None => 0
-                };
+        let setting = match setting {
+            Some(setting) => setting,
+            None => return Err(CommandError::Validation("No settings found. Please kick and reinvite Sakura.".to_string()))
+        };
 
-                if remaining_seconds > 0 {
-                    let next_check_s = now.timestamp() + remaining_seconds;
-                    format!("You may run an invite check at <t:{}> (<t:{}:R>)", next_check_s, next_check_s)
-                } else if setting.results_channel_id.is_none() {
-                    "No results channel has been set for this guild. Please set one before running an invite check.".to_string()
-                } else if context.cache.guild_channel(setting.results_channel_id.unwrap()).is_none() {
-                    "Your current results channel may have been deleted. Please set a new one.".to_string()
-                } else if setting.results_channel_id.unwrap() != command.channel_id {
-                    format!("This command can only be run in <#{}>.", setting.results_channel_id.unwrap())
-                } else if setting.category_channel_ids.len() <= 0 {
-                    "There are no categories to check. Please add some before running an invite check.".to_string()
-                } else if setting.in_check {
-                    "Sakura is still checking categories for this guild. Please try again at a later time.".to_string()
-                } else {
-                    String::new()
-                }
-            },
-            None => "No settings found. Please kick and reinvite Sakura.".to_string(),
+        if let Some(next_check) = setting.next_check(context.config.check_cooldown(setting.check_cooldown), now.naive_utc()) {
+            let next_check_s = next_check.and_utc().timestamp();
+
+            return Err(CommandError::Validation(format!("You may run an invite check at <t:{}> (<t:{}:R>)", next_check_s, next_check_s)))
+        }
+
+        let known_codes = Self::prepare(&context, &setting, Some(command.channel_id)).await?;
+        let user_id = command.member
+            .as_ref()
+            .and_then(|member| member.user.as_ref())
+            .map(|user| user.id)
+            .ok_or_else(|| CommandError::Internal("check command has no member".to_string()))?;
+
+        Self::start(context, setting, known_codes, user_id, Some(command), options.export.unwrap_or(false)).await
+    }
+
+    // Everything a check needs besides the cooldown, which scheduled checks don't wait for
+    // Commands have to come from the results channel, scheduled checks pass None
+    pub async fn prepare(context: &Context, setting: &Setting, channel_id: Option<Id<ChannelMarker>>) -> Result<HashMap<String, Invite>, CommandError> {
+        let setting_error_description = match setting.results_channel_id {
+            None => "No results channel has been set for this guild. Please set one before running an invite check.".to_string(),
+            Some(results_channel_id) if context.cache.guild_channel(results_channel_id).is_none() => "Your current results channel may have been deleted. Please set a new one.".to_string(),
+            Some(results_channel_id) if channel_id.is_some_and(|channel_id| channel_id != results_channel_id) => format!("This command can only be run in <#{}>.", results_channel_id),
+            _ if setting.category_channel_ids.is_empty() => "There are no categories to check. Please add some before running an invite check.".to_string(),
+            _ if setting.in_check => IN_CHECK_MESSAGE.to_string(),
+            _ => String::new()
         };
 
         if !setting_error_description.is_empty() {
-            context
-                .get_interaction_client()
-                .interaction_callback(
-                    command.id,
-                    &command.token,
-                    &InteractionResponse::ChannelMessageWithSource(
-                        CallbackDataBuilder::new()
-                            .embeds(error_embed.description(setting_error_description).build())
-                            .flags(MessageFlags::EPHEMERAL)
-                            .build()
-                    )
-                )
-                .exec()
-                .await?;
+            return Err(CommandError::Validation(setting_error_description))
+        }
+
+        let known_codes = context.database.read_guild_invites(setting.guild_id).await?;
 
-            return Ok(())
+        if let Some(last_check) = setting.last_check {
+            if known_codes.values().any(|code| code.is_valid.is_some() && code.updated_at < last_check) {
+                return Err(CommandError::Validation("All invites have not been updated since your last invite check. Please try again at a later time.".to_string()))
+            }
         }
 
-        let setting = setting.unwrap();
-        let known_codes = context.database.read_guild_invites(guild_id).await;
-        let codes_error_description = match &known_codes {
-            Some(known_codes) => match setting.last_check {
-                Some(last_check) if known_codes.values().into_iter().any(|code| code.is_valid.is_some() && code.updated_at < last_check) => {
-                    "All invites have not been updated since your last invite check. Please try again at a later time.".to_string()
-                },
-                _ => String::new(),
-            },
-            None => "There are no codes to check.".to_string(),
+        Ok(known_codes)
+    }
+
+    // Runs a prepared check, answering the command when there is one and announcing it in the results channel otherwise
+    pub async fn start(
+        context: Arc<Context>,
+        setting: Setting,
+        known_codes: HashMap<String, Invite>,
+        user_id: Id<UserMarker>,
+        command: Option<ApplicationCommand>,
+        is_exported: bool
+    ) -> Result<(), CommandError> {
+        let guild_id = setting.guild_id;
+        // prepare only lets checks through when there's a results channel
+        let results_channel_id = setting.results_channel_id.unwrap();
+        let lock = CheckLock::acquire(context.clone(), guild_id, results_channel_id).await?;
+        let run_id = context.database.create_check_run(guild_id, user_id).await?;
+        let started_at = Instant::now();
+        let result = Self::check(command, context.clone(), setting, known_codes, run_id, is_exported).await;
+        let outcome = match &result {
+            Ok(status) => status.as_str(),
+            Err(_) => "failed"
         };
 
-        if !codes_error_description.is_empty() {
-            context
-                .get_interaction_client()
-                .interaction_callback(
-                    command.id,
-                    &command.token,
-                    &InteractionResponse::ChannelMessageWithSource(
-                        CallbackDataBuilder::new()
-                            .embeds(error_embed.description(codes_error_description).build())
-                            .flags(MessageFlags::EPHEMERAL)
-                            .build()
+        if result.is_err() {
+            if let Err(error) = context.database.finish_check_run(run_id, CheckStatus::Failed).await {
+                warn!(%guild_id, run_id, %error, "could not mark check run as failed");
+            }
+        }
+
+        context.metrics.checks.with_label_values(&[outcome]).inc();
+        context.metrics.check_duration.observe(started_at.elapsed().as_secs_f64());
+        lock.release().await?;
+
+        result.map(|_| ())
+    }
+
+    // Completed, or Cancelled when /cancel-check stopped it between channels
+    async fn check(command: Option<ApplicationCommand>, context: Arc<Context>, setting: Setting, known_codes: HashMap<String, Invite>, run_id: i64, is_exported: bool) -> Result<CheckStatus, CommandError> {
+        let guild_id = setting.guild_id;
+        let now = Utc::now();
+        let embed = |description: &str| EmbedBuilder::new().color(setting.embed_color).description(description).build();
+
+        let (progress_message, progress_description) = match command {
+            Some(command) => {
+                let description = "Sakura is checking your invites now!";
+
+                context
+                    .get_interaction_client()
+                    .interaction_callback(
+                        command.id,
+                        &command.token,
+                        &InteractionResponse::ChannelMessageWithSource(
+                            CallbackDataBuilder::new().embeds(embed(description)).build()
+                        )
                     )
-                )
-                .exec()
-                .await?;
+                    .exec()
+                    .await?;
 
-            return Ok(())
-        }
+                (ProgressMessage::Interaction(command.token), description)
+            },
+            None => {
+                let description = "Sakura is running a scheduled invite check now!";
+                let message = context
+                    .client
+                    .create_message(setting.results_channel_id.unwrap())
+                    .embeds(&[embed(description)?])?
+                    .exec()
+                    .await?
+                    .model()
+                    .await?;
 
-        context.database.update_in_check(guild_id, true).await;
-        context
-            .get_interaction_client()
-            .interaction_callback(
-                command.id,
-                &command.token,
-                &InteractionResponse::ChannelMessageWithSource(
-                    CallbackDataBuilder::new()
-                        .embeds(EmbedBuilder::new().color(setting.embed_color).description("Sakura is checking your invites now!").build())
-                        .build()
-                )
-            )
-            .exec()
-            .await?;
+                (ProgressMessage::Channel(message.channel_id, message.id), description)
+            }
+        };
 
-        let known_codes = known_codes.unwrap();
         let Setting { category_channel_ids, ignored_channel_ids, ..} = setting;
+        let scan_depths = context.database.read_channel_scan_depths(guild_id).await?;
         let results_channel_id = setting.results_channel_id.unwrap();
-        let guild_channel_ids = context.cache.guild_channels(guild_id).unwrap();
-        let mut ids: HashMap<Id<ChannelMarker>, HashSet<(Id<ChannelMarker>, Option<Id<MessageMarker>>, i64)>> = HashMap::new();
-        let mut invite_check = InviteCheck::new();
+        let guild_channel_ids = context.cache
+            .guild_channels(guild_id)
+            .ok_or_else(|| CommandError::Internal(format!("guild {guild_id} is not cached")))?;
+        let mut ids: HashMap<Id<ChannelMarker>, HashSet<ChildChannel>> = HashMap::new();
         let minimum_client_permissions = Permissions::READ_MESSAGE_HISTORY | Permissions::VIEW_CHANNEL;
        
         for guild_channel_id in guild_channel_ids.value() {
@@ -304,41 +531,63 @@ impl CheckCommand {
                 None => None,
             })
             .collect();
-        sorted_categories.sort_by(|a , b| a.2.cmp(&b.2));
+        sorted_categories.sort_by_key(|category| category.2);
+        let channel_count = sorted_categories
+            .iter()
+            .map(|category| ids.get(&category.0).map_or(0, |children| children.len()))
+            .sum();
+        let mut progress = CheckProgress::new(progress_message, setting.embed_color, progress_description, sorted_categories.len(), channel_count);
+        let mut is_cancelled = false;
+
+        for (position, sorted_category) in sorted_categories.into_iter().enumerate() {
+            if context.shutdown.is_check_cancelled(guild_id) {
+                is_cancelled = true;
+                break
+            }
 
-        for sorted_category in sorted_categories {
-            let mut category_result = CategoryResult::new(sorted_category.1);
+            let mut category_result = CategoryResult::new(sorted_category.0, sorted_category.1);
             let children = ids.get(&sorted_category.0);
 
             if children.is_none() {
                 context
                     .client
                     .create_message(results_channel_id)
-                    .embeds(&[category_result.embed(setting.embed_color)])?
+                    .embeds(&[category_result.embed(setting.embed_color, Utc::now().naive_utc())?])?
                     .exec()
                     .await?;
-                invite_check.category_results.push(category_result);
+                context.database.create_category_result(run_id, position, category_result).await?;
+                progress.categories_done += 1;
+                progress.update(&context).await;
                 continue
             }
 
-            let mut sorted_children: Vec<(Id<ChannelMarker>, Option<Id<MessageMarker>>, i64)> = Vec::from_iter(children.unwrap().to_owned());
-            sorted_children.sort_by(|a, b| a.2.cmp(&b.2));
+            let mut sorted_children: Vec<ChildChannel> = Vec::from_iter(children.unwrap().to_owned());
+            sorted_children.sort_by_key(|child| child.2);
 
             for (channel_id, last_message_id, ..) in sorted_children {
+                if context.shutdown.is_check_cancelled(guild_id) {
+                    is_cancelled = true;
+                    break
+                }
+
+                progress.update(&context).await;
+                // Counted as it starts since several checks below skip ahead, the next update runs once it's done
+                progress.channels_done += 1;
+
                 let mut channel_result = ChannelResult::new(channel_id);
                 let channel_reference = match context.cache.guild_channel(channel_id) {
                     Some(channel) => channel,
                     None => {
-                        category_result.issues += 1;
+                        category_result.issue_channel_ids.push(channel_id);
                         continue
                     }
                 };
                 let channel = channel_reference.value().resource();
                 
-                match context.cache.permissions().in_channel(CLIENT_ID.clone(), channel_id) {
+                match context.cache.permissions().in_channel(context.client_id, channel_id) {
                     Ok(permissions) if permissions.contains(minimum_client_permissions) => {},
                     _ => {
-                        category_result.manual.push(channel.id());
+                        category_result.manual_channel_ids.push(channel.id());
                         continue
                     }
                 };
@@ -348,86 +597,451 @@ impl CheckCommand {
                     continue
                 }
 
-                let mut request = context.client.channel_messages(channel_id).limit(15).unwrap().exec();
-                let context_clone = context.clone();
-
-                request.set_pre_flight(Box::new(move || {
-                    match context_clone.cache.channel_messages(channel_id) {
-                        Some(message_ids) => message_ids.count() < 15,
-                        None => true,
-                    }
-                }));
-
-                let messages = match request.await?.models().await {
-                    Ok(messages) => messages,
-                    _ => {
-                        category_result.manual.push(channel.id());
+                let scan_depth = scan_depths.get(&channel_id).copied().unwrap_or(setting.scan_depth);
+                let messages = match read_messages(&context, channel_id, scan_depth).await? {
+                    Some(messages) => messages,
+                    None => {
+                        category_result.manual_channel_ids.push(channel.id());
                         continue
                     }
                 };
+
+                channel_result.messages = messages.len() as u32;
                 let mut codes: HashSet<String> = HashSet::new();
+                let mut occurrences = vec![];
 
                 for message in messages {
-                    let extracted = extract_codes_from_message(message);
-                    codes.extend(extracted);
+                    let message_id = message.id;
+                    let mut extracted = Vec::from_iter(extract_codes_from_message(message));
+
+                    extracted.sort();
+                    codes.extend(extracted.iter().cloned());
+                    occurrences.extend(extracted.into_iter().map(|code| (message_id, code)));
                 }
 
+                let mut lookups = vec![];
+                let mut lookup_codes = vec![];
+                // Each code's status, permanence and expiry, shared by every message it appears in
+                let mut resolved: HashMap<String, (InviteStatus, Option<bool>, Option<NaiveDateTime>)> = HashMap::new();
+
                 for code in codes {
                     match known_codes.get(&code) {
                         Some(known_code) if known_code.is_checked => {
                             let is_expired_code = match known_code.expires_at {
-                                Some(ndt) => ndt.timestamp_millis() <= now.timestamp_millis(),
+                                Some(ndt) => ndt.and_utc().timestamp_millis() <= now.timestamp_millis(),
                                 None => false,
                             };
-
-                            if known_code.is_valid.unwrap() && (known_code.is_permanent.unwrap() || !is_expired_code) {
-                                channel_result.good += 1;
+                            let status = if known_code.is_valid == Some(true) && (known_code.is_permanent == Some(true) || !is_expired_code) {
+                                InviteStatus::Valid
                             } else {
-                                channel_result.bad += 1;
-                            }
-        
-                        },
-                        _ => {
-                            let (expires_at, is_permanent, is_valid) = match context.client.invite(&code).with_expiration().exec().await {
-                                Ok(response) => {
-                                    let invite = response.model().await.unwrap();
-    
-                                    channel_result.good += 1;
-                                    (invite.expires_at, invite.expires_at.is_none() && invite.max_age.is_none() && invite.max_uses.is_none(), true)
-                                },
-                                Err(_) => {
-                                    channel_result.bad += 1;
-                                    (None, false, false)
-                                },
+                                InviteStatus::Invalid
                             };
 
-                            context.database.upsert_code(guild_id, code, expires_at, is_permanent, is_valid).await;
+                            resolved.insert(code, (status, known_code.is_permanent, known_code.expires_at));
                         },
+                        _ => {
+                            lookup_codes.push(code.clone());
+                            lookups.push(context.resolver.check(context.database.as_ref(), guild_id, code, Priority::Interactive));
+                        }
+                    }
+                }
+
+                for (code, outcome) in lookup_codes.into_iter().zip(future::join_all(lookups).await) {
+                    let resolution = match outcome? {
+                        InviteOutcome::Valid { expires_at, is_permanent } => (
+                            InviteStatus::Valid,
+                            Some(is_permanent),
+                            expires_at.and_then(|expires_at| DateTime::from_timestamp(expires_at.as_secs(), 0)).map(|expires_at| expires_at.naive_utc())
+                        ),
+                        InviteOutcome::Unknown => (InviteStatus::Invalid, None, None),
+                        InviteOutcome::RateLimited { .. } | InviteOutcome::Transient => (InviteStatus::Unresolved, None, None)
+                    };
+
+                    resolved.insert(code, resolution);
+                }
+
+                progress.invites += resolved.len();
+
+                for (status, ..) in resolved.values() {
+                    match status {
+                        InviteStatus::Valid => channel_result.good += 1,
+                        InviteStatus::Invalid => channel_result.bad += 1,
+                        InviteStatus::Unresolved => channel_result.unresolved += 1
                     }
                 }
 
+                channel_result.invites = occurrences
+                    .into_iter()
+                    .filter_map(|(message_id, code)| {
+                        let (status, is_permanent, expires_at) = *resolved.get(&code)?;
+
+                        Some(InviteResult { message_id, code, status, is_permanent, expires_at })
+                    })
+                    .collect();
                 category_result.channel_results.push(channel_result);
             }
             
             context
                 .client
                 .create_message(results_channel_id)
-                .embeds(&[category_result.embed(setting.embed_color)])?
+                .embeds(&[category_result.embed(setting.embed_color, Utc::now().naive_utc())?])?
                 .exec()
                 .await?;
-            invite_check.category_results.push(category_result);
+            context.database.create_category_result(run_id, position, category_result).await?;
+
+            if is_cancelled {
+                break
+            }
+
+            progress.categories_done += 1;
+            progress.update(&context).await;
         }
 
+        let status = if is_cancelled { CheckStatus::Cancelled } else { CheckStatus::Completed };
+
+        context.database.finish_check_run(run_id, status).await?;
+
+        let run = context.database
+            .read_check_run(guild_id, run_id)
+            .await?
+            .ok_or_else(|| CommandError::Internal(format!("check run {run_id} disappeared")))?;
+        let category_results = context.database.read_category_results(run_id).await?;
+        // A partial run would report every channel it didn't reach as removed
+        let diff = if is_cancelled { None } else { read_diff(&context, &run, &category_results).await? };
+
         context
             .client
             .create_message(results_channel_id)
-            .embeds(&[invite_check.end_and_show_results(setting.embed_color)])?
+            .embeds(&[summary_embed(&run, &category_results, diff.as_ref(), setting.embed_color)?])?
             .exec()
             .await?;
+        progress.finish(&context, status).await;
 
-        context.database.update_last_check(guild_id).await;
-        context.database.update_in_check(guild_id, false).await;
+        if is_exported {
+            let files = export::files(&run, &category_results);
+            let attachments = files
+                .iter()
+                .map(|(filename, content)| AttachmentFile::from_bytes(filename, content.as_bytes()))
+                .collect::<Vec<AttachmentFile>>();
 
-        Ok(())
+            context.client.create_message(results_channel_id).attach(&attachments).exec().await?;
+        }
+
+        // Cancelled checks leave the cooldown unused, so the guild can start over right away
+        if !is_cancelled {
+            context.database.update_last_check(guild_id).await?;
+        }
+
+        Ok(status)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        commands::{cancel_check::CancelCheckCommand, error::{self, CommandError}},
+        database::check::CheckStatus,
+        testing::{self, discord::{InviteState, MockDiscord}, CATEGORY_ID, GUILD_ID, OWNER_ID, PARTNER_CHANNEL_IDS, RESULTS_CHANNEL_ID}
+    };
+    use dashmap::DashSet;
+    use serde_json::json;
+    use std::time::Duration;
+    use twilight_model::id::Id;
+    use super::{CheckCommand, CheckProgress, ProgressMessage};
+
+    #[tokio::test]
+    async fn check_reports_each_channel_and_records_conclusive_lookups() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+
+        context.database.create_setting(GUILD_ID).await.unwrap();
+        context.database.update_results_channel_id(GUILD_ID, Some(RESULTS_CHANNEL_ID)).await.unwrap();
+        context.database.update_category_channel_ids(GUILD_ID, DashSet::from_iter([CATEGORY_ID])).await.unwrap();
+        mock.set_messages(PARTNER_CHANNEL_IDS[0], &["Join us at discord.gg/good and discord.gg/gone"]);
+        mock.set_messages(PARTNER_CHANNEL_IDS[1], &["https://discord.com/invite/flaky"]);
+        mock.set_invite("good", InviteState::permanent());
+        mock.set_invite("gone", InviteState::Unknown);
+        mock.set_invite("flaky", InviteState::ServerError);
+
+        let command = testing::command("check", RESULTS_CHANNEL_ID, json!([]));
+        let token = command.token.clone();
+
+        CheckCommand::run(command, context.clone()).await.unwrap();
+
+        let callbacks = mock.callbacks();
+        assert_eq!(callbacks.len(), 1);
+        assert_eq!(callbacks[0]["data"]["embeds"][0]["description"], "Sakura is checking your invites now!");
+
+        let progress = &mock.edited_original(&token).unwrap()["embeds"][0];
+        assert_eq!(progress["description"], "Sakura finished this invite check. The results are below.");
+        assert_eq!(progress["fields"][0]["value"], "1/1");
+        assert_eq!(progress["fields"][1]["value"], "2/2");
+        assert_eq!(progress["fields"][2]["value"], "3");
+
+        let messages = mock.created_messages(RESULTS_CHANNEL_ID);
+        assert_eq!(messages.len(), 2);
+
+        let category = messages[0]["embeds"][0]["description"].as_str().unwrap();
+        assert!(category.contains("🔴 <#301> - **2** total (**1** bad)"), "{category}");
+        assert!(category.contains("🟡 <#302> - **1** total (**1** could not be verified)"), "{category}");
+
+        let stats = messages[1]["embeds"][0]["fields"][1]["value"].as_str().unwrap();
+        assert!(stats.contains("- **1** invite(s) could not be verified"), "{stats}");
+
+        let invites = context.database.read_guild_invites(GUILD_ID).await.unwrap();
+        assert_eq!(invites["good"].is_valid, Some(true));
+        assert_eq!(invites["good"].is_permanent, Some(true));
+        assert_eq!(invites["gone"].is_valid, Some(false));
+        assert!(!invites.contains_key("flaky"));
+
+        let setting = context.database.read_setting(GUILD_ID).await.unwrap().unwrap();
+        assert!(!setting.in_check);
+        assert!(setting.last_check.is_some());
+
+        let run = context.database.read_check_run(GUILD_ID, 1).await.unwrap().unwrap();
+        assert_eq!(run.status, CheckStatus::Completed);
+        assert_eq!(run.user_id, OWNER_ID);
+        assert!(run.finished_at.is_some());
+
+        let category_results = context.database.read_category_results(1).await.unwrap();
+        assert_eq!(category_results.len(), 1);
+        assert_eq!(category_results[0].category_id, CATEGORY_ID);
+        assert_eq!(category_results[0].channel_results.len(), 2);
+        assert_eq!((category_results[0].channel_results[0].good, category_results[0].channel_results[0].bad), (1, 1));
+        assert_eq!(category_results[0].channel_results[1].unresolved, 1);
+    }
+
+    #[tokio::test]
+    async fn check_leaves_channels_it_cannot_read_for_a_manual_check() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+
+        context.database.create_setting(GUILD_ID).await.unwrap();
+        context.database.update_results_channel_id(GUILD_ID, Some(RESULTS_CHANNEL_ID)).await.unwrap();
+        context.database.update_category_channel_ids(GUILD_ID, DashSet::from_iter([CATEGORY_ID])).await.unwrap();
+        mock.set_messages(PARTNER_CHANNEL_IDS[0], &["discord.gg/good"]);
+        mock.set_invite("good", InviteState::permanent());
+        mock.forbid_messages(PARTNER_CHANNEL_IDS[1]);
+
+        CheckCommand::run(testing::command("check", RESULTS_CHANNEL_ID, json!([])), context.clone()).await.unwrap();
+
+        let category_results = context.database.read_category_results(1).await.unwrap();
+        assert_eq!(category_results[0].channel_results.len(), 1);
+        assert_eq!(category_results[0].manual_channel_ids, vec![PARTNER_CHANNEL_IDS[1]]);
+        assert_eq!(context.database.read_check_run(GUILD_ID, 1).await.unwrap().unwrap().status, CheckStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn check_reports_invites_that_expired_since_the_previous_check() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+
+        context.database.create_setting(GUILD_ID).await.unwrap();
+        context.database.update_results_channel_id(GUILD_ID, Some(RESULTS_CHANNEL_ID)).await.unwrap();
+        context.database.update_category_channel_ids(GUILD_ID, DashSet::from_iter([CATEGORY_ID])).await.unwrap();
+        mock.set_messages(PARTNER_CHANNEL_IDS[0], &["discord.gg/good"]);
+        mock.set_invite("good", InviteState::permanent());
+
+        CheckCommand::run(testing::command("check", RESULTS_CHANNEL_ID, json!([])), context.clone()).await.unwrap();
+        context.database.upsert_code(GUILD_ID, "good".to_string(), None, false, false).await.unwrap();
+        context.database.recover_check(GUILD_ID, true).await.unwrap();
+        CheckCommand::run(testing::command("check", RESULTS_CHANNEL_ID, json!([])), context.clone()).await.unwrap();
+
+        let messages = mock.created_messages(RESULTS_CHANNEL_ID);
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[1]["embeds"][0]["fields"].as_array().unwrap().len(), 2);
+
+        let changes = &messages[3]["embeds"][0]["fields"][2];
+        assert_eq!(changes["name"], "Changes since check #1");
+        assert_eq!(changes["value"], "- **Now has bad invites:** <#301>\n- **Expired invites:** `good` in <#301>");
+    }
+
+    #[tokio::test]
+    async fn check_attaches_an_export_with_one_row_per_invite_occurrence() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+
+        context.database.create_setting(GUILD_ID).await.unwrap();
+        context.database.update_results_channel_id(GUILD_ID, Some(RESULTS_CHANNEL_ID)).await.unwrap();
+        context.database.update_category_channel_ids(GUILD_ID, DashSet::from_iter([CATEGORY_ID])).await.unwrap();
+        mock.set_messages(PARTNER_CHANNEL_IDS[0], &["discord.gg/good", "discord.gg/good and discord.gg/gone"]);
+        mock.set_invite("good", InviteState::permanent());
+        mock.set_invite("gone", InviteState::Unknown);
+
+        CheckCommand::run(
+            testing::command("check", RESULTS_CHANNEL_ID, json!([{ "name": "export", "type": 5, "value": true }])),
+            context.clone()
+        ).await.unwrap();
+
+        let messages = mock.created_messages(RESULTS_CHANNEL_ID);
+        assert_eq!(messages.len(), 3);
+
+        let files = messages[2]["files"].as_array().unwrap();
+        assert_eq!(files[0]["filename"], "sakura-check-1.json");
+        assert_eq!(files[1]["filename"], "sakura-check-1.csv");
+
+        let export: serde_json::Value = serde_json::from_str(files[0]["content"].as_str().unwrap()).unwrap();
+        assert_eq!(export["invites"].as_array().unwrap().len(), 3);
+
+        let csv = files[1]["content"].as_str().unwrap();
+        assert_eq!(csv.lines().count(), 4);
+        assert_eq!(csv.lines().filter(|line| line.contains(",gone,invalid,")).count(), 1);
+    }
+
+    #[tokio::test]
+    async fn check_pages_back_to_each_channels_scan_depth() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+        let mut history = vec!["nothing to see here"; 150];
+
+        history[119] = "discord.gg/deep";
+        history[120] = "discord.gg/deeper";
+        context.database.create_setting(GUILD_ID).await.unwrap();
+        context.database.update_results_channel_id(GUILD_ID, Some(RESULTS_CHANNEL_ID)).await.unwrap();
+        context.database.update_category_channel_ids(GUILD_ID, DashSet::from_iter([CATEGORY_ID])).await.unwrap();
+        context.database.update_scan_depth(GUILD_ID, 20).await.unwrap();
+        context.database.update_channel_scan_depth(GUILD_ID, PARTNER_CHANNEL_IDS[0], Some(120)).await.unwrap();
+        mock.set_messages(PARTNER_CHANNEL_IDS[0], &history);
+        mock.set_messages(PARTNER_CHANNEL_IDS[1], &history);
+        mock.set_invite("deep", InviteState::permanent());
+        mock.set_invite("deeper", InviteState::permanent());
+
+        CheckCommand::run(testing::command("check", RESULTS_CHANNEL_ID, json!([])), context.clone()).await.unwrap();
+
+        let messages = mock.created_messages(RESULTS_CHANNEL_ID);
+        let category = messages[0]["embeds"][0]["description"].as_str().unwrap();
+
+        assert!(category.contains("🟢 <#301> - **1** total"), "{category}");
+        assert!(category.contains("🟢 <#302> - **0** total"), "{category}");
+        assert_eq!(messages[0]["embeds"][0]["footer"]["text"], "Checked 140 messages");
+        assert_eq!(mock.invite_lookups("deeper"), 0);
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_the_check_after_the_current_channel_without_using_the_cooldown() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+
+        context.database.create_setting(GUILD_ID).await.unwrap();
+        context.database.update_results_channel_id(GUILD_ID, Some(RESULTS_CHANNEL_ID)).await.unwrap();
+        context.database.update_category_channel_ids(GUILD_ID, DashSet::from_iter([CATEGORY_ID])).await.unwrap();
+        mock.set_messages(PARTNER_CHANNEL_IDS[0], &["discord.gg/good"]);
+        mock.set_messages(PARTNER_CHANNEL_IDS[1], &["discord.gg/later"]);
+        mock.set_invite("good", InviteState::permanent());
+        mock.set_invite("later", InviteState::permanent());
+
+        let gate = mock.hold_messages(PARTNER_CHANNEL_IDS[0]);
+        let command = testing::command("check", RESULTS_CHANNEL_ID, json!([]));
+        let token = command.token.clone();
+        let check = tokio::spawn(CheckCommand::run(command, context.clone()));
+
+        while mock.callbacks().is_empty() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        CancelCheckCommand::run(testing::command("cancel-check", RESULTS_CHANNEL_ID, json!([])), context.clone()).await.unwrap();
+        gate.add_permits(1);
+        check.await.unwrap().unwrap();
+
+        let callbacks = mock.callbacks();
+        assert_eq!(callbacks[1]["data"]["embeds"][0]["description"], "Sakura will stop the invite check after the channel it is on. Your cooldown will not be used.");
+
+        let messages = mock.created_messages(RESULTS_CHANNEL_ID);
+        assert_eq!(messages.len(), 2);
+
+        let category = messages[0]["embeds"][0]["description"].as_str().unwrap();
+        assert!(category.contains("🟢 <#301> - **1** total"), "{category}");
+        assert!(!category.contains("<#302>"), "{category}");
+        assert_eq!(messages[1]["embeds"][0]["title"], "Invite check results (cancelled)");
+        assert_eq!(mock.invite_lookups("later"), 0);
+
+        let progress = &mock.edited_original(&token).unwrap()["embeds"][0];
+        assert_eq!(progress["description"], "Sakura stopped this invite check early. The partial results are below.");
+        assert_eq!(progress["fields"][1]["value"], "1/2");
+
+        let setting = context.database.read_setting(GUILD_ID).await.unwrap().unwrap();
+        assert!(!setting.in_check);
+        assert!(setting.last_check.is_none());
+        assert_eq!(context.database.read_check_run(GUILD_ID, 1).await.unwrap().unwrap().status, CheckStatus::Cancelled);
+
+        let error = CancelCheckCommand::run(testing::command("cancel-check", RESULTS_CHANNEL_ID, json!([])), context.clone()).await.unwrap_err();
+
+        assert!(matches!(error, CommandError::Validation(_)));
+    }
+
+    #[test]
+    fn progress_estimates_the_rest_of_the_check_from_its_rate() {
+        let mut progress = CheckProgress::new(ProgressMessage::Interaction("token".to_string()), 0xF8F8FF, "Sakura is checking your invites now!", 4, 40);
+
+        assert_eq!(progress.embed(Duration::ZERO, None).unwrap().fields[4].value, "Calculating...");
+
+        progress.channels_done = 10;
+
+        let embed = progress.embed(Duration::from_secs(60), None).unwrap();
+        assert_eq!(embed.fields[1].value, "10/40");
+        assert_eq!(embed.fields[3].value, "10.0 channels/min");
+        assert_eq!(embed.fields[4].value, "3m");
+    }
+
+    #[tokio::test]
+    async fn check_does_not_start_when_another_check_took_the_lock_after_prepare() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+
+        context.database.create_setting(GUILD_ID).await.unwrap();
+        context.database.update_results_channel_id(GUILD_ID, Some(RESULTS_CHANNEL_ID)).await.unwrap();
+        context.database.update_category_channel_ids(GUILD_ID, DashSet::from_iter([CATEGORY_ID])).await.unwrap();
+
+        let setting = context.database.read_setting(GUILD_ID).await.unwrap().unwrap();
+        let known_codes = CheckCommand::prepare(&context, &setting, None).await.unwrap();
+
+        assert!(context.database.update_in_check(GUILD_ID, true).await.unwrap());
+
+        let error = CheckCommand::start(context.clone(), setting, known_codes, Id::new(OWNER_ID), None, false).await.unwrap_err();
+
+        assert!(matches!(error, CommandError::Validation(_)));
+        assert!(context.database.read_setting(GUILD_ID).await.unwrap().unwrap().in_check);
+        assert!(context.database.read_check_run(GUILD_ID, 1).await.unwrap().is_none());
+        assert!(mock.created_messages(RESULTS_CHANNEL_ID).is_empty());
+    }
+
+    #[tokio::test]
+    async fn check_clears_in_check_when_it_fails_midway() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+
+        context.database.create_setting(GUILD_ID).await.unwrap();
+        context.database.update_results_channel_id(GUILD_ID, Some(RESULTS_CHANNEL_ID)).await.unwrap();
+        context.database.update_category_channel_ids(GUILD_ID, DashSet::from_iter([CATEGORY_ID])).await.unwrap();
+        mock.fail_message_creation(true);
+
+        let error = CheckCommand::run(testing::command("check", RESULTS_CHANNEL_ID, json!([])), context.clone()).await.unwrap_err();
+
+        assert!(matches!(error, CommandError::Http(_)));
+
+        let setting = context.database.read_setting(GUILD_ID).await.unwrap().unwrap();
+        assert!(!setting.in_check);
+        assert!(setting.last_check.is_none());
+        assert_eq!(context.database.read_check_run(GUILD_ID, 1).await.unwrap().unwrap().status, CheckStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn check_refuses_to_run_outside_the_results_channel() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+
+        context.database.create_setting(GUILD_ID).await.unwrap();
+        context.database.update_results_channel_id(GUILD_ID, Some(RESULTS_CHANNEL_ID)).await.unwrap();
+        context.database.update_category_channel_ids(GUILD_ID, DashSet::from_iter([CATEGORY_ID])).await.unwrap();
+
+        let command = testing::command("check", PARTNER_CHANNEL_IDS[0], json!([]));
+        let error = CheckCommand::run(command.clone(), context.clone()).await.unwrap_err();
+
+        assert!(matches!(error, CommandError::Validation(_)));
+        error::report(&context, command.id, &command.token, &error).await;
+
+        let callbacks = mock.callbacks();
+        assert_eq!(callbacks[0]["data"]["embeds"][0]["description"], "This command can only be run in <#200>.");
+        assert_eq!(callbacks[0]["data"]["flags"], 64);
+        assert!(mock.created_messages(RESULTS_CHANNEL_ID).is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{database::StorageError, util::context::Context};
+use std::{error::Error, fmt};
+use tracing::warn;
+use twilight_embed_builder::{EmbedBuilder, EmbedError};
+use twilight_http::{response::DeserializeBodyError, Error as HttpError};
+use twilight_interactions::error::ParseError;
+use twilight_model::{
+    application::callback::InteractionResponse,
+    channel::message::MessageFlags,
+    datetime::TimestampParseError,
+    id::{Id, marker::InteractionMarker}
+};
+use twilight_util::builder::CallbackDataBuilder;
+use twilight_validate::{message::MessageValidationError, request::ValidationError};
+
+pub const SHUTDOWN_MESSAGE: &str = "Sakura is restarting. Please try again in a minute.";
+
+#[derive(Debug)]
+pub enum CommandError {
+    Database(StorageError),
+    Http(Box<HttpError>),
+    // Something Sakura itself built or parsed was malformed, like an embed or a response body
+    Internal(String),
+    Permission(String),
+    Validation(String)
+}
+
+impl CommandError {
+    // Permission and validation messages are written for the user, everything else gets a generic explanation
+    pub fn user_message(&self) -> String {
+        match self {
+            Self::Database(_) => "Sakura could not reach its database. Please try again in a moment.".to_string(),
+            Self::Http(_) => "Discord did not accept a request from Sakura. Please try again in a moment.".to_string(),
+            Self::Internal(_) => "Something went wrong while running this command.".to_string(),
+            Self::Permission(message) | Self::Validation(message) => message.clone()
+        }
+    }
+
+    pub fn is_user_error(&self) -> bool {
+        matches!(self, Self::Permission(_) | Self::Validation(_))
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Database(error) => write!(f, "database error: {error}"),
+            Self::Http(error) => write!(f, "discord http error: {error}"),
+            Self::Internal(message) => write!(f, "internal error: {message}"),
+            Self::Permission(message) => write!(f, "permission denied: {message}"),
+            Self::Validation(message) => write!(f, "invalid command usage: {message}")
+        }
+    }
+}
+
+impl Error for CommandError {}
+
+impl From<StorageError> for CommandError {
+    fn from(error: StorageError) -> Self {
+        Self::Database(error)
+    }
+}
+
+impl From<HttpError> for CommandError {
+    fn from(error: HttpError) -> Self {
+        Self::Http(Box::new(error))
+    }
+}
+
+impl From<DeserializeBodyError> for CommandError {
+    fn from(error: DeserializeBodyError) -> Self {
+        Self::Internal(error.to_string())
+    }
+}
+
+impl From<EmbedError> for CommandError {
+    fn from(error: EmbedError) -> Self {
+        Self::Internal(error.to_string())
+    }
+}
+
+impl From<MessageValidationError> for CommandError {
+    fn from(error: MessageValidationError) -> Self {
+        Self::Internal(error.to_string())
+    }
+}
+
+impl From<ParseError> for CommandError {
+    fn from(error: ParseError) -> Self {
+        Self::Internal(error.to_string())
+    }
+}
+
+impl From<TimestampParseError> for CommandError {
+    fn from(error: TimestampParseError) -> Self {
+        Self::Internal(error.to_string())
+    }
+}
+
+impl From<ValidationError> for CommandError {
+    fn from(error: ValidationError) -> Self {
+        Self::Internal(error.to_string())
+    }
+}
+
+// Shows the error to whoever ran the command (the caller logs it), using whichever kind of response the interaction still accepts
+pub async fn report(context: &Context, interaction_id: Id<InteractionMarker>, token: &str, error: &CommandError) {
+    let embed = match EmbedBuilder::new().color(0xF8F8FF).description(error.user_message()).build() {
+        Ok(embed) => embed,
+        Err(_) => return
+    };
+    let interaction_client = context.get_interaction_client();
+    let callback = interaction_client
+        .interaction_callback(
+            interaction_id,
+            token,
+            &InteractionResponse::ChannelMessageWithSource(
+                CallbackDataBuilder::new()
+                    .embeds([embed.clone()])
+                    .flags(MessageFlags::EPHEMERAL)
+                    .build()
+            )
+        )
+        .exec()
+        .await;
+
+    if callback.is_ok() {
+        return
+    }
+
+    // The interaction was already acknowledged, so either fill in the deferred response or follow up on the real one
+    let is_deferred = match interaction_client.get_interaction_original(token).exec().await {
+        Ok(response) => match response.model().await {
+            Ok(message) => message.flags.is_some_and(|flags| flags.contains(MessageFlags::LOADING)),
+            Err(_) => false
+        },
+        Err(_) => false
+    };
+    let embeds = [embed];
+    let result = if is_deferred {
+        match interaction_client.update_interaction_original(token).embeds(Some(&embeds)) {
+            Ok(request) => request.exec().await.map(|_| ()),
+            Err(_) => return
+        }
+    } else {
+        match interaction_client.create_followup_message(token).embeds(&embeds) {
+            Ok(request) => request.ephemeral(true).exec().await.map(|_| ()),
+            Err(_) => return
+        }
+    };
+
+    if let Err(error) = result {
+        warn!(%error, "could not report a command error");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{self, discord::MockDiscord, RESULTS_CHANNEL_ID};
+    use serde_json::json;
+    use twilight_model::application::callback::InteractionResponse;
+    use twilight_util::builder::CallbackDataBuilder;
+    use super::{report, CommandError};
+
+    #[tokio::test]
+    async fn errors_use_whichever_response_the_interaction_still_accepts() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+        let command = testing::command("settings", RESULTS_CHANNEL_ID, json!([]));
+        let error = CommandError::Validation("Nope.".to_string());
+
+        report(&context, command.id, "fresh", &error).await;
+
+        let callbacks = mock.callbacks();
+        assert_eq!(callbacks[0]["data"]["embeds"][0]["description"], "Nope.");
+        assert_eq!(callbacks[0]["data"]["flags"], 64);
+
+        context
+            .get_interaction_client()
+            .interaction_callback(command.id, "deferred", &InteractionResponse::DeferredChannelMessageWithSource(CallbackDataBuilder::new().build()))
+            .exec()
+            .await
+            .unwrap();
+        report(&context, command.id, "deferred", &CommandError::Internal("broken".to_string())).await;
+
+        let original = mock.edited_original("deferred").unwrap();
+        assert_eq!(original["embeds"][0]["description"], "Something went wrong while running this command.");
+
+        context
+            .get_interaction_client()
+            .interaction_callback(command.id, "responded", &InteractionResponse::ChannelMessageWithSource(CallbackDataBuilder::new().content("Hi".to_string()).build()))
+            .exec()
+            .await
+            .unwrap();
+        report(&context, command.id, "responded", &error).await;
+
+        let followups = mock.followups();
+        assert_eq!(followups.len(), 1);
+        assert_eq!(followups[0]["embeds"][0]["description"], "Nope.");
+        assert_eq!(followups[0]["flags"], 64);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{
+    commands::{check::{read_diff, summary_embed}, error::CommandError},
+    database::check::{CheckRun, CheckStatus, CheckTotals},
+    util::{context::Context, export}
+};
+use std::sync::Arc;
+use twilight_embed_builder::{EmbedBuilder, EmbedFooterBuilder};
+use twilight_http::request::AttachmentFile;
+use twilight_interactions::command::{CommandModel, CreateCommand};
+use twilight_model::{
+    application::{
+        callback::{CallbackData, InteractionResponse},
+        component::{button::ButtonStyle, ActionRow, Button, Component},
+        interaction::{ApplicationCommand, MessageComponentInteraction}
+    },
+    id::{Id, marker::GuildMarker}
+};
+use twilight_util::builder::CallbackDataBuilder;
+
+const HISTORY_LENGTH: u16 = 10;
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    desc = "Shows past invite checks",
+    name = "history"
+)]
+pub struct HistoryCommand {
+    #[command(desc = "The number of a check to show in full", min_value = 1)]
+    check: Option<i64>,
+    #[command(desc = "Also attach that check's results as JSON and CSV files")]
+    export: Option<bool>
+}
+
+fn describe_status(status: CheckStatus) -> Option<&'static str> {
+    match status {
+        CheckStatus::Running => Some("still running"),
+        CheckStatus::Completed => None,
+        CheckStatus::Failed => Some("failed"),
+        CheckStatus::Aborted => Some("aborted"),
+        CheckStatus::Cancelled => Some("cancelled")
+    }
+}
+
+fn describe_run(run: &CheckRun, totals: &CheckTotals) -> String {
+    let status = match describe_status(run.status) {
+        Some(status) => format!(" **({status})**"),
+        None => String::new()
+    };
+
+    format!(
+        "**#{}** <t:{}:f> by <@{}> - **{}** channel(s), **{}** invite(s), **{:.2}%** invalid{status}",
+        run.id,
+        run.started_at.and_utc().timestamp(),
+        run.user_id,
+        totals.channels,
+        totals.invites(),
+        totals.invalid_percentage()
+    )
+}
+
+// Page 0 is the run's summary, every page after it is one category
+fn page_buttons(run_id: i64, page: usize, page_count: usize) -> Component {
+    let button = |action: &str, label: &str, target: usize, disabled: bool| Component::Button(Button {
+        custom_id: Some(format!("history:{action}:{run_id}:{target}")),
+        disabled,
+        emoji: None,
+        label: Some(label.to_string()),
+        style: ButtonStyle::Secondary,
+        url: None
+    });
+
+    Component::ActionRow(ActionRow {
+        components: vec![
+            button("previous", "Previous", page.saturating_sub(1), page == 0),
+            button("next", "Next", page + 1, page + 1 >= page_count)
+        ]
+    })
+}
+
+async fn embed_color(context: &Context, guild_id: Id<GuildMarker>) -> Result<u32, CommandError> {
+    Ok(context.database.read_setting(guild_id).await?.map_or(0xF8F8FF, |setting| setting.embed_color))
+}
+
+async fn list(context: &Context, guild_id: Id<GuildMarker>) -> Result<CallbackData, CommandError> {
+    let runs = context.database.read_check_runs(guild_id, HISTORY_LENGTH).await?;
+    let embed = EmbedBuilder::new().color(embed_color(context, guild_id).await?).title("Recent invite checks");
+    let embed = if runs.is_empty() {
+        embed.description("No invite checks have been run in this guild yet.")
+    } else {
+        embed
+            .description(runs.iter().map(|(run, totals)| describe_run(run, totals)).collect::<Vec<String>>().join("\n"))
+            .footer(EmbedFooterBuilder::new("Use /history with a check number to see it in full"))
+    };
+
+    Ok(CallbackDataBuilder::new().embeds([embed.build()?]).build())
+}
+
+async fn page(context: &Context, guild_id: Id<GuildMarker>, run_id: i64, page: usize) -> Result<CallbackData, CommandError> {
+    let run = context.database
+        .read_check_run(guild_id, run_id)
+        .await?
+        .ok_or_else(|| CommandError::Validation(format!("There is no check #{run_id} in this guild.")))?;
+    let category_results = context.database.read_category_results(run_id).await?;
+    let color = embed_color(context, guild_id).await?;
+    let page_count = category_results.len() + 1;
+    let page = page.min(page_count - 1);
+    let embed = match page {
+        0 => summary_embed(&run, &category_results, read_diff(context, &run, &category_results).await?.as_ref(), color)?,
+        page => category_results[page - 1].embed(color, run.finished_at.unwrap_or(run.started_at))?
+    };
+
+    Ok(CallbackDataBuilder::new()
+        .components([page_buttons(run_id, page, page_count)])
+        .content(format!("Check #{run_id} - page {} of {page_count}", page + 1))
+        .embeds([embed])
+        .build())
+}
+
+impl HistoryCommand {
+    pub async fn run(command: ApplicationCommand, context: Arc<Context>) -> Result<(), CommandError> {
+        let guild_id = command.guild_id.unwrap();
+        let options = HistoryCommand::from_interaction(command.data.into())?;
+        let is_exported = options.export.unwrap_or(false);
+        let data = match options.check {
+            Some(run_id) => page(&context, guild_id, run_id, 0).await?,
+            None if is_exported => return Err(CommandError::Validation("Choose a check to export.".to_string())),
+            None => list(&context, guild_id).await?
+        };
+        let client = context.get_interaction_client();
+
+        client
+            .interaction_callback(command.id, &command.token, &InteractionResponse::ChannelMessageWithSource(data))
+            .exec()
+            .await?;
+
+        // Callbacks can't carry files, so the export follows as its own message
+        if let (Some(run_id), true) = (options.check, is_exported) {
+            let run = context.database
+                .read_check_run(guild_id, run_id)
+                .await?
+                .ok_or_else(|| CommandError::Internal(format!("check run {run_id} disappeared")))?;
+            let files = export::files(&run, &context.database.read_category_results(run_id).await?);
+            let attachments = files
+                .iter()
+                .map(|(filename, content)| AttachmentFile::from_bytes(filename, content.as_bytes()))
+                .collect::<Vec<AttachmentFile>>();
+
+            client.create_followup_message(&command.token).attach(&attachments).exec().await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn paginate(component: MessageComponentInteraction, context: Arc<Context>) -> Result<(), CommandError> {
+        let guild_id = component.guild_id.unwrap();
+        let (run_id, target) = match component.data.custom_id.split(':').collect::<Vec<&str>>()[..] {
+            ["history", _, run_id, target] => (run_id.parse::<i64>().ok(), target.parse::<usize>().ok()),
+            _ => (None, None)
+        };
+        let (run_id, target) = run_id
+            .zip(target)
+            .ok_or_else(|| CommandError::Internal(format!("malformed history button \"{}\"", component.data.custom_id)))?;
+        let data = page(&context, guild_id, run_id, target).await?;
+
+        context
+            .get_interaction_client()
+            .interaction_callback(component.id, &component.token, &InteractionResponse::UpdateMessage(data))
+            .exec()
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        database::check::{CategoryResult, ChannelResult, CheckStatus},
+        testing::{self, discord::MockDiscord, CATEGORY_ID, GUILD_ID, OWNER_ID, PARTNER_CHANNEL_IDS, RESULTS_CHANNEL_ID}
+    };
+    use serde_json::json;
+    use twilight_model::id::Id;
+    use super::HistoryCommand;
+
+    #[tokio::test]
+    async fn history_lists_runs_and_pages_through_a_past_run() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+        let mut category_result = CategoryResult::new(CATEGORY_ID, "Partners".to_string());
+        let mut channel_result = ChannelResult::new(PARTNER_CHANNEL_IDS[0]);
+
+        channel_result.good = 3;
+        channel_result.bad = 1;
+        category_result.channel_results.push(channel_result);
+        context.database.create_setting(GUILD_ID).await.unwrap();
+
+        let run_id = context.database.create_check_run(GUILD_ID, Id::new(OWNER_ID)).await.unwrap();
+
+        context.database.create_category_result(run_id, 0, category_result).await.unwrap();
+        context.database.finish_check_run(run_id, CheckStatus::Completed).await.unwrap();
+        context.database.create_check_run(GUILD_ID, Id::new(OWNER_ID)).await.unwrap();
+
+        HistoryCommand::run(testing::command("history", RESULTS_CHANNEL_ID, json!([])), context.clone()).await.unwrap();
+        HistoryCommand::run(
+            testing::command("history", RESULTS_CHANNEL_ID, json!([{ "name": "check", "type": 4, "value": run_id }])),
+            context.clone()
+        ).await.unwrap();
+        HistoryCommand::paginate(testing::component(&format!("history:next:{run_id}:1"), RESULTS_CHANNEL_ID), context.clone()).await.unwrap();
+
+        let callbacks = mock.callbacks();
+        let list = callbacks[0]["data"]["embeds"][0]["description"].as_str().unwrap();
+        let lines = list.lines().collect::<Vec<&str>>();
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("(still running)"), "{list}");
+        assert!(lines[1].contains("**1** channel(s), **4** invite(s), **25.00%** invalid"), "{list}");
+
+        assert_eq!(callbacks[1]["type"], 4);
+        assert_eq!(callbacks[1]["data"]["embeds"][0]["title"], "Invite check results");
+        assert_eq!(callbacks[1]["data"]["components"][0]["components"][0]["disabled"], true);
+        assert_eq!(callbacks[1]["data"]["components"][0]["components"][1]["custom_id"], format!("history:next:{run_id}:1"));
+
+        assert_eq!(callbacks[2]["type"], 7);
+        assert_eq!(callbacks[2]["data"]["content"], format!("Check #{run_id} - page 2 of 2"));
+        assert!(callbacks[2]["data"]["embeds"][0]["description"].as_str().unwrap().contains("🔴 <#301> - **4** total (**1** bad)"));
+        assert_eq!(callbacks[2]["data"]["components"][0]["components"][1]["disabled"], true);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::util::context::Context;
+use crate::{
+    commands::error::CommandError,
+    util::context::Context
+};
 use std::sync::Arc;
 use twilight_embed_builder::EmbedBuilder; 
 use twilight_interactions::command::{CommandModel, CreateCommand};
@@ -35,10 +38,10 @@ pub struct IgnoreRemove {
 }
 
 impl IgnoreCommand {
-    pub async fn run(command: ApplicationCommand, context: Arc<Context>) {
+    pub async fn run(command: ApplicationCommand, context: Arc<Context>) -> Result<(), CommandError> {
         let guild_id = command.guild_id.unwrap();
-        let options = IgnoreCommand::from_interaction(command.data.into()).unwrap();
-        let ignored_channel_ids = context.database.read_ignored_channel_ids(guild_id).await;
+        let options = IgnoreCommand::from_interaction(command.data.into())?;
+        let ignored_channel_ids = context.database.read_ignored_channel_ids(guild_id).await?;
         let mut embed = EmbedBuilder::new().color(0xF8F8FF);
     
         embed = match options {
@@ -47,7 +50,7 @@ impl IgnoreCommand {
                     embed.description("This channel is already ignored.")
                 } else {
                     ignored_channel_ids.insert(channel.id);
-                    context.database.update_ignored_channel_ids(guild_id, ignored_channel_ids).await;
+                    context.database.update_ignored_channel_ids(guild_id, ignored_channel_ids).await?;
                     embed.description(format!("<#{}> will now be ignored during invite checks.", channel.id))
                 }
             },
@@ -56,7 +59,7 @@ impl IgnoreCommand {
                     embed.description("This channel is not in the \"ignored\" list.")
                 } else {
                     ignored_channel_ids.remove(&channel.id);
-                    context.database.update_ignored_channel_ids(guild_id, ignored_channel_ids).await;
+                    context.database.update_ignored_channel_ids(guild_id, ignored_channel_ids).await?;
                     embed.description(format!("<#{}> will no longer be ignored during invite checks.", channel.id))
                 }
             },
@@ -72,7 +75,8 @@ impl IgnoreCommand {
                 )
             )
             .exec()
-            .await
-            .unwrap();
+            .await?;
+
+        Ok(())
     }
 }
\ No newline at end of file
@@ -1,15 +1,25 @@
+pub mod access;
+pub mod cancel_check;
 pub mod category;
 pub mod check;
+pub mod error;
+pub mod history;
 pub mod ignore;
 pub mod ping;
+pub mod registry;
+pub mod reset_cooldown;
 pub mod set;
 pub mod settings;
 pub mod stats;
 
+pub use access::AccessCommand;
+pub use cancel_check::CancelCheckCommand;
 pub use category::CategoryCommand;
 pub use check::CheckCommand;
+pub use history::HistoryCommand;
 pub use ignore::IgnoreCommand;
 pub use ping::PingCommand;
+pub use reset_cooldown::ResetCooldownCommand;
 pub use set::SetCommand;
 pub use settings::SettingsCommand;
 pub use stats::StatsCommand;
\ No newline at end of file
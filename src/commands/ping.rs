@@ -1,4 +1,7 @@
-use crate::util::{context::Context, random::{get_shard_id, snowflake_to_ms}};
+use crate::{
+    commands::error::CommandError,
+    util::{context::Context, random::{get_shard_id, snowflake_to_ms}}
+};
 use std::sync::Arc;
 use twilight_embed_builder::{EmbedBuilder, EmbedFooterBuilder};
 use twilight_interactions::command::{CommandModel, CreateCommand};
@@ -16,7 +19,7 @@ use twilight_util::builder::CallbackDataBuilder;
 pub struct PingCommand;
 
 impl PingCommand {
-    pub async fn run(command: ApplicationCommand, context: Arc<Context>) {
+    pub async fn run(command: ApplicationCommand, context: Arc<Context>) -> Result<(), CommandError> {
         let interaction_client = context.get_interaction_client();
 
         interaction_client
@@ -25,40 +28,34 @@ impl PingCommand {
                 &command.token,
                 &InteractionResponse::DeferredChannelMessageWithSource(CallbackDataBuilder::new().build()))
             .exec()
-            .await
-            .unwrap();
+            .await?;
         let deferred_message = interaction_client
             .get_interaction_original(&command.token)
             .exec()
-            .await
-            .unwrap()
+            .await?
             .model()
-            .await
-            .unwrap();
+            .await?;
         let rtt = snowflake_to_ms(deferred_message.id.cast()) - snowflake_to_ms(command.id.cast());
-        let shard_id = get_shard_id(command.guild_id.unwrap(), context.cluster.shards().len().try_into().unwrap());
-        let description = if let Ok(info) = context.cluster.shard(shard_id).unwrap().info() {
-            if info.latency().heartbeats() > 0 {
-                format!("🏓 **Latency**: {} ms\n🔂 **RTT**: {} ms", info.latency().average().unwrap().as_millis(), rtt)
-            } else {
-                format!("🔂 **RTT**: {} ms", rtt)
-            }
-        } else {
-            "No data returned.".to_string()
+        let shard_id = get_shard_id(command.guild_id.unwrap(), context.cluster.shards().len() as u64);
+        let description = match context.cluster.shard(shard_id).map(|shard| shard.info()) {
+            Some(Ok(info)) => match info.latency().average() {
+                Some(latency) => format!("🏓 **Latency**: {} ms\n🔂 **RTT**: {} ms", latency.as_millis(), rtt),
+                None => format!("🔂 **RTT**: {} ms", rtt)
+            },
+            _ => "No data returned.".to_string()
         };
         let embed = EmbedBuilder::new()
             .color(0xF8F8FF)
             .description(description)
             .footer(EmbedFooterBuilder::new(format!("Shard {} stats", shard_id)))
-            .build()
-            .unwrap();
+            .build()?;
 
         interaction_client
             .update_followup_message(&command.token, deferred_message.id)
-            .embeds(Some(&[embed]))
-            .unwrap()
+            .embeds(Some(&[embed]))?
             .exec()
-            .await
-            .unwrap();
+            .await?;
+
+        Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,143 @@
+use crate::commands::*;
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult}
+};
+use twilight_http::client::InteractionClient;
+use twilight_interactions::command::CreateCommand;
+use twilight_model::{
+    application::command::Command,
+    id::{Id, marker::GuildMarker}
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CommandScope {
+    Global,
+    Guild(Id<GuildMarker>)
+}
+
+impl Display for CommandScope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Global => write!(f, "globally"),
+            Self::Guild(guild_id) => write!(f, "in guild {guild_id}")
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>
+}
+
+impl SyncReport {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl Display for SyncReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        if self.is_empty() {
+            return write!(f, "already up to date")
+        }
+
+        let sections = [("added", &self.added), ("changed", &self.changed), ("removed", &self.removed)]
+            .iter()
+            .filter(|(_, names)| !names.is_empty())
+            .map(|(label, names)| format!("{label} {}", names.join(", ")))
+            .collect::<Vec<String>>();
+
+        write!(f, "{}", sections.join("; "))
+    }
+}
+
+pub fn definitions() -> Vec<Command> {
+    vec![
+        AccessCommand::create_command().into(),
+        CancelCheckCommand::create_command().into(),
+        CategoryCommand::create_command().into(),
+        CheckCommand::create_command().into(),
+        HistoryCommand::create_command().into(),
+        IgnoreCommand::create_command().into(),
+        PingCommand::create_command().into(),
+        ResetCooldownCommand::create_command().into(),
+        SetCommand::create_command().into(),
+        SettingsCommand::create_command().into(),
+        StatsCommand::create_command().into()
+    ]
+}
+
+// Remote commands carry ids and versions that local definitions never have, so only compare what we define
+fn is_same(local: &Command, remote: &Command) -> bool {
+    local.kind == remote.kind
+        && local.description == remote.description
+        && local.default_permission.unwrap_or(true) == remote.default_permission.unwrap_or(true)
+        && local.options == remote.options
+}
+
+pub async fn sync(client: &InteractionClient<'_>, scope: CommandScope) -> Result<SyncReport, Box<dyn Error + Send + Sync>> {
+    let local = definitions();
+    let remote = match scope {
+        CommandScope::Global => client.get_global_commands().exec().await?.models().await?,
+        CommandScope::Guild(guild_id) => client.get_guild_commands(guild_id).exec().await?.models().await?
+    };
+    let mut report = SyncReport::default();
+
+    for command in &local {
+        match remote.iter().find(|remote_command| remote_command.name == command.name) {
+            Some(remote_command) if is_same(command, remote_command) => {},
+            Some(_) => report.changed.push(command.name.clone()),
+            None => report.added.push(command.name.clone())
+        }
+    }
+
+    for remote_command in &remote {
+        if !local.iter().any(|command| command.name == remote_command.name) {
+            report.removed.push(remote_command.name.clone());
+        }
+    }
+
+    if !report.is_empty() {
+        match scope {
+            CommandScope::Global => client.set_global_commands(&local).exec().await?,
+            CommandScope::Guild(guild_id) => client.set_guild_commands(guild_id, &local).exec().await?
+        };
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{self, discord::MockDiscord};
+    use serde_json::json;
+    use super::{sync, CommandScope};
+
+    #[tokio::test]
+    async fn sync_only_overwrites_when_definitions_differ() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+        let client = context.get_interaction_client();
+
+        mock.set_commands(None, json!([{ "description": "Old", "id": "1", "name": "legacy", "type": 1, "version": "1" }]));
+
+        let report = sync(&client, CommandScope::Global).await.unwrap();
+
+        assert_eq!(report.added.len(), 11);
+        assert_eq!(report.removed, vec!["legacy".to_string()]);
+        assert_eq!(mock.command_overwrites(), 1);
+
+        let report = sync(&client, CommandScope::Global).await.unwrap();
+
+        assert!(report.is_empty());
+        assert_eq!(mock.command_overwrites(), 1);
+
+        let report = sync(&client, CommandScope::Guild(testing::GUILD_ID)).await.unwrap();
+
+        assert_eq!(report.added.len(), 11);
+        assert_eq!(mock.command_overwrites(), 2);
+    }
+}
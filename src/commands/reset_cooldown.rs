@@ -0,0 +1,61 @@
+use crate::{
+    commands::error::CommandError,
+    util::context::Context
+};
+use std::sync::Arc;
+use twilight_embed_builder::EmbedBuilder;
+use twilight_interactions::command::{CommandModel, CreateCommand};
+use twilight_model::{
+    application::{callback::InteractionResponse, interaction::ApplicationCommand},
+    id::Id
+};
+use twilight_util::builder::CallbackDataBuilder;
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    desc = "Lets a guild run an invite check again right away (bot owner only)",
+    name = "reset-cooldown"
+)]
+pub struct ResetCooldownCommand {
+    #[command(desc = "The ID of the guild, defaults to this one")]
+    guild: Option<String>
+}
+
+impl ResetCooldownCommand {
+    pub async fn run(command: ApplicationCommand, context: Arc<Context>) -> Result<(), CommandError> {
+        let options = ResetCooldownCommand::from_interaction(command.data.into())?;
+        let guild_id = match options.guild {
+            Some(guild) => guild
+                .trim()
+                .parse::<u64>()
+                .ok()
+                .and_then(Id::new_checked)
+                .ok_or_else(|| CommandError::Validation(format!("\"{guild}\" is not a guild ID.")))?,
+            None => command.guild_id.unwrap()
+        };
+
+        if context.database.read_setting(guild_id).await?.is_none() {
+            return Err(CommandError::Validation(format!("Sakura has no settings for guild {guild_id}.")))
+        }
+
+        context.database.clear_last_check(guild_id).await?;
+
+        let embed = EmbedBuilder::new()
+            .color(0xF8F8FF)
+            .description(format!("Guild {guild_id} can run an invite check again right away."));
+
+        context
+            .get_interaction_client()
+            .interaction_callback(
+                command.id,
+                &command.token,
+                &InteractionResponse::ChannelMessageWithSource(
+                    CallbackDataBuilder::new().embeds(embed.build()).build()
+                )
+            )
+            .exec()
+            .await?;
+
+        Ok(())
+    }
+}
@@ -1,5 +1,10 @@
-use crate::util::{context::Context, random::{remove_leading_hashtag, validate_hex_code}};
-use std::{iter, sync::Arc};
+use chrono::Utc;
+use crate::{
+    commands::error::CommandError,
+    database::setting::{CheckSchedule, DEFAULT_SCAN_DEPTH, MAX_SCAN_DEPTH, MAX_SCHEDULE_HOURS},
+    util::{context::Context, random::{humanize, remove_leading_hashtag, validate_hex_code}}
+};
+use std::{iter, sync::Arc, time::Duration};
 use twilight_embed_builder::EmbedBuilder; 
 use twilight_interactions::command::{CommandModel, CreateCommand};
 use twilight_model::application::{
@@ -17,7 +22,13 @@ pub enum SetCommand {
     #[command(name = "results-channel")]
     ResultsChannel(SetResultsChannel),
     #[command(name = "embed-color")]
-    EmbedColor(SetEmbedColor)
+    EmbedColor(SetEmbedColor),
+    #[command(name = "scan-depth")]
+    ScanDepth(SetScanDepth),
+    #[command(name = "cooldown")]
+    Cooldown(SetCooldown),
+    #[command(name = "schedule")]
+    Schedule(SetSchedule)
 }
 
 #[derive(CommandModel, CreateCommand, Debug)]
@@ -34,21 +45,56 @@ pub struct SetEmbedColor {
     color: String
 }
 
+#[derive(CommandModel, CreateCommand, Debug)]
+#[command(desc = "Sets how many of each channel's latest messages are checked", name = "scan-depth")]
+pub struct SetScanDepth {
+    #[command(desc = "The number of messages, leave empty to reset", min_value = 1, max_value = 500)]
+    messages: Option<i64>,
+    #[command(channel_types = "guild_text", desc = "Only set it for this channel")]
+    channel: Option<InteractionChannel>
+}
+
+#[derive(CommandModel, CreateCommand, Debug)]
+#[command(desc = "Sets how long to wait between invite checks", name = "cooldown")]
+pub struct SetCooldown {
+    #[command(desc = "The number of hours, leave empty to reset", min_value = 1)]
+    hours: Option<i64>
+}
+
+#[derive(CommandModel, CreateCommand, Debug)]
+#[command(desc = "Runs invite checks automatically, leave everything empty to stop", name = "schedule")]
+pub struct SetSchedule {
+    #[command(desc = "Check every day at this hour (UTC)", min_value = 0, max_value = 23)]
+    hour: Option<i64>,
+    #[command(desc = "The minute of that hour", min_value = 0, max_value = 59)]
+    minute: Option<i64>,
+    #[command(desc = "Check every this many hours instead", min_value = 1, max_value = 8760)]
+    every: Option<i64>
+}
+
+pub fn describe_schedule(schedule: CheckSchedule) -> String {
+    match schedule {
+        CheckSchedule::Daily { hour, minute } => format!("daily at **{hour:02}:{minute:02} UTC**"),
+        CheckSchedule::Every { hours: 1 } => "every **hour**".to_string(),
+        CheckSchedule::Every { hours } => format!("every **{hours}** hours")
+    }
+}
+
 impl SetCommand {
-    pub async fn run(command: ApplicationCommand, context: Arc<Context>) {
+    pub async fn run(command: ApplicationCommand, context: Arc<Context>) -> Result<(), CommandError> {
         let guild_id = command.guild_id.unwrap();
-        let options = SetCommand::from_interaction(command.data.into()).unwrap();
+        let options = SetCommand::from_interaction(command.data.into())?;
         let mut embed = EmbedBuilder::new().color(0xF8F8FF);
         
         embed = match options {
             SetCommand::ResultsChannel(option) => {
                 match option.channel {
                     Some(channel) => {
-                        context.database.update_results_channel_id(guild_id, Some(channel.id)).await;
+                        context.database.update_results_channel_id(guild_id, Some(channel.id)).await?;
                         embed.description(format!("Invite check results will now be sent in <#{}>.", channel.id))
                     },
                     None => {
-                        context.database.update_results_channel_id(guild_id, None).await;
+                        context.database.update_results_channel_id(guild_id, None).await?;
                         embed.description("This server no longer has a results channel.")
                     }
                 }
@@ -58,17 +104,105 @@ impl SetCommand {
                 let mut formatted_hashtag_free_color = String::new();
                 
                 if hashtag_free_color.len() == 3 {
-                    formatted_hashtag_free_color.extend(hashtag_free_color.chars().flat_map(|c| iter::repeat(c).take(2)));
+                    formatted_hashtag_free_color.extend(hashtag_free_color.chars().flat_map(|c| iter::repeat_n(c, 2)));
                 } else {
                     formatted_hashtag_free_color = hashtag_free_color;
                 }
 
-                if !validate_hex_code(&formatted_hashtag_free_color) {
-                    embed.description("No valid color provided.")
-                } else {
-                    let color = u32::from_str_radix(&formatted_hashtag_free_color, 16).unwrap();
-                    context.database.update_embed_color(guild_id, color).await;
-                    embed.description(format!("The embed color for invite check embeds is now **#{:06X}**.", color))
+                match u32::from_str_radix(&formatted_hashtag_free_color, 16) {
+                    Ok(color) if validate_hex_code(&formatted_hashtag_free_color) => {
+                        context.database.update_embed_color(guild_id, color).await?;
+                        embed.description(format!("The embed color for invite check embeds is now **#{:06X}**.", color))
+                    },
+                    _ => return Err(CommandError::Validation("No valid color provided.".to_string()))
+                }
+            },
+            SetCommand::ScanDepth(option) => {
+                let scan_depth = match option.messages {
+                    Some(messages) if messages < 1 || messages > MAX_SCAN_DEPTH as i64 => {
+                        return Err(CommandError::Validation(format!("The scan depth has to be between 1 and {MAX_SCAN_DEPTH} messages.")))
+                    },
+                    messages => messages.map(|messages| messages as u16)
+                };
+
+                match (option.channel, scan_depth) {
+                    (Some(channel), Some(scan_depth)) => {
+                        context.database.update_channel_scan_depth(guild_id, channel.id, Some(scan_depth)).await?;
+                        embed.description(format!("The latest **{scan_depth}** messages in <#{}> will now be checked.", channel.id))
+                    },
+                    (Some(channel), None) => {
+                        context.database.update_channel_scan_depth(guild_id, channel.id, None).await?;
+                        embed.description(format!("<#{}> now uses the server's scan depth.", channel.id))
+                    },
+                    (None, scan_depth) => {
+                        let scan_depth = scan_depth.unwrap_or(DEFAULT_SCAN_DEPTH);
+
+                        context.database.update_scan_depth(guild_id, scan_depth).await?;
+                        embed.description(format!("The latest **{scan_depth}** messages in each channel will now be checked."))
+                    }
+                }
+            },
+            SetCommand::Cooldown(option) => {
+                let (minimum, maximum) = (context.config.min_invite_check_cooldown, context.config.max_invite_check_cooldown);
+
+                let cooldown = match option.hours {
+                    // Hours that don't fit in seconds are out of range too
+                    Some(hours) => match u64::try_from(hours).ok().and_then(|hours| hours.checked_mul(3_600)).map(Duration::from_secs) {
+                        Some(cooldown) if (minimum..=maximum).contains(&cooldown) => Some(cooldown),
+                        _ => return Err(CommandError::Validation(format!(
+                            "The cooldown has to be between {} and {}.",
+                            humanize(minimum.as_millis() as u64, false),
+                            humanize(maximum.as_millis() as u64, false)
+                        )))
+                    },
+                    None => None
+                };
+
+                match cooldown {
+                    Some(cooldown) => {
+                        context.database.update_check_cooldown(guild_id, Some(cooldown)).await?;
+                        embed.description(format!("Invite checks can now be run every **{}**.", humanize(cooldown.as_millis() as u64, false)))
+                    },
+                    None => {
+                        context.database.update_check_cooldown(guild_id, None).await?;
+                        embed.description(format!(
+                            "Invite checks are back to the default cooldown of **{}**.",
+                            humanize(context.config.check_cooldown(None).as_millis() as u64, false)
+                        ))
+                    }
+                }
+            },
+            SetCommand::Schedule(option) => {
+                let schedule = match (option.hour, option.minute, option.every) {
+                    (None, None, None) => None,
+                    (Some(hour), minute, None) if (0..24).contains(&hour) && (0..60).contains(&minute.unwrap_or(0)) => {
+                        Some(CheckSchedule::Daily { hour: hour as u32, minute: minute.unwrap_or(0) as u32 })
+                    },
+                    (None, None, Some(hours)) if hours > 0 && hours <= MAX_SCHEDULE_HOURS as i64 => Some(CheckSchedule::Every { hours: hours as u32 }),
+                    _ => return Err(CommandError::Validation("Choose either an hour (and minute) to check daily, or a number of hours to check every.".to_string()))
+                };
+
+                match schedule {
+                    Some(schedule) if schedule.period() < context.config.min_invite_check_cooldown => {
+                        return Err(CommandError::Validation(format!(
+                            "Scheduled checks have to be at least {} apart.",
+                            humanize(context.config.min_invite_check_cooldown.as_millis() as u64, false)
+                        )))
+                    },
+                    Some(schedule) => {
+                        let next_scheduled_check = schedule.next_after(Utc::now().naive_utc());
+
+                        context.database.update_schedule(guild_id, Some(schedule), Some(next_scheduled_check)).await?;
+                        embed.description(format!(
+                            "Invite checks will now run {}, starting <t:{}:R>.",
+                            describe_schedule(schedule),
+                            next_scheduled_check.and_utc().timestamp()
+                        ))
+                    },
+                    None => {
+                        context.database.update_schedule(guild_id, None, None).await?;
+                        embed.description("Invite checks will no longer run automatically.")
+                    }
                 }
             },
         };
@@ -83,7 +217,32 @@ impl SetCommand {
                 )
             )
             .exec()
-            .await
-            .unwrap();
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        commands::error::CommandError,
+        testing::{self, discord::MockDiscord, GUILD_ID, RESULTS_CHANNEL_ID}
+    };
+    use serde_json::json;
+    use super::SetCommand;
+
+    #[tokio::test]
+    async fn cooldowns_too_long_to_represent_are_rejected() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+
+        context.database.create_setting(GUILD_ID).await.unwrap();
+
+        let options = json!([{ "name": "cooldown", "options": [{ "name": "hours", "type": 4, "value": i64::MAX }], "type": 1 }]);
+        let error = SetCommand::run(testing::command("set", RESULTS_CHANNEL_ID, options), context.clone()).await.unwrap_err();
+
+        assert!(matches!(error, CommandError::Validation(_)));
+        assert!(context.database.read_setting(GUILD_ID).await.unwrap().unwrap().check_cooldown.is_none());
     }
-}
\ No newline at end of file
+}
@@ -1,5 +1,9 @@
-use crate::util::context::Context;
-use std::sync::Arc;
+use chrono::Utc;
+use crate::{
+    commands::{error::CommandError, set::describe_schedule},
+    util::{context::Context, random::humanize}
+};
+use std::{iter, sync::Arc};
 use twilight_embed_builder::{EmbedBuilder, EmbedFieldBuilder};
 use twilight_interactions::command::{CommandModel, CreateCommand};
 use twilight_model::application::{callback::InteractionResponse, interaction::ApplicationCommand};
@@ -14,13 +18,30 @@ use twilight_util::builder::CallbackDataBuilder;
 pub struct SettingsCommand;
 
 impl SettingsCommand {
-    pub async fn run(command: ApplicationCommand, context: Arc<Context>) {
+    pub async fn run(command: ApplicationCommand, context: Arc<Context>) -> Result<(), CommandError> {
         let guild_id = command.guild_id.unwrap();
         let mut embed = EmbedBuilder::new().color(0xF8F8FF);
         
-        embed = match context.database.read_setting(guild_id).await {
+        embed = match context.database.read_setting(guild_id).await.ok().flatten() {
             Some(setting) => {
-                let categories_text = if setting.category_channel_ids.len() == 0 {
+                let cooldown = context.config.check_cooldown(setting.check_cooldown);
+                let cooldown_text = match setting.next_check(cooldown, Utc::now().naive_utc()) {
+                    Some(next_check) => format!(
+                        "**{}**, the next invite check can be run <t:{}:R>",
+                        humanize(cooldown.as_millis() as u64, false),
+                        next_check.and_utc().timestamp()
+                    ),
+                    None => format!("**{}**, an invite check can be run now", humanize(cooldown.as_millis() as u64, false))
+                };
+                let schedule_text = match (setting.schedule, setting.next_scheduled_check) {
+                    (Some(schedule), Some(next_scheduled_check)) => format!(
+                        "Runs {}, next <t:{}:R>",
+                        describe_schedule(schedule),
+                        next_scheduled_check.and_utc().timestamp()
+                    ),
+                    _ => "No scheduled invite checks".to_string()
+                };
+                let categories_text = if setting.category_channel_ids.is_empty() {
                     "No categories added".to_string()
                 } else {
                     setting.category_channel_ids.into_iter().map(|channel_id| {
@@ -31,7 +52,7 @@ impl SettingsCommand {
                     }).collect::<Vec<String>>().join("\n")
                 };
                 let color_text = format!("#{:06X}", setting.embed_color);
-                let ignored_text = if setting.ignored_channel_ids.len() == 0 {
+                let ignored_text = if setting.ignored_channel_ids.is_empty() {
                     "No channels ignored".to_string()
                 } else {
                     setting.ignored_channel_ids.into_iter().map(|channel_id| {
@@ -41,6 +62,14 @@ impl SettingsCommand {
                         }
                     }).collect::<Vec<String>>().join("\n")
                 };
+                let mut scan_depths = context.database.read_channel_scan_depths(guild_id).await?.into_iter().collect::<Vec<_>>();
+
+                scan_depths.sort();
+
+                let scan_depth_text = iter::once(format!("**{}** messages per channel", setting.scan_depth))
+                    .chain(scan_depths.into_iter().map(|(channel_id, scan_depth)| format!("<#{}>: **{}**", channel_id, scan_depth)))
+                    .collect::<Vec<String>>()
+                    .join("\n");
                 let result_text = match setting.results_channel_id {
                     Some(channel_id) => format!("<#{}>", channel_id),
                     None => "No results channel set".to_string()
@@ -48,9 +77,12 @@ impl SettingsCommand {
 
                 embed
                     .field(EmbedFieldBuilder::new("Categories", categories_text).build())
+                    .field(EmbedFieldBuilder::new("Cooldown", cooldown_text).build())
                     .field(EmbedFieldBuilder::new("Embed color", color_text).build())
                     .field(EmbedFieldBuilder::new("Ignored", ignored_text).build())
-                    .field(EmbedFieldBuilder::new("Results channel", result_text).build())              
+                    .field(EmbedFieldBuilder::new("Results channel", result_text).build())
+                    .field(EmbedFieldBuilder::new("Scan depth", scan_depth_text).build())
+                    .field(EmbedFieldBuilder::new("Schedule", schedule_text).build())              
             },
             None => {
                 embed.description("No settings found. Please kick and reinvite Sakura.")
@@ -67,7 +99,8 @@ impl SettingsCommand {
                 )
             )
             .exec()
-            .await
-            .unwrap();
+            .await?;
+
+        Ok(())
     }
 }
\ No newline at end of file
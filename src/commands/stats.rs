@@ -1,4 +1,7 @@
-use crate::util::{context::Context, random::{add_commas, humanize}};
+use crate::{
+    commands::error::CommandError,
+    util::{context::Context, random::{add_commas, humanize}}
+};
 use std::sync::Arc;
 use sysinfo::{
     ProcessExt,
@@ -24,13 +27,16 @@ use twilight_util::builder::CallbackDataBuilder;
 pub struct StatsCommand;
 
 impl StatsCommand {
-    pub async fn run(command: ApplicationCommand, context: Arc<Context>) {
+    pub async fn run(command: ApplicationCommand, context: Arc<Context>) -> Result<(), CommandError> {
         let mut system = System::new_with_specifics(RefreshKind::new().with_processes(ProcessRefreshKind::everything()));
 
         system.refresh_all();
 
         let guild_count = add_commas(&context.cache.stats().guilds().to_string());
-        let process = system.process(sysinfo::get_current_pid().unwrap()).unwrap();
+        let process = sysinfo::get_current_pid()
+            .ok()
+            .and_then(|pid| system.process(pid))
+            .ok_or_else(|| CommandError::Internal("could not read Sakura's own process".to_string()))?;
         let memory = add_commas(&((f64::trunc((process.memory() as f64 / 1024_f64)  * 100.0) / 100.0).to_string()));
         let uptime = humanize(process.run_time() * 1000, false);
         let embed = EmbedBuilder::new()
@@ -48,7 +54,8 @@ impl StatsCommand {
                 )
             )
             .exec()
-            .await
-            .unwrap();
+            .await?;
+
+        Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,363 @@
+use crate::commands::registry::CommandScope;
+use std::{
+    collections::HashMap,
+    env,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs,
+    net::SocketAddr,
+    str::FromStr,
+    time::Duration
+};
+use toml::Value;
+use tracing_subscriber::EnvFilter;
+use twilight_model::id::{Id, marker::{ApplicationMarker, GuildMarker, UserMarker}};
+
+const CONFIG_PATH_VAR: &str = "SAKURA_CONFIG";
+const DEFAULT_CONFIG_PATH: &str = "sakura.toml";
+
+// Every key can be set in the TOML file, or through the environment as its uppercase name, which takes precedence
+const KEYS: [&str; 19] = [
+    "application_id",
+    "bot_token",
+    "check_lock_timeout",
+    "client_id",
+    "database_url",
+    "environment",
+    "http_address",
+    "invite_cache_ttl",
+    "invite_check_cooldown",
+    "invite_flush_interval",
+    "invite_lookup_concurrency",
+    "log_filter",
+    "log_format",
+    "max_invite_check_cooldown",
+    "min_invite_check_cooldown",
+    "owner_id",
+    "refund_interrupted_checks",
+    "shutdown_grace_period",
+    "test_guild_id"
+];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Environment {
+    Development,
+    Production
+}
+
+impl FromStr for Environment {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "development" | "dev" => Ok(Self::Development),
+            "production" | "prod" => Ok(Self::Production),
+            _ => Err(())
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LogFormat {
+    Json,
+    Pretty
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "pretty" => Ok(Self::Pretty),
+            _ => Err(())
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub application_id: Id<ApplicationMarker>,
+    pub bot_token: String,
+    // A check holding in_check for longer than this is assumed to be stuck and gets recovered
+    pub check_lock_timeout: Duration,
+    // Resolved from Discord at startup when not set
+    pub client_id: Option<Id<UserMarker>>,
+    pub database_url: String,
+    pub environment: Environment,
+    // Where the metrics, health and readiness endpoints listen
+    pub http_address: SocketAddr,
+    pub invite_cache_ttl: Duration,
+    // The cooldown for guilds that haven't set their own
+    pub invite_check_cooldown: Duration,
+    pub invite_flush_interval: Duration,
+    pub invite_lookup_concurrency: usize,
+    // An EnvFilter directive, like `info` or `sakura=debug,twilight_gateway=warn`
+    pub log_filter: String,
+    pub log_format: LogFormat,
+    // Bounds for the cooldown guilds can set themselves
+    pub max_invite_check_cooldown: Duration,
+    pub min_invite_check_cooldown: Duration,
+    // Can use owner-only commands like /reset-cooldown, nobody can when it isn't set
+    pub owner_id: Option<Id<UserMarker>>,
+    // Whether a check that was interrupted after recording last_check gives that cooldown back
+    pub refund_interrupted_checks: bool,
+    // How long running checks get to finish after a shutdown signal before they're aborted
+    pub shutdown_grace_period: Duration,
+    // Commands are registered here instead of globally during development
+    pub test_guild_id: Option<Id<GuildMarker>>
+}
+
+#[derive(Debug)]
+pub struct ConfigError {
+    pub problems: Vec<String>
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "Invalid configuration:")?;
+
+        for problem in &self.problems {
+            write!(f, "\n  - {problem}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for ConfigError {}
+
+struct Values {
+    entries: HashMap<String, String>,
+    problems: Vec<String>
+}
+
+impl Values {
+    fn parse<T: FromStr>(&mut self, key: &str, expected: &str) -> Option<Option<T>> {
+        match self.entries.get(key) {
+            Some(value) => match value.parse::<T>() {
+                Ok(value) => Some(Some(value)),
+                Err(_) => {
+                    self.problems.push(format!("{key} must be {expected}, got {value:?}"));
+                    None
+                }
+            },
+            None => Some(None)
+        }
+    }
+
+    fn required<T: FromStr>(&mut self, key: &str, expected: &str) -> Option<T> {
+        let value = self.parse(key, expected)?;
+
+        if value.is_none() {
+            self.problems.push(format!("{key} is missing (set {} or `{key}` in the config file)", key.to_uppercase()));
+        }
+
+        value
+    }
+
+    fn optional<T: FromStr>(&mut self, key: &str, expected: &str) -> Option<T> {
+        self.parse(key, expected).flatten()
+    }
+
+    fn seconds(&mut self, key: &str, default: u64) -> Duration {
+        Duration::from_secs(self.optional(key, "a whole number of seconds").unwrap_or(default))
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut problems = Vec::new();
+        let file = match env::var(CONFIG_PATH_VAR) {
+            Ok(path) => match fs::read_to_string(&path) {
+                Ok(contents) => Some(contents),
+                Err(error) => {
+                    problems.push(format!("could not read config file {path}: {error}"));
+                    None
+                }
+            },
+            Err(_) => fs::read_to_string(DEFAULT_CONFIG_PATH).ok()
+        };
+
+        Self::from_sources(file.as_deref(), |name| env::var(name).ok(), problems)
+    }
+
+    fn from_sources(file: Option<&str>, env: impl Fn(&str) -> Option<String>, mut problems: Vec<String>) -> Result<Self, ConfigError> {
+        let mut entries = HashMap::new();
+
+        if let Some(file) = file {
+            match file.parse::<Value>() {
+                Ok(Value::Table(table)) => for (key, value) in table {
+                    if !KEYS.contains(&key.as_str()) {
+                        problems.push(format!("unknown key `{key}` in the config file"));
+                        continue
+                    }
+
+                    match value {
+                        Value::String(value) => entries.insert(key, value),
+                        Value::Integer(value) => entries.insert(key, value.to_string()),
+                        Value::Boolean(value) => entries.insert(key, value.to_string()),
+                        _ => {
+                            problems.push(format!("{key} must be a string, an integer or a boolean in the config file"));
+                            continue
+                        }
+                    };
+                },
+                Ok(_) => problems.push("the config file must be a table of keys".to_string()),
+                Err(error) => problems.push(format!("could not parse the config file: {error}"))
+            }
+        }
+
+        for key in KEYS {
+            // Blank variables, like the ones in .env.example, count as unset
+            if let Some(value) = env(&key.to_uppercase()).filter(|value| !value.trim().is_empty()) {
+                entries.insert(key.to_string(), value.trim().to_string());
+            }
+        }
+
+        let mut values = Values { entries, problems };
+        let application_id = values.required("application_id", "a non-zero snowflake");
+        let bot_token = values.required::<String>("bot_token", "a string");
+        let check_lock_timeout = values.seconds("check_lock_timeout", 10_800);
+        let client_id = values.optional("client_id", "a non-zero snowflake");
+        let database_url = values.required::<String>("database_url", "a string");
+        let environment = values.optional("environment", "development or production").unwrap_or(Environment::Development);
+        let http_address = values.optional("http_address", "an address like 0.0.0.0:8080").unwrap_or_else(|| SocketAddr::from(([0, 0, 0, 0], 8080)));
+        let invite_cache_ttl = values.seconds("invite_cache_ttl", 60);
+        let invite_check_cooldown = values.seconds("invite_check_cooldown", 86_400);
+        let invite_flush_interval = values.seconds("invite_flush_interval", 5);
+        let invite_lookup_concurrency = values.optional("invite_lookup_concurrency", "a whole number").unwrap_or(4);
+        let log_filter = values.optional::<String>("log_filter", "a string").unwrap_or_else(|| "info".to_string());
+        let log_format = values.optional("log_format", "pretty or json").unwrap_or(LogFormat::Pretty);
+        let max_invite_check_cooldown = values.seconds("max_invite_check_cooldown", 604_800);
+        let min_invite_check_cooldown = values.seconds("min_invite_check_cooldown", 3_600);
+        let owner_id = values.optional("owner_id", "a non-zero snowflake");
+        let refund_interrupted_checks = values.optional("refund_interrupted_checks", "true or false").unwrap_or(true);
+        let shutdown_grace_period = values.seconds("shutdown_grace_period", 30);
+        let test_guild_id = match environment {
+            Environment::Development => values.required("test_guild_id", "a non-zero snowflake"),
+            Environment::Production => values.optional("test_guild_id", "a non-zero snowflake")
+        };
+
+        if invite_flush_interval.is_zero() {
+            values.problems.push("invite_flush_interval must be at least 1 second".to_string());
+        }
+
+        if min_invite_check_cooldown > max_invite_check_cooldown {
+            values.problems.push("min_invite_check_cooldown must not be more than max_invite_check_cooldown".to_string());
+        } else if invite_check_cooldown < min_invite_check_cooldown || invite_check_cooldown > max_invite_check_cooldown {
+            values.problems.push("invite_check_cooldown must be between min_invite_check_cooldown and max_invite_check_cooldown".to_string());
+        }
+
+        if invite_lookup_concurrency < 2 {
+            values.problems.push("invite_lookup_concurrency must be at least 2".to_string());
+        }
+
+        if let Err(error) = EnvFilter::try_new(&log_filter) {
+            values.problems.push(format!("log_filter is not a valid filter: {error}"));
+        }
+
+        match (application_id, bot_token, database_url) {
+            (Some(application_id), Some(bot_token), Some(database_url)) if values.problems.is_empty() => Ok(Self {
+                application_id,
+                bot_token,
+                check_lock_timeout,
+                client_id,
+                database_url,
+                environment,
+                http_address,
+                invite_cache_ttl,
+                invite_check_cooldown,
+                invite_flush_interval,
+                invite_lookup_concurrency,
+                log_filter,
+                log_format,
+                max_invite_check_cooldown,
+                min_invite_check_cooldown,
+                owner_id,
+                refund_interrupted_checks,
+                shutdown_grace_period,
+                test_guild_id
+            }),
+            _ => Err(ConfigError { problems: values.problems })
+        }
+    }
+
+    // A guild's own cooldown is kept within the current bounds, in case they changed since it was set
+    pub fn check_cooldown(&self, check_cooldown: Option<Duration>) -> Duration {
+        check_cooldown
+            .unwrap_or(self.invite_check_cooldown)
+            .clamp(self.min_invite_check_cooldown, self.max_invite_check_cooldown)
+    }
+
+    pub fn command_scope(&self) -> CommandScope {
+        match (self.environment, self.test_guild_id) {
+            (Environment::Development, Some(guild_id)) => CommandScope::Guild(guild_id),
+            _ => CommandScope::Global
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+    use crate::commands::registry::CommandScope;
+    use super::{Config, LogFormat};
+
+    fn env(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let pairs = pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<HashMap<String, String>>();
+
+        move |name| pairs.get(name).cloned()
+    }
+
+    #[test]
+    fn every_problem_is_reported_at_once() {
+        let file = "application_id = \"0\"\ninvite_check_cooldown = \"daily\"\ncolour = 1\nlog_format = \"xml\"";
+        let error = Config::from_sources(Some(file), env(&[("BOT_TOKEN", " ")]), Vec::new()).unwrap_err();
+        let problems = error.problems.join("\n");
+
+        assert!(problems.contains("unknown key `colour`"));
+        assert!(problems.contains("application_id must be a non-zero snowflake"));
+        assert!(problems.contains("bot_token is missing"));
+        assert!(problems.contains("database_url is missing"));
+        assert!(problems.contains("invite_check_cooldown must be a whole number of seconds"));
+        assert!(problems.contains("log_format must be pretty or json"));
+        assert!(problems.contains("test_guild_id is missing"));
+        assert_eq!(error.problems.len(), 7);
+    }
+
+    #[test]
+    fn environment_overrides_file_and_defaults_fill_the_rest() {
+        let file = "application_id = 1\nbot_token = \"file-token\"\ndatabase_url = \"memory:\"\ninvite_check_cooldown = 3600";
+        let config = Config::from_sources(Some(file), env(&[("BOT_TOKEN", "env-token"), ("TEST_GUILD_ID", "2")]), Vec::new()).unwrap();
+
+        assert_eq!(config.bot_token, "env-token");
+        assert_eq!(config.application_id.get(), 1);
+        assert_eq!(config.client_id, None);
+        assert_eq!(config.invite_check_cooldown, Duration::from_secs(3600));
+        assert_eq!(config.check_cooldown(None), Duration::from_secs(3600));
+        assert_eq!(config.check_cooldown(Some(Duration::from_secs(60))), Duration::from_secs(3600));
+        assert_eq!(config.check_cooldown(Some(Duration::from_secs(2_592_000))), Duration::from_secs(604_800));
+        assert_eq!(config.invite_cache_ttl, Duration::from_secs(60));
+        assert_eq!(config.invite_lookup_concurrency, 4);
+        assert_eq!(config.log_format, LogFormat::Pretty);
+        assert_eq!(config.command_scope(), CommandScope::Guild(config.test_guild_id.unwrap()));
+    }
+
+    #[test]
+    fn production_registers_globally_without_a_test_guild() {
+        let config = Config::from_sources(None, env(&[
+            ("APPLICATION_ID", "1"),
+            ("BOT_TOKEN", "token"),
+            ("DATABASE_URL", "memory:"),
+            ("ENVIRONMENT", "production"),
+            ("LOG_FORMAT", "JSON")
+        ]), Vec::new()).unwrap();
+
+        assert_eq!(config.command_scope(), CommandScope::Global);
+        assert_eq!(config.log_format, LogFormat::Json);
+    }
+}
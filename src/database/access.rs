@@ -0,0 +1,79 @@
+use dashmap::DashSet;
+use tokio_postgres::Row;
+use twilight_model::{
+    guild::Permissions,
+    id::{Id, marker::{GuildMarker, RoleMarker}}
+};
+
+#[derive(Clone, Debug)]
+pub struct CommandAccess {
+    pub guild_id: Id<GuildMarker>,
+    pub command: String,
+    pub role_ids: DashSet<Id<RoleMarker>>,
+    pub permissions: Permissions
+}
+
+impl CommandAccess {
+    pub fn new(guild_id: Id<GuildMarker>, command: String) -> Self {
+        Self {
+            guild_id,
+            command,
+            role_ids: DashSet::new(),
+            permissions: Permissions::empty()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.role_ids.is_empty() && self.permissions.is_empty()
+    }
+
+    // Administrators always pass so a guild can't lock itself out of a command
+    pub fn allows(&self, role_ids: &[Id<RoleMarker>], permissions: Permissions) -> bool {
+        permissions.contains(Permissions::ADMINISTRATOR)
+            || role_ids.iter().any(|role_id| self.role_ids.contains(role_id))
+            || (!self.permissions.is_empty() && permissions.contains(self.permissions))
+    }
+}
+
+// Commands without a configured rule stay administrator-only
+pub fn is_allowed(access: Option<&CommandAccess>, role_ids: &[Id<RoleMarker>], permissions: Permissions) -> bool {
+    match access {
+        Some(access) => access.allows(role_ids, permissions),
+        None => permissions.contains(Permissions::ADMINISTRATOR)
+    }
+}
+
+impl From<Row> for CommandAccess {
+    fn from(row: Row) -> Self {
+        Self {
+            guild_id: Id::new(row.get::<_, i64>(0) as u64),
+            command: row.get(1),
+            role_ids: row.get::<_, Vec<i64>>(2).into_iter().map(|id| Id::new(id as u64)).collect(),
+            permissions: Permissions::from_bits_truncate(row.get::<_, i64>(3) as u64)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use twilight_model::{guild::Permissions, id::Id};
+    use super::{is_allowed, CommandAccess};
+
+    #[test]
+    fn rules_grant_by_role_or_permission_and_admins_always_pass() {
+        let access = CommandAccess::new(Id::new(1), "check".to_string());
+
+        access.role_ids.insert(Id::new(5));
+
+        assert!(access.allows(&[Id::new(4), Id::new(5)], Permissions::empty()));
+        assert!(!access.allows(&[Id::new(4)], Permissions::MANAGE_GUILD));
+        assert!(access.allows(&[], Permissions::ADMINISTRATOR));
+
+        let access = CommandAccess { permissions: Permissions::MANAGE_GUILD, ..access };
+
+        assert!(access.allows(&[], Permissions::MANAGE_GUILD | Permissions::SEND_MESSAGES));
+        assert!(!access.allows(&[], Permissions::SEND_MESSAGES));
+        assert!(!is_allowed(None, &[Id::new(5)], Permissions::MANAGE_GUILD));
+        assert!(is_allowed(None, &[], Permissions::ADMINISTRATOR));
+    }
+}
@@ -0,0 +1,379 @@
+use chrono::NaiveDateTime;
+use std::{collections::{BTreeSet, HashMap, HashSet}, str::FromStr};
+use tokio_postgres::Row;
+use twilight_model::id::{Id, marker::{ChannelMarker, GuildMarker, MessageMarker, UserMarker}};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CheckStatus {
+    Running,
+    Completed,
+    Failed,
+    // Stopped by a shutdown or a crash before it could finish
+    Aborted,
+    // Stopped early by /cancel-check, so its results only cover part of the guild
+    Cancelled
+}
+
+impl CheckStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Aborted => "aborted",
+            Self::Cancelled => "cancelled"
+        }
+    }
+}
+
+impl FromStr for CheckStatus {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "running" => Ok(Self::Running),
+            "completed" => Ok(Self::Completed),
+            "failed" => Ok(Self::Failed),
+            "aborted" => Ok(Self::Aborted),
+            "cancelled" => Ok(Self::Cancelled),
+            _ => Err(())
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InviteStatus {
+    Valid,
+    Invalid,
+    // The lookup failed, so nothing is known about the invite
+    Unresolved
+}
+
+impl InviteStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Valid => "valid",
+            Self::Invalid => "invalid",
+            Self::Unresolved => "unresolved"
+        }
+    }
+}
+
+impl FromStr for InviteStatus {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "valid" => Ok(Self::Valid),
+            "invalid" => Ok(Self::Invalid),
+            "unresolved" => Ok(Self::Unresolved),
+            _ => Err(())
+        }
+    }
+}
+
+// One occurrence of an invite, so a code posted twice in a channel shows up twice
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InviteResult {
+    pub message_id: Id<MessageMarker>,
+    pub code: String,
+    pub status: InviteStatus,
+    pub is_permanent: Option<bool>,
+    pub expires_at: Option<NaiveDateTime>
+}
+
+// Invite results are read separately and attached to their channel by the caller
+impl From<&Row> for InviteResult {
+    fn from(row: &Row) -> Self {
+        Self {
+            message_id: Id::new(row.get::<_, i64>(0) as u64),
+            code: row.get(1),
+            status: row.get::<_, String>(2).parse().unwrap_or(InviteStatus::Unresolved),
+            is_permanent: row.get(3),
+            expires_at: row.get(4)
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CheckRun {
+    pub id: i64,
+    pub guild_id: Id<GuildMarker>,
+    // Who ran the check
+    pub user_id: Id<UserMarker>,
+    pub status: CheckStatus,
+    pub started_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>
+}
+
+impl From<Row> for CheckRun {
+    fn from(row: Row) -> Self {
+        Self {
+            id: row.get(0),
+            guild_id: Id::new(row.get::<_, i64>(1) as u64),
+            user_id: Id::new(row.get::<_, i64>(2) as u64),
+            status: row.get::<_, String>(3).parse().unwrap_or(CheckStatus::Failed),
+            started_at: row.get(4),
+            finished_at: row.get(5)
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChannelResult {
+    pub channel_id: Id<ChannelMarker>,
+    pub good: u32,
+    pub bad: u32,
+    pub unresolved: u32,
+    pub messages: u32,
+    // Also how the next check tells which invites expired in between
+    pub invites: Vec<InviteResult>
+}
+
+impl ChannelResult {
+    pub fn new(channel_id: Id<ChannelMarker>) -> Self {
+        Self {
+            channel_id,
+            good: 0,
+            bad: 0,
+            unresolved: 0,
+            messages: 0,
+            invites: vec![]
+        }
+    }
+}
+
+impl From<&Row> for ChannelResult {
+    fn from(row: &Row) -> Self {
+        Self {
+            channel_id: Id::new(row.get::<_, i64>(0) as u64),
+            good: row.get::<_, i32>(1) as u32,
+            bad: row.get::<_, i32>(2) as u32,
+            unresolved: row.get::<_, i32>(3) as u32,
+            messages: row.get::<_, i32>(6) as u32,
+            invites: vec![]
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CategoryResult {
+    pub category_id: Id<ChannelMarker>,
+    pub name: String,
+    pub channel_results: Vec<ChannelResult>,
+    // Channels that disappeared from the cache mid-check
+    pub issue_channel_ids: Vec<Id<ChannelMarker>>,
+    // Channels Sakura can't read, which someone has to check by hand
+    pub manual_channel_ids: Vec<Id<ChannelMarker>>
+}
+
+impl CategoryResult {
+    pub fn new(category_id: Id<ChannelMarker>, name: String) -> Self {
+        Self {
+            category_id,
+            name,
+            channel_results: vec![],
+            issue_channel_ids: vec![],
+            manual_channel_ids: vec![]
+        }
+    }
+}
+
+// Channel results are read separately and attached by the caller
+impl From<&Row> for CategoryResult {
+    fn from(row: &Row) -> Self {
+        let ids = |index| row.get::<_, Vec<i64>>(index).into_iter().map(|id| Id::new(id as u64)).collect();
+
+        Self {
+            category_id: Id::new(row.get::<_, i64>(1) as u64),
+            name: row.get(2),
+            channel_results: vec![],
+            issue_channel_ids: ids(3),
+            manual_channel_ids: ids(4)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CheckTotals {
+    // Every channel the check looked at, including the ones it couldn't read
+    pub channels: u32,
+    pub good: u32,
+    pub bad: u32,
+    pub unresolved: u32
+}
+
+impl CheckTotals {
+    pub fn invites(&self) -> u32 {
+        self.good + self.bad + self.unresolved
+    }
+
+    pub fn invalid_percentage(&self) -> f32 {
+        (self.bad * 100) as f32 / self.invites().max(1) as f32
+    }
+}
+
+impl From<&[CategoryResult]> for CheckTotals {
+    fn from(category_results: &[CategoryResult]) -> Self {
+        let mut totals = Self::default();
+
+        for category_result in category_results {
+            totals.channels += (category_result.channel_results.len()
+                + category_result.issue_channel_ids.len()
+                + category_result.manual_channel_ids.len()) as u32;
+
+            for channel_result in &category_result.channel_results {
+                totals.good += channel_result.good;
+                totals.bad += channel_result.bad;
+                totals.unresolved += channel_result.unresolved;
+            }
+        }
+
+        totals
+    }
+}
+
+// Totals come after the run's own columns when a query sums them up
+impl From<&Row> for CheckTotals {
+    fn from(row: &Row) -> Self {
+        Self {
+            channels: row.get::<_, i64>(6) as u32,
+            good: row.get::<_, i64>(7) as u32,
+            bad: row.get::<_, i64>(8) as u32,
+            unresolved: row.get::<_, i64>(9) as u32
+        }
+    }
+}
+
+fn channel_ids(category_results: &[CategoryResult]) -> Vec<Id<ChannelMarker>> {
+    category_results
+        .iter()
+        .flat_map(|category_result| category_result.channel_results
+            .iter()
+            .map(|channel_result| channel_result.channel_id)
+            .chain(category_result.issue_channel_ids.iter().copied())
+            .chain(category_result.manual_channel_ids.iter().copied()))
+        .collect()
+}
+
+fn channel_results(category_results: &[CategoryResult]) -> HashMap<Id<ChannelMarker>, &ChannelResult> {
+    category_results
+        .iter()
+        .flat_map(|category_result| &category_result.channel_results)
+        .map(|channel_result| (channel_result.channel_id, channel_result))
+        .collect()
+}
+
+// Each code once, in order, since a channel can post the same invite more than once
+fn codes(channel_result: &ChannelResult, status: InviteStatus) -> BTreeSet<&String> {
+    channel_result.invites
+        .iter()
+        .filter(|invite| invite.status == status)
+        .map(|invite| &invite.code)
+        .collect()
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CheckDiff {
+    pub previous_run_id: i64,
+    // Channels that had nothing wrong last time and now have bad invites
+    pub broken_channel_ids: Vec<Id<ChannelMarker>>,
+    pub fixed_channel_ids: Vec<Id<ChannelMarker>>,
+    pub new_channel_ids: Vec<Id<ChannelMarker>>,
+    pub removed_channel_ids: Vec<Id<ChannelMarker>>,
+    // Invites that were good last time and are bad now, with the channel they're in now
+    pub expired_codes: Vec<(Id<ChannelMarker>, String)>
+}
+
+impl CheckDiff {
+    pub fn new(previous_run_id: i64, previous: &[CategoryResult], current: &[CategoryResult]) -> Self {
+        let previous_channel_ids = channel_ids(previous);
+        let current_channel_ids = channel_ids(current);
+        let previous_results = channel_results(previous);
+        let previous_good_codes = previous_results
+            .values()
+            .flat_map(|channel_result| codes(channel_result, InviteStatus::Valid))
+            .collect::<HashSet<&String>>();
+        let mut diff = Self {
+            previous_run_id,
+            new_channel_ids: current_channel_ids.iter().filter(|channel_id| !previous_channel_ids.contains(channel_id)).copied().collect(),
+            removed_channel_ids: previous_channel_ids.iter().filter(|channel_id| !current_channel_ids.contains(channel_id)).copied().collect(),
+            ..Self::default()
+        };
+
+        for channel_result in current.iter().flat_map(|category_result| &category_result.channel_results) {
+            if let Some(previous_result) = previous_results.get(&channel_result.channel_id) {
+                if previous_result.bad == 0 && previous_result.unresolved == 0 && channel_result.bad > 0 {
+                    diff.broken_channel_ids.push(channel_result.channel_id);
+                } else if previous_result.bad > 0 && channel_result.bad == 0 {
+                    diff.fixed_channel_ids.push(channel_result.channel_id);
+                }
+            }
+
+            for code in codes(channel_result, InviteStatus::Invalid) {
+                if previous_good_codes.contains(code) {
+                    diff.expired_codes.push((channel_result.channel_id, code.clone()));
+                }
+            }
+        }
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.broken_channel_ids.is_empty()
+            && self.fixed_channel_ids.is_empty()
+            && self.new_channel_ids.is_empty()
+            && self.removed_channel_ids.is_empty()
+            && self.expired_codes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use twilight_model::id::Id;
+    use super::{CategoryResult, ChannelResult, CheckDiff, InviteResult, InviteStatus};
+
+    fn channel(channel_id: u64, good_codes: &[&str], bad_codes: &[&str]) -> ChannelResult {
+        let mut channel_result = ChannelResult::new(Id::new(channel_id));
+        let invite = |code: &&str, status| InviteResult {
+            message_id: Id::new(1),
+            code: code.to_string(),
+            status,
+            is_permanent: None,
+            expires_at: None
+        };
+
+        channel_result.invites = good_codes
+            .iter()
+            .map(|code| invite(code, InviteStatus::Valid))
+            .chain(bad_codes.iter().map(|code| invite(code, InviteStatus::Invalid)))
+            .collect();
+        channel_result.good = good_codes.len() as u32;
+        channel_result.bad = bad_codes.len() as u32;
+        channel_result
+    }
+
+    fn category(channel_results: Vec<ChannelResult>, manual_channel_ids: &[u64]) -> CategoryResult {
+        let mut category_result = CategoryResult::new(Id::new(1), "Partners".to_string());
+
+        category_result.channel_results = channel_results;
+        category_result.manual_channel_ids = manual_channel_ids.iter().map(|channel_id| Id::new(*channel_id)).collect();
+        category_result
+    }
+
+    #[test]
+    fn diff_tracks_broken_fixed_new_and_removed_channels_and_expired_invites() {
+        let previous = [category(vec![channel(10, &["a", "b"], &[]), channel(11, &[], &["c"]), channel(12, &["d"], &[])], &[13])];
+        let current = [category(vec![channel(10, &["a"], &["b", "b"]), channel(11, &["e"], &[]), channel(14, &["f"], &[])], &[13])];
+        let diff = CheckDiff::new(7, &previous, &current);
+
+        assert_eq!(diff.previous_run_id, 7);
+        assert_eq!(diff.broken_channel_ids, vec![Id::new(10)]);
+        assert_eq!(diff.fixed_channel_ids, vec![Id::new(11)]);
+        assert_eq!(diff.new_channel_ids, vec![Id::new(14)]);
+        assert_eq!(diff.removed_channel_ids, vec![Id::new(12)]);
+        assert_eq!(diff.expired_codes, vec![(Id::new(10), "b".to_string())]);
+        assert!(CheckDiff::new(7, &current, &current).is_empty());
+    }
+}
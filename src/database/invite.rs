@@ -1,10 +1,10 @@
 use chrono::NaiveDateTime;
-use super::Database;
-use std::collections::{HashMap, HashSet};
+use std::ops::AddAssign;
 use tokio_postgres::Row;
-use twilight_model::{id::{Id, marker::GuildMarker}, datetime::Timestamp};
+use twilight_model::id::{Id, marker::GuildMarker};
 
-#[derive(Debug)]
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
 pub struct Invite {
     pub guild_id: Id<GuildMarker>,
     pub code: String,
@@ -21,18 +21,9 @@ impl From<Row> for Invite {
         Self {
             guild_id: Id::new(row.get::<_, i64>(0) as u64), 
             code: row.get(1),
-            expires_at: match row.try_get::<_, chrono::NaiveDateTime>(2) {
-                Ok(ndt) => Some(ndt),
-                Err(_) => None   
-            },
-            is_permanent: match row.try_get::<_, bool>(3) {
-                Ok(value) => Some(value),
-                Err(_) => None   
-            },
-            is_valid: match row.try_get::<_, bool>(4) {
-                Ok(value) => Some(value),
-                Err(_) => None   
-            },
+            expires_at: row.try_get::<_, NaiveDateTime>(2).ok(),
+            is_permanent: row.try_get::<_, bool>(3).ok(),
+            is_valid: row.try_get::<_, bool>(4).ok(),
             is_checked: row.get(5),
             created_at: row.get(6),
             updated_at: row.get(7),  
@@ -40,7 +31,7 @@ impl From<Row> for Invite {
     }
 }
 
-#[derive(Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Code {
     pub guild_id: Id<GuildMarker>,
     pub code: String
@@ -55,108 +46,15 @@ impl From<Row> for Code {
     }
 }
 
-impl Database {
-    pub async fn create_invites(&self, guild_id: Id<GuildMarker>, codes: HashSet<String>) {
-        let client = self.get_object().await;
-        let values = codes.iter().map(|code| format!("({}, '{}')", guild_id, code)).collect::<Vec<String>>().join(", ");
-        let query = format!("INSERT INTO invite(guild_id, code) VALUES {} ON CONFLICT DO NOTHING;", values);
-        
-        client.query(&query, &[]).await.unwrap();
-    }
-
-    pub async fn read_checked_codes(&self, amount: u16) -> Option<HashSet<Code>> {
-        let client = self.get_object().await;
-        let query = "SELECT guild_id, code FROM invite WHERE is_checked = TRUE and is_valid = TRUE ORDER BY updated_at LIMIT $1;";
-
-        match client.query(query, &[&(amount as i64)]).await {
-            Ok(rows) => {
-                let mut codes = HashSet::new();
-
-                for row in rows.into_iter() {
-                    codes.insert(Code::from(row));
-                }
-
-                Some(codes)
-            },
-            Err(_) => None
-        }
-    }
-
-    pub async fn read_guild_invites(&self, guild_id: Id<GuildMarker>) -> Option<HashMap<String, Invite>> {
-        let client = self.get_object().await;
-        let query = "SELECT * FROM invite WHERE guild_id = $1;";
-        
-        match client.query(query, &[&(guild_id.get() as i64)]).await {
-            Ok(rows) => {
-                let mut invites = HashMap::new();
-
-                for row in rows.into_iter() {
-                    invites.insert(row.get(1), Invite::from(row));
-                }
-                
-                Some(invites)
-            },
-            Err(_) => None
-        }
-    }
-
-    pub async fn read_unchecked_codes(&self, amount: u16) -> Option<HashSet<Code>> {
-        let client = self.get_object().await;
-        let query = "SELECT guild_id, code FROM invite WHERE is_checked = FALSE ORDER BY created_at LIMIT $1;";
-
-        match client.query(query, &[&(amount as i64)]).await {
-            Ok(rows) => {
-                let mut codes = HashSet::new();
-
-                for row in rows.into_iter() {
-                    codes.insert(Code::from(row));
-                }
-
-                Some(codes)
-            },
-            Err(_) => None
-        }
-    }
-
-    pub async fn update_code(&self, guild_id: Id<GuildMarker>, code: String, expires_at: Option<Timestamp>, is_permanent: bool, is_valid: bool) {
-        let client = self.get_object().await;
-        let query = "
-            UPDATE invite
-            SET 
-                expires_at = $3,
-                is_permanent = $4,
-                is_valid = $5,
-                is_checked = TRUE,
-                updated_at = CURRENT_TIMESTAMP
-            WHERE
-                guild_id = $1
-                AND code = $2;
-        ";
-
-        match expires_at {  
-            Some(timestamp) => client.query(query, &[&(guild_id.get() as i64), &code, &NaiveDateTime::from_timestamp(timestamp.as_secs(), 0), &is_permanent, &is_valid]).await.unwrap(),
-            None => client.query(query, &[&(guild_id.get() as i64), &code, &None::<NaiveDateTime>, &is_permanent, &is_valid]).await.unwrap(),
-        };
-    }
-
-    pub async fn upsert_code(&self, guild_id: Id<GuildMarker>, code: String, expires_at: Option<Timestamp>, is_permanent: bool, is_valid: bool) {
-        let client = self.get_object().await;
-        let query = "
-            INSERT INTO invite(guild_id, code, expires_at, is_permanent, is_valid, is_checked)
-            VALUES($1, $2, $3, $4, $5, TRUE)
-            ON CONFLICT (guild_id, code)
-            DO 
-            UPDATE SET
-                expires_at = EXCLUDED.expires_at,
-                is_permanent = EXCLUDED.is_permanent,
-                is_valid = EXCLUDED.is_valid,
-                is_checked = TRUE,
-                updated_at = CURRENT_TIMESTAMP
-        ";
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct IngestReport {
+    pub new: usize,
+    pub known: usize
+}
 
-        match expires_at {  
-            Some(timestamp) => client.query(query, &[&(guild_id.get() as i64), &code, &NaiveDateTime::from_timestamp(timestamp.as_secs(), 0), &is_permanent, &is_valid]).await.unwrap(),
-            None => client.query(query, &[&(guild_id.get() as i64), &code, &None::<NaiveDateTime>, &is_permanent, &is_valid]).await.unwrap(),
-        };
+impl AddAssign for IngestReport {
+    fn add_assign(&mut self, other: Self) {
+        self.new += other.new;
+        self.known += other.known;
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,431 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use dashmap::{mapref::entry::Entry, DashMap, DashSet};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::atomic::{AtomicI64, Ordering},
+    time::Duration
+};
+use super::{
+    access::CommandAccess,
+    check::{CategoryResult, CheckRun, CheckStatus, CheckTotals},
+    invite::{Code, IngestReport, Invite},
+    migration::{Migration, MigrationError},
+    setting::{CheckSchedule, Setting},
+    Storage,
+    StorageError
+};
+use twilight_model::{
+    datetime::Timestamp,
+    id::{Id, marker::{ChannelMarker, GuildMarker, UserMarker}}
+};
+
+#[derive(Default)]
+pub struct MemoryStorage {
+    category_results: DashMap<i64, BTreeMap<usize, CategoryResult>>,
+    channel_scan_depths: DashMap<(Id<GuildMarker>, Id<ChannelMarker>), u16>,
+    check_runs: DashMap<i64, CheckRun>,
+    command_access: DashMap<(Id<GuildMarker>, String), CommandAccess>,
+    last_check_run_id: AtomicI64,
+    invites: DashMap<(Id<GuildMarker>, String), Invite>,
+    settings: DashMap<Id<GuildMarker>, Setting>
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn migrate(&self) -> Result<Vec<&'static Migration>, MigrationError> {
+        Ok(vec![])
+    }
+
+    async fn ping(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    async fn create_setting(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError> {
+        self.settings.entry(guild_id).or_insert_with(|| Setting::new(guild_id));
+
+        Ok(())
+    }
+
+    async fn delete_channel(&self, guild_id: Id<GuildMarker>, channel_id: Id<ChannelMarker>) -> Result<(), StorageError> {
+        if let Some(mut setting) = self.settings.get_mut(&guild_id) {
+            setting.category_channel_ids.remove(&channel_id);
+            setting.ignored_channel_ids.remove(&channel_id);
+
+            if setting.results_channel_id == Some(channel_id) {
+                setting.results_channel_id = None;
+            }
+
+            self.channel_scan_depths.remove(&(guild_id, channel_id));
+        }
+
+        Ok(())
+    }
+
+    async fn read_category_channel_ids(&self, guild_id: Id<GuildMarker>) -> Result<DashSet<Id<ChannelMarker>>, StorageError> {
+        match self.settings.get(&guild_id) {
+            Some(setting) => Ok(setting.category_channel_ids.clone()),
+            None => Err(StorageError::MissingSetting(guild_id))
+        }
+    }
+
+    async fn read_ignored_channel_ids(&self, guild_id: Id<GuildMarker>) -> Result<DashSet<Id<ChannelMarker>>, StorageError> {
+        match self.settings.get(&guild_id) {
+            Some(setting) => Ok(setting.ignored_channel_ids.clone()),
+            None => Err(StorageError::MissingSetting(guild_id))
+        }
+    }
+
+    async fn read_setting(&self, guild_id: Id<GuildMarker>) -> Result<Option<Setting>, StorageError> {
+        Ok(self.settings.get(&guild_id).map(|setting| setting.clone()))
+    }
+
+    async fn update_results_channel_id(&self, guild_id: Id<GuildMarker>, channel_id: Option<Id<ChannelMarker>>) -> Result<(), StorageError> {
+        if let Some(mut setting) = self.settings.get_mut(&guild_id) {
+            setting.results_channel_id = channel_id;
+        }
+
+        Ok(())
+    }
+
+    async fn update_category_channel_ids(&self, guild_id: Id<GuildMarker>, channel_ids: DashSet<Id<ChannelMarker>>) -> Result<(), StorageError> {
+        if let Some(mut setting) = self.settings.get_mut(&guild_id) {
+            setting.category_channel_ids = channel_ids;
+        }
+
+        Ok(())
+    }
+
+    async fn update_ignored_channel_ids(&self, guild_id: Id<GuildMarker>, channel_ids: DashSet<Id<ChannelMarker>>) -> Result<(), StorageError> {
+        if let Some(mut setting) = self.settings.get_mut(&guild_id) {
+            setting.ignored_channel_ids = channel_ids;
+        }
+
+        Ok(())
+    }
+
+    async fn update_embed_color(&self, guild_id: Id<GuildMarker>, color: u32) -> Result<(), StorageError> {
+        if let Some(mut setting) = self.settings.get_mut(&guild_id) {
+            setting.embed_color = color;
+        }
+
+        Ok(())
+    }
+
+    async fn update_scan_depth(&self, guild_id: Id<GuildMarker>, scan_depth: u16) -> Result<(), StorageError> {
+        if let Some(mut setting) = self.settings.get_mut(&guild_id) {
+            setting.scan_depth = scan_depth;
+        }
+
+        Ok(())
+    }
+
+    async fn update_check_cooldown(&self, guild_id: Id<GuildMarker>, check_cooldown: Option<Duration>) -> Result<(), StorageError> {
+        if let Some(mut setting) = self.settings.get_mut(&guild_id) {
+            setting.check_cooldown = check_cooldown;
+        }
+
+        Ok(())
+    }
+
+    async fn update_last_check(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError> {
+        if let Some(mut setting) = self.settings.get_mut(&guild_id) {
+            setting.last_check = Some(Utc::now().naive_utc());
+        }
+
+        Ok(())
+    }
+
+    async fn clear_last_check(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError> {
+        if let Some(mut setting) = self.settings.get_mut(&guild_id) {
+            setting.last_check = None;
+        }
+
+        Ok(())
+    }
+
+    async fn update_schedule(&self, guild_id: Id<GuildMarker>, schedule: Option<CheckSchedule>, next_scheduled_check: Option<NaiveDateTime>) -> Result<(), StorageError> {
+        if let Some(mut setting) = self.settings.get_mut(&guild_id) {
+            setting.schedule = schedule;
+            setting.next_scheduled_check = next_scheduled_check;
+        }
+
+        Ok(())
+    }
+
+    async fn read_due_schedules(&self, now: NaiveDateTime) -> Result<Vec<Setting>, StorageError> {
+        Ok(self.settings
+            .iter()
+            .filter(|setting| setting.schedule.is_some() && setting.next_scheduled_check.is_some_and(|next_scheduled_check| next_scheduled_check <= now))
+            .map(|setting| setting.value().clone())
+            .collect())
+    }
+
+    async fn update_in_check(&self, guild_id: Id<GuildMarker>, in_check: bool) -> Result<bool, StorageError> {
+        match self.settings.get_mut(&guild_id) {
+            Some(mut setting) if setting.in_check != in_check => {
+                setting.in_check = in_check;
+                setting.check_started_at = in_check.then(|| Utc::now().naive_utc());
+
+                Ok(true)
+            },
+            _ => Ok(false)
+        }
+    }
+
+    async fn read_interrupted_checks(&self, started_before: Option<NaiveDateTime>) -> Result<Vec<Setting>, StorageError> {
+        Ok(self.settings
+            .iter()
+            .filter(|setting| setting.in_check)
+            .filter(|setting| match (started_before, setting.check_started_at) {
+                (Some(started_before), Some(check_started_at)) => check_started_at < started_before,
+                _ => true
+            })
+            .map(|setting| setting.clone())
+            .collect())
+    }
+
+    async fn recover_check(&self, guild_id: Id<GuildMarker>, clear_last_check: bool) -> Result<(), StorageError> {
+        if let Some(mut setting) = self.settings.get_mut(&guild_id) {
+            setting.in_check = false;
+            setting.check_started_at = None;
+
+            if clear_last_check {
+                setting.last_check = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn delete_setting(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError> {
+        self.settings.remove(&guild_id);
+        self.command_access.retain(|(access_guild_id, _), _| *access_guild_id != guild_id);
+        self.channel_scan_depths.retain(|(scan_depth_guild_id, _), _| *scan_depth_guild_id != guild_id);
+        self.check_runs.retain(|_, run| run.guild_id != guild_id);
+        self.category_results.retain(|run_id, _| self.check_runs.contains_key(run_id));
+
+        Ok(())
+    }
+
+    async fn read_command_access(&self, guild_id: Id<GuildMarker>) -> Result<Vec<CommandAccess>, StorageError> {
+        let mut access = self.command_access
+            .iter()
+            .filter(|entry| entry.guild_id == guild_id)
+            .map(|entry| entry.value().clone())
+            .collect::<Vec<CommandAccess>>();
+
+        access.sort_by(|a, b| a.command.cmp(&b.command));
+
+        Ok(access)
+    }
+
+    async fn upsert_command_access(&self, access: CommandAccess) -> Result<(), StorageError> {
+        self.command_access.insert((access.guild_id, access.command.clone()), access);
+
+        Ok(())
+    }
+
+    async fn delete_command_access(&self, guild_id: Id<GuildMarker>, command: String) -> Result<(), StorageError> {
+        self.command_access.remove(&(guild_id, command));
+
+        Ok(())
+    }
+
+    async fn read_channel_scan_depths(&self, guild_id: Id<GuildMarker>) -> Result<HashMap<Id<ChannelMarker>, u16>, StorageError> {
+        Ok(self.channel_scan_depths
+            .iter()
+            .filter(|entry| entry.key().0 == guild_id)
+            .map(|entry| (entry.key().1, *entry.value()))
+            .collect())
+    }
+
+    async fn update_channel_scan_depth(&self, guild_id: Id<GuildMarker>, channel_id: Id<ChannelMarker>, scan_depth: Option<u16>) -> Result<(), StorageError> {
+        match scan_depth {
+            Some(scan_depth) => self.channel_scan_depths.insert((guild_id, channel_id), scan_depth),
+            None => self.channel_scan_depths.remove(&(guild_id, channel_id)).map(|(_, scan_depth)| scan_depth)
+        };
+
+        Ok(())
+    }
+
+    async fn create_check_run(&self, guild_id: Id<GuildMarker>, user_id: Id<UserMarker>) -> Result<i64, StorageError> {
+        let id = self.last_check_run_id.fetch_add(1, Ordering::SeqCst) + 1;
+
+        self.check_runs.insert(id, CheckRun {
+            id,
+            guild_id,
+            user_id,
+            status: CheckStatus::Running,
+            started_at: Utc::now().naive_utc(),
+            finished_at: None
+        });
+
+        Ok(id)
+    }
+
+    async fn create_category_result(&self, run_id: i64, position: usize, result: CategoryResult) -> Result<(), StorageError> {
+        self.category_results.entry(run_id).or_default().insert(position, result);
+
+        Ok(())
+    }
+
+    async fn finish_check_run(&self, run_id: i64, status: CheckStatus) -> Result<(), StorageError> {
+        if let Some(mut run) = self.check_runs.get_mut(&run_id) {
+            run.status = status;
+            run.finished_at = Some(Utc::now().naive_utc());
+        }
+
+        Ok(())
+    }
+
+    async fn abort_check_runs(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError> {
+        for mut run in self.check_runs.iter_mut() {
+            if run.guild_id == guild_id && run.status == CheckStatus::Running {
+                run.status = CheckStatus::Aborted;
+                run.finished_at = Some(Utc::now().naive_utc());
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn read_check_run(&self, guild_id: Id<GuildMarker>, run_id: i64) -> Result<Option<CheckRun>, StorageError> {
+        Ok(self.check_runs
+            .get(&run_id)
+            .filter(|run| run.guild_id == guild_id)
+            .map(|run| run.clone()))
+    }
+
+    async fn read_previous_check_run(&self, guild_id: Id<GuildMarker>, run_id: i64) -> Result<Option<CheckRun>, StorageError> {
+        Ok(self.check_runs
+            .iter()
+            .filter(|run| run.guild_id == guild_id && run.id < run_id && run.status == CheckStatus::Completed)
+            .max_by_key(|run| run.id)
+            .map(|run| run.clone()))
+    }
+
+    async fn read_check_runs(&self, guild_id: Id<GuildMarker>, amount: u16) -> Result<Vec<(CheckRun, CheckTotals)>, StorageError> {
+        let mut runs = self.check_runs
+            .iter()
+            .filter(|run| run.guild_id == guild_id)
+            .map(|run| run.clone())
+            .collect::<Vec<CheckRun>>();
+
+        runs.sort_by_key(|run| Reverse((run.started_at, run.id)));
+        runs.truncate(amount as usize);
+
+        let mut summaries = vec![];
+
+        for run in runs {
+            let category_results = self.read_category_results(run.id).await?;
+
+            summaries.push((run, CheckTotals::from(category_results.as_slice())));
+        }
+
+        Ok(summaries)
+    }
+
+    async fn read_category_results(&self, run_id: i64) -> Result<Vec<CategoryResult>, StorageError> {
+        Ok(self.category_results
+            .get(&run_id)
+            .map(|results| results.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn count_unchecked_codes(&self) -> Result<u64, StorageError> {
+        Ok(self.invites.iter().filter(|invite| !invite.is_checked).count() as u64)
+    }
+
+    async fn create_invites(&self, guild_id: Id<GuildMarker>, codes: HashSet<String>) -> Result<IngestReport, StorageError> {
+        let now = Utc::now().naive_utc();
+        let mut report = IngestReport::default();
+
+        for code in codes {
+            match self.invites.entry((guild_id, code.clone())) {
+                Entry::Occupied(_) => report.known += 1,
+                Entry::Vacant(entry) => {
+                    report.new += 1;
+                    entry.insert(Invite {
+                        guild_id,
+                        code,
+                        expires_at: None,
+                        is_permanent: None,
+                        is_valid: None,
+                        is_checked: false,
+                        created_at: now,
+                        updated_at: now
+                    });
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    async fn read_checked_codes(&self, amount: u16) -> Result<HashSet<Code>, StorageError> {
+        let mut invites = self.invites
+            .iter()
+            .filter(|invite| invite.is_checked && invite.is_valid == Some(true))
+            .map(|invite| (invite.updated_at, Code { guild_id: invite.guild_id, code: invite.code.clone() }))
+            .collect::<Vec<_>>();
+        invites.sort_by_key(|(updated_at, _)| *updated_at);
+
+        Ok(invites.into_iter().take(amount as usize).map(|(_, code)| code).collect())
+    }
+
+    async fn read_guild_invites(&self, guild_id: Id<GuildMarker>) -> Result<HashMap<String, Invite>, StorageError> {
+        Ok(self.invites
+            .iter()
+            .filter(|invite| invite.guild_id == guild_id)
+            .map(|invite| (invite.code.clone(), invite.clone()))
+            .collect())
+    }
+
+    async fn read_unchecked_codes(&self, amount: u16) -> Result<HashSet<Code>, StorageError> {
+        let mut invites = self.invites
+            .iter()
+            .filter(|invite| !invite.is_checked)
+            .map(|invite| (invite.created_at, Code { guild_id: invite.guild_id, code: invite.code.clone() }))
+            .collect::<Vec<_>>();
+        invites.sort_by_key(|(created_at, _)| *created_at);
+
+        Ok(invites.into_iter().take(amount as usize).map(|(_, code)| code).collect())
+    }
+
+    async fn upsert_code(&self, guild_id: Id<GuildMarker>, code: String, expires_at: Option<Timestamp>, is_permanent: bool, is_valid: bool) -> Result<(), StorageError> {
+        let now = Utc::now().naive_utc();
+        let expires_at = expires_at
+            .and_then(|timestamp| DateTime::from_timestamp(timestamp.as_secs(), 0))
+            .map(|date_time| date_time.naive_utc());
+
+        self.invites
+            .entry((guild_id, code.clone()))
+            .and_modify(|invite| {
+                invite.expires_at = expires_at;
+                invite.is_permanent = Some(is_permanent);
+                invite.is_valid = Some(is_valid);
+                invite.is_checked = true;
+                invite.updated_at = now;
+            })
+            .or_insert_with(|| Invite {
+                guild_id,
+                code,
+                expires_at,
+                is_permanent: Some(is_permanent),
+                is_valid: Some(is_valid),
+                is_checked: true,
+                created_at: now,
+                updated_at: now
+            });
+
+        Ok(())
+    }
+}
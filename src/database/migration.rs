@@ -0,0 +1,356 @@
+use std::{error::Error, fmt};
+use super::StorageError;
+
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub sql: &'static str
+}
+
+pub const POSTGRES_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_setting_and_invite",
+        sql: "
+            CREATE TABLE IF NOT EXISTS public.setting (
+                guild_id INT8 NOT NULL,
+                results_channel_id INT8,
+                category_channel_ids INT8[] NOT NULL DEFAULT '{}',
+                ignored_channel_ids INT8[] NOT NULL DEFAULT '{}',
+                embed_color INT4 NOT NULL DEFAULT 16316671,
+                last_check TIMESTAMP(3),
+                in_check BOOLEAN NOT NULL DEFAULT FALSE,
+                CONSTRAINT pk_setting PRIMARY KEY (guild_id)
+            );
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_setting_guild_id ON public.setting USING btree (guild_id);
+            CREATE TABLE IF NOT EXISTS public.invite (
+                guild_id INT8 NOT NULL,
+                code TEXT NOT NULL,
+                expires_at TIMESTAMP(3),
+                is_permanent BOOLEAN,
+                is_valid BOOLEAN,
+                is_checked BOOLEAN NOT NULL DEFAULT FALSE,
+                created_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                updated_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                CONSTRAINT ck_invite PRIMARY KEY (guild_id, code)
+            );
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_invite_guild_id_code ON public.invite USING btree (guild_id, code);
+        "
+    },
+    Migration {
+        version: 2,
+        name: "create_command_access",
+        sql: "
+            CREATE TABLE public.command_access (
+                guild_id INT8 NOT NULL,
+                command TEXT NOT NULL,
+                role_ids INT8[] NOT NULL DEFAULT '{}',
+                permissions INT8 NOT NULL DEFAULT 0,
+                CONSTRAINT pk_command_access PRIMARY KEY (guild_id, command)
+            );
+        "
+    },
+    Migration {
+        version: 3,
+        name: "add_setting_check_started_at",
+        sql: "ALTER TABLE public.setting ADD COLUMN check_started_at TIMESTAMP(3);"
+    },
+    Migration {
+        version: 4,
+        name: "create_check_results",
+        sql: "
+            CREATE TABLE public.check_run (
+                id INT8 GENERATED ALWAYS AS IDENTITY,
+                guild_id INT8 NOT NULL,
+                user_id INT8 NOT NULL,
+                status TEXT NOT NULL DEFAULT 'running',
+                started_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                finished_at TIMESTAMP(3),
+                CONSTRAINT pk_check_run PRIMARY KEY (id)
+            );
+            CREATE INDEX idx_check_run_guild_id_started_at ON public.check_run USING btree (guild_id, started_at DESC);
+            CREATE TABLE public.check_category_result (
+                run_id INT8 NOT NULL,
+                position INT4 NOT NULL,
+                category_id INT8 NOT NULL,
+                name TEXT NOT NULL,
+                issue_channel_ids INT8[] NOT NULL DEFAULT '{}',
+                manual_channel_ids INT8[] NOT NULL DEFAULT '{}',
+                CONSTRAINT pk_check_category_result PRIMARY KEY (run_id, position),
+                CONSTRAINT fk_check_category_result_run FOREIGN KEY (run_id) REFERENCES public.check_run (id) ON DELETE CASCADE
+            );
+            CREATE TABLE public.check_channel_result (
+                run_id INT8 NOT NULL,
+                category_position INT4 NOT NULL,
+                position INT4 NOT NULL,
+                channel_id INT8 NOT NULL,
+                good INT4 NOT NULL DEFAULT 0,
+                bad INT4 NOT NULL DEFAULT 0,
+                unresolved INT4 NOT NULL DEFAULT 0,
+                CONSTRAINT pk_check_channel_result PRIMARY KEY (run_id, category_position, position),
+                CONSTRAINT fk_check_channel_result_category FOREIGN KEY (run_id, category_position)
+                    REFERENCES public.check_category_result (run_id, position) ON DELETE CASCADE
+            );
+        "
+    },
+    Migration {
+        version: 5,
+        name: "add_check_channel_result_codes",
+        sql: "
+            ALTER TABLE public.check_channel_result
+                ADD COLUMN good_codes TEXT[] NOT NULL DEFAULT '{}',
+                ADD COLUMN bad_codes TEXT[] NOT NULL DEFAULT '{}';
+        "
+    },
+    Migration {
+        version: 6,
+        name: "create_check_invite_result",
+        sql: "
+            CREATE TABLE public.check_invite_result (
+                run_id INT8 NOT NULL,
+                category_position INT4 NOT NULL,
+                channel_position INT4 NOT NULL,
+                position INT4 NOT NULL,
+                message_id INT8 NOT NULL,
+                code TEXT NOT NULL,
+                status TEXT NOT NULL,
+                is_permanent BOOL,
+                expires_at TIMESTAMP(3),
+                CONSTRAINT pk_check_invite_result PRIMARY KEY (run_id, category_position, channel_position, position),
+                CONSTRAINT fk_check_invite_result_channel FOREIGN KEY (run_id, category_position, channel_position)
+                    REFERENCES public.check_channel_result (run_id, category_position, position) ON DELETE CASCADE
+            );
+        "
+    },
+    Migration {
+        version: 7,
+        name: "add_scan_depth",
+        sql: "
+            ALTER TABLE public.setting ADD COLUMN scan_depth INT4 NOT NULL DEFAULT 15;
+            ALTER TABLE public.check_channel_result ADD COLUMN messages INT4 NOT NULL DEFAULT 0;
+            CREATE TABLE public.channel_scan_depth (
+                guild_id INT8 NOT NULL,
+                channel_id INT8 NOT NULL,
+                scan_depth INT4 NOT NULL,
+                CONSTRAINT pk_channel_scan_depth PRIMARY KEY (guild_id, channel_id)
+            );
+        "
+    },
+    Migration {
+        version: 8,
+        name: "add_setting_check_cooldown",
+        sql: "ALTER TABLE public.setting ADD COLUMN check_cooldown INT4;"
+    },
+    Migration {
+        version: 9,
+        name: "add_setting_schedule",
+        sql: "
+            ALTER TABLE public.setting
+                ADD COLUMN schedule TEXT,
+                ADD COLUMN next_scheduled_check TIMESTAMP(3);
+            CREATE INDEX idx_setting_next_scheduled_check ON public.setting USING btree (next_scheduled_check);
+        "
+    },
+    Migration {
+        version: 10,
+        name: "drop_check_channel_result_codes",
+        sql: "
+            ALTER TABLE public.check_channel_result
+                DROP COLUMN good_codes,
+                DROP COLUMN bad_codes;
+        "
+    }
+];
+
+pub const SQLITE_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_setting_and_invite",
+        sql: "
+            CREATE TABLE IF NOT EXISTS setting (
+                guild_id INTEGER NOT NULL,
+                results_channel_id INTEGER,
+                category_channel_ids TEXT NOT NULL DEFAULT '[]',
+                ignored_channel_ids TEXT NOT NULL DEFAULT '[]',
+                embed_color INTEGER NOT NULL DEFAULT 16316671,
+                last_check TEXT,
+                in_check INTEGER NOT NULL DEFAULT 0,
+                CONSTRAINT pk_setting PRIMARY KEY (guild_id)
+            );
+            CREATE TABLE IF NOT EXISTS invite (
+                guild_id INTEGER NOT NULL,
+                code TEXT NOT NULL,
+                expires_at TEXT,
+                is_permanent INTEGER,
+                is_valid INTEGER,
+                is_checked INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+                updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+                CONSTRAINT ck_invite PRIMARY KEY (guild_id, code)
+            );
+        "
+    },
+    Migration {
+        version: 2,
+        name: "create_command_access",
+        sql: "
+            CREATE TABLE command_access (
+                guild_id INTEGER NOT NULL,
+                command TEXT NOT NULL,
+                role_ids TEXT NOT NULL DEFAULT '[]',
+                permissions INTEGER NOT NULL DEFAULT 0,
+                CONSTRAINT pk_command_access PRIMARY KEY (guild_id, command)
+            );
+        "
+    },
+    Migration {
+        version: 3,
+        name: "add_setting_check_started_at",
+        sql: "ALTER TABLE setting ADD COLUMN check_started_at TEXT;"
+    },
+    // Foreign keys aren't enforced without PRAGMA foreign_keys, so deletes clean up every table themselves
+    Migration {
+        version: 4,
+        name: "create_check_results",
+        sql: "
+            CREATE TABLE check_run (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                guild_id INTEGER NOT NULL,
+                user_id INTEGER NOT NULL,
+                status TEXT NOT NULL DEFAULT 'running',
+                started_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+                finished_at TEXT
+            );
+            CREATE INDEX idx_check_run_guild_id_started_at ON check_run (guild_id, started_at DESC);
+            CREATE TABLE check_category_result (
+                run_id INTEGER NOT NULL,
+                position INTEGER NOT NULL,
+                category_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                issue_channel_ids TEXT NOT NULL DEFAULT '[]',
+                manual_channel_ids TEXT NOT NULL DEFAULT '[]',
+                CONSTRAINT pk_check_category_result PRIMARY KEY (run_id, position)
+            );
+            CREATE TABLE check_channel_result (
+                run_id INTEGER NOT NULL,
+                category_position INTEGER NOT NULL,
+                position INTEGER NOT NULL,
+                channel_id INTEGER NOT NULL,
+                good INTEGER NOT NULL DEFAULT 0,
+                bad INTEGER NOT NULL DEFAULT 0,
+                unresolved INTEGER NOT NULL DEFAULT 0,
+                CONSTRAINT pk_check_channel_result PRIMARY KEY (run_id, category_position, position)
+            );
+        "
+    },
+    Migration {
+        version: 5,
+        name: "add_check_channel_result_codes",
+        sql: "
+            ALTER TABLE check_channel_result ADD COLUMN good_codes TEXT NOT NULL DEFAULT '[]';
+            ALTER TABLE check_channel_result ADD COLUMN bad_codes TEXT NOT NULL DEFAULT '[]';
+        "
+    },
+    Migration {
+        version: 6,
+        name: "create_check_invite_result",
+        sql: "
+            CREATE TABLE check_invite_result (
+                run_id INTEGER NOT NULL,
+                category_position INTEGER NOT NULL,
+                channel_position INTEGER NOT NULL,
+                position INTEGER NOT NULL,
+                message_id INTEGER NOT NULL,
+                code TEXT NOT NULL,
+                status TEXT NOT NULL,
+                is_permanent INTEGER,
+                expires_at TEXT,
+                CONSTRAINT pk_check_invite_result PRIMARY KEY (run_id, category_position, channel_position, position)
+            );
+        "
+    },
+    Migration {
+        version: 7,
+        name: "add_scan_depth",
+        sql: "
+            ALTER TABLE setting ADD COLUMN scan_depth INTEGER NOT NULL DEFAULT 15;
+            ALTER TABLE check_channel_result ADD COLUMN messages INTEGER NOT NULL DEFAULT 0;
+            CREATE TABLE channel_scan_depth (
+                guild_id INTEGER NOT NULL,
+                channel_id INTEGER NOT NULL,
+                scan_depth INTEGER NOT NULL,
+                CONSTRAINT pk_channel_scan_depth PRIMARY KEY (guild_id, channel_id)
+            );
+        "
+    },
+    Migration {
+        version: 8,
+        name: "add_setting_check_cooldown",
+        sql: "ALTER TABLE setting ADD COLUMN check_cooldown INTEGER;"
+    },
+    Migration {
+        version: 9,
+        name: "add_setting_schedule",
+        sql: "
+            ALTER TABLE setting ADD COLUMN schedule TEXT;
+            ALTER TABLE setting ADD COLUMN next_scheduled_check TEXT;
+            CREATE INDEX idx_setting_next_scheduled_check ON setting (next_scheduled_check);
+        "
+    },
+    Migration {
+        version: 10,
+        name: "drop_check_channel_result_codes",
+        sql: "
+            ALTER TABLE check_channel_result DROP COLUMN good_codes;
+            ALTER TABLE check_channel_result DROP COLUMN bad_codes;
+        "
+    }
+];
+
+#[derive(Debug)]
+pub enum MigrationError {
+    SchemaTooNew { database_version: i32, latest_version: i32 },
+    Storage(StorageError)
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SchemaTooNew { database_version, latest_version } => write!(
+                f,
+                "database schema is at version {database_version}, but this build only knows up to version {latest_version}"
+            ),
+            Self::Storage(error) => write!(f, "could not run migrations: {error}")
+        }
+    }
+}
+
+impl Error for MigrationError {}
+
+impl From<StorageError> for MigrationError {
+    fn from(error: StorageError) -> Self {
+        Self::Storage(error)
+    }
+}
+
+impl From<deadpool_postgres::PoolError> for MigrationError {
+    fn from(error: deadpool_postgres::PoolError) -> Self {
+        Self::Storage(error.into())
+    }
+}
+
+impl From<tokio_postgres::Error> for MigrationError {
+    fn from(error: tokio_postgres::Error) -> Self {
+        Self::Storage(error.into())
+    }
+}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(error: rusqlite::Error) -> Self {
+        Self::Storage(error.into())
+    }
+}
+
+pub fn latest_version(migrations: &[Migration]) -> i32 {
+    migrations.last().map_or(0, |migration| migration.version)
+}
@@ -1,63 +1,151 @@
-pub mod setting;
+pub mod access;
+pub mod check;
 pub mod invite;
+pub mod memory;
+pub mod migration;
+pub mod postgres;
+pub mod setting;
+pub mod sqlite;
 
-use crate::constants::DATABASE_URL;
-use deadpool_postgres::{Client, Manager, ManagerConfig, Pool, RecyclingMethod};
-use std::str::FromStr;
-use tokio_postgres::{Config, NoTls};
+use access::CommandAccess;
+use async_trait::async_trait;
+use check::{CategoryResult, CheckRun, CheckStatus, CheckTotals};
+use chrono::NaiveDateTime;
+use dashmap::DashSet;
+use deadpool_postgres::PoolError;
+use invite::{Code, IngestReport, Invite};
+use memory::MemoryStorage;
+use migration::{Migration, MigrationError};
+use postgres::PostgresStorage;
+use setting::{CheckSchedule, Setting};
+use sqlite::SqliteStorage;
+use std::{collections::{HashMap, HashSet}, error::Error, fmt, sync::Arc, time::Duration};
+use twilight_model::{
+    datetime::Timestamp,
+    id::{Id, marker::{ChannelMarker, GuildMarker, UserMarker}}
+};
 
-pub struct Database {
-    pool: Pool
+#[derive(Debug)]
+pub enum StorageError {
+    MissingSetting(Id<GuildMarker>),
+    Pool(PoolError),
+    Postgres(tokio_postgres::Error),
+    Setup(String),
+    Sqlite(rusqlite::Error)
 }
 
-impl Database {
-    pub fn new() -> Self {
-        let pool = Pool::builder(Manager::from_config(
-            Config::from_str(&DATABASE_URL).unwrap(),
-            NoTls,
-            ManagerConfig { recycling_method: RecyclingMethod::Fast }
-        ))
-            .max_size(16)
-            .build()
-            .unwrap();
-
-        Self {
-            pool
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSetting(guild_id) => write!(f, "no setting exists for guild {guild_id}"),
+            Self::Pool(error) => write!(f, "could not get a database connection: {error}"),
+            Self::Postgres(error) => write!(f, "postgres error: {error}"),
+            Self::Setup(message) => write!(f, "could not set up storage: {message}"),
+            Self::Sqlite(error) => write!(f, "sqlite error: {error}")
         }
     }
+}
+
+impl Error for StorageError {}
+
+impl From<PoolError> for StorageError {
+    fn from(error: PoolError) -> Self {
+        Self::Pool(error)
+    }
+}
+
+impl From<tokio_postgres::Error> for StorageError {
+    fn from(error: tokio_postgres::Error) -> Self {
+        Self::Postgres(error)
+    }
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(error: rusqlite::Error) -> Self {
+        Self::Sqlite(error)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PoolStatus {
+    pub max_size: usize,
+    pub size: usize,
+    // Negative when callers are waiting for a connection
+    pub available: isize
+}
+
+// Upper bound on the number of codes written by a single INSERT
+pub const INVITE_BATCH_SIZE: usize = 1_000;
 
-    async fn get_object(&self) -> Client {
-        self.pool.get().await.unwrap()
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn migrate(&self) -> Result<Vec<&'static Migration>, MigrationError>;
+
+    // Only pooled backends have anything to report
+    fn pool_status(&self) -> Option<PoolStatus> {
+        None
     }
 
-    pub async fn create_tables(&self) {
-        let client = self.get_object().await;
-        let query = "
-            CREATE TABLE IF NOT EXISTS public.setting (
-                guild_id INT8 NOT NULL,
-                results_channel_id INT8,
-                category_channel_ids INT8[] NOT NULL DEFAULT '{}',
-                ignored_channel_ids INT8[] NOT NULL DEFAULT '{}',
-                embed_color INT4 NOT NULL DEFAULT 16316671,
-                last_check TIMESTAMP(3),
-                in_check BOOLEAN NOT NULL DEFAULT FALSE,
-                CONSTRAINT pk_setting PRIMARY KEY (guild_id)
-            );
-            CREATE UNIQUE INDEX IF NOT EXISTS idx_setting_guild_id ON public.setting USING btree (guild_id);
-            CREATE TABLE IF NOT EXISTS public.invite (
-                guild_id INT8 NOT NULL,
-                code TEXT NOT NULL,
-                expires_at TIMESTAMP(3),
-                is_permanent BOOLEAN,
-                is_valid BOOLEAN,
-                is_checked BOOLEAN NOT NULL DEFAULT FALSE,
-                created_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
-                updated_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
-                CONSTRAINT ck_invite PRIMARY KEY (guild_id, code)
-            );
-            CREATE UNIQUE INDEX IF NOT EXISTS idx_invite_guild_id_code ON public.invite USING btree (guild_id, code);
-        ";
-        
-        client.batch_execute(query).await.unwrap();
+    async fn ping(&self) -> Result<(), StorageError>;
+
+    async fn create_setting(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError>;
+    async fn delete_channel(&self, guild_id: Id<GuildMarker>, channel_id: Id<ChannelMarker>) -> Result<(), StorageError>;
+    async fn read_category_channel_ids(&self, guild_id: Id<GuildMarker>) -> Result<DashSet<Id<ChannelMarker>>, StorageError>;
+    async fn read_ignored_channel_ids(&self, guild_id: Id<GuildMarker>) -> Result<DashSet<Id<ChannelMarker>>, StorageError>;
+    async fn read_setting(&self, guild_id: Id<GuildMarker>) -> Result<Option<Setting>, StorageError>;
+    async fn update_results_channel_id(&self, guild_id: Id<GuildMarker>, channel_id: Option<Id<ChannelMarker>>) -> Result<(), StorageError>;
+    async fn update_category_channel_ids(&self, guild_id: Id<GuildMarker>, channel_ids: DashSet<Id<ChannelMarker>>) -> Result<(), StorageError>;
+    async fn update_ignored_channel_ids(&self, guild_id: Id<GuildMarker>, channel_ids: DashSet<Id<ChannelMarker>>) -> Result<(), StorageError>;
+    async fn update_embed_color(&self, guild_id: Id<GuildMarker>, color: u32) -> Result<(), StorageError>;
+    async fn update_scan_depth(&self, guild_id: Id<GuildMarker>, scan_depth: u16) -> Result<(), StorageError>;
+    async fn update_check_cooldown(&self, guild_id: Id<GuildMarker>, check_cooldown: Option<Duration>) -> Result<(), StorageError>;
+    async fn update_last_check(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError>;
+    async fn clear_last_check(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError>;
+    async fn update_schedule(&self, guild_id: Id<GuildMarker>, schedule: Option<CheckSchedule>, next_scheduled_check: Option<NaiveDateTime>) -> Result<(), StorageError>;
+    // Settings with a scheduled check due at or before `now`
+    async fn read_due_schedules(&self, now: NaiveDateTime) -> Result<Vec<Setting>, StorageError>;
+    // Taking the lock also records check_started_at, releasing it clears it
+    // Only changes a lock that isn't already in that state, and returns whether it did, so two checks can't both take it
+    async fn update_in_check(&self, guild_id: Id<GuildMarker>, in_check: bool) -> Result<bool, StorageError>;
+    // Settings still in a check, limited to checks started before `started_before` when given
+    async fn read_interrupted_checks(&self, started_before: Option<NaiveDateTime>) -> Result<Vec<Setting>, StorageError>;
+    async fn recover_check(&self, guild_id: Id<GuildMarker>, clear_last_check: bool) -> Result<(), StorageError>;
+    async fn delete_setting(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError>;
+
+    async fn read_command_access(&self, guild_id: Id<GuildMarker>) -> Result<Vec<CommandAccess>, StorageError>;
+    async fn upsert_command_access(&self, access: CommandAccess) -> Result<(), StorageError>;
+    async fn delete_command_access(&self, guild_id: Id<GuildMarker>, command: String) -> Result<(), StorageError>;
+
+    async fn read_channel_scan_depths(&self, guild_id: Id<GuildMarker>) -> Result<HashMap<Id<ChannelMarker>, u16>, StorageError>;
+    // None removes the override, so the channel goes back to the guild's scan depth
+    async fn update_channel_scan_depth(&self, guild_id: Id<GuildMarker>, channel_id: Id<ChannelMarker>, scan_depth: Option<u16>) -> Result<(), StorageError>;
+
+    async fn create_check_run(&self, guild_id: Id<GuildMarker>, user_id: Id<UserMarker>) -> Result<i64, StorageError>;
+    // Written once per category as the check goes, so a check that dies midway keeps what it had finished
+    async fn create_category_result(&self, run_id: i64, position: usize, result: CategoryResult) -> Result<(), StorageError>;
+    async fn finish_check_run(&self, run_id: i64, status: CheckStatus) -> Result<(), StorageError>;
+    // Marks the guild's running checks as aborted, for when the process that ran them is gone
+    async fn abort_check_runs(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError>;
+    async fn read_check_run(&self, guild_id: Id<GuildMarker>, run_id: i64) -> Result<Option<CheckRun>, StorageError>;
+    // The last run in the guild to complete before the given one
+    async fn read_previous_check_run(&self, guild_id: Id<GuildMarker>, run_id: i64) -> Result<Option<CheckRun>, StorageError>;
+    // The guild's most recent runs first, each with its totals summed up
+    async fn read_check_runs(&self, guild_id: Id<GuildMarker>, amount: u16) -> Result<Vec<(CheckRun, CheckTotals)>, StorageError>;
+    async fn read_category_results(&self, run_id: i64) -> Result<Vec<CategoryResult>, StorageError>;
+
+    async fn count_unchecked_codes(&self) -> Result<u64, StorageError>;
+    async fn create_invites(&self, guild_id: Id<GuildMarker>, codes: HashSet<String>) -> Result<IngestReport, StorageError>;
+    async fn read_checked_codes(&self, amount: u16) -> Result<HashSet<Code>, StorageError>;
+    async fn read_guild_invites(&self, guild_id: Id<GuildMarker>) -> Result<HashMap<String, Invite>, StorageError>;
+    async fn read_unchecked_codes(&self, amount: u16) -> Result<HashSet<Code>, StorageError>;
+    async fn upsert_code(&self, guild_id: Id<GuildMarker>, code: String, expires_at: Option<Timestamp>, is_permanent: bool, is_valid: bool) -> Result<(), StorageError>;
+}
+
+pub fn connect(url: &str) -> Result<Arc<dyn Storage>, StorageError> {
+    match url.split_once(':').map(|(scheme, _)| scheme) {
+        Some("postgres" | "postgresql") => Ok(Arc::new(PostgresStorage::new(url)?)),
+        Some("sqlite") => Ok(Arc::new(SqliteStorage::new(url)?)),
+        Some("memory") => Ok(Arc::new(MemoryStorage::new())),
+        _ => Err(StorageError::Setup(format!("unsupported DATABASE_URL scheme in \"{url}\"")))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,674 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime};
+use dashmap::DashSet;
+use deadpool_postgres::{Client, Manager, ManagerConfig, Pool, RecyclingMethod};
+use std::{collections::{HashMap, HashSet}, str::FromStr, time::Duration};
+use super::{
+    access::CommandAccess,
+    check::{CategoryResult, ChannelResult, CheckRun, CheckStatus, CheckTotals, InviteResult},
+    invite::{Code, IngestReport, Invite},
+    migration::{latest_version, Migration, MigrationError, POSTGRES_MIGRATIONS},
+    setting::{CheckSchedule, Setting},
+    PoolStatus,
+    Storage,
+    StorageError,
+    INVITE_BATCH_SIZE
+};
+use tokio_postgres::{Config, NoTls};
+use twilight_model::{
+    datetime::Timestamp,
+    id::{Id, marker::{ChannelMarker, GuildMarker, UserMarker}}
+};
+
+// "SAKURA" in ASCII, used as the advisory lock key so two instances starting at once don't both migrate
+const MIGRATION_LOCK_ID: i64 = 0x5341_4B55_5241;
+
+pub struct PostgresStorage {
+    pool: Pool
+}
+
+impl PostgresStorage {
+    pub fn new(url: &str) -> Result<Self, StorageError> {
+        let pool = Pool::builder(Manager::from_config(
+            Config::from_str(url)?,
+            NoTls,
+            ManagerConfig { recycling_method: RecyclingMethod::Fast }
+        ))
+            .max_size(16)
+            .build()
+            .map_err(|error| StorageError::Setup(error.to_string()))?;
+
+        Ok(Self {
+            pool
+        })
+    }
+
+    async fn get_object(&self) -> Result<Client, StorageError> {
+        Ok(self.pool.get().await?)
+    }
+}
+
+fn to_naive_date_time(timestamp: Option<Timestamp>) -> Option<NaiveDateTime> {
+    timestamp
+        .and_then(|timestamp| DateTime::from_timestamp(timestamp.as_secs(), 0))
+        .map(|date_time| date_time.naive_utc())
+}
+
+#[async_trait]
+impl Storage for PostgresStorage {
+    fn pool_status(&self) -> Option<PoolStatus> {
+        let status = self.pool.status();
+
+        Some(PoolStatus {
+            max_size: status.max_size,
+            size: status.size,
+            available: status.available
+        })
+    }
+
+    async fn migrate(&self) -> Result<Vec<&'static Migration>, MigrationError> {
+        let mut client = self.get_object().await?;
+        let transaction = client.transaction().await?;
+
+        transaction.execute("SELECT pg_advisory_xact_lock($1);", &[&MIGRATION_LOCK_ID]).await?;
+        transaction.batch_execute("
+            CREATE TABLE IF NOT EXISTS public.schema_version (
+                version INT4 NOT NULL,
+                name TEXT NOT NULL,
+                applied_at TIMESTAMP(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                CONSTRAINT pk_schema_version PRIMARY KEY (version)
+            );
+        ").await?;
+
+        let row = transaction.query_one("SELECT COALESCE(MAX(version), 0) FROM schema_version;", &[]).await?;
+        let database_version: i32 = row.get(0);
+        let latest_version = latest_version(POSTGRES_MIGRATIONS);
+
+        if database_version > latest_version {
+            return Err(MigrationError::SchemaTooNew { database_version, latest_version })
+        }
+
+        let pending = POSTGRES_MIGRATIONS.iter().filter(|migration| migration.version > database_version).collect::<Vec<_>>();
+
+        for migration in &pending {
+            transaction.batch_execute(migration.sql).await?;
+            transaction.execute(
+                "INSERT INTO schema_version(version, name) VALUES($1, $2);",
+                &[&migration.version, &migration.name]
+            ).await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(pending)
+    }
+
+    async fn ping(&self) -> Result<(), StorageError> {
+        self.get_object().await?.simple_query("SELECT 1;").await?;
+
+        Ok(())
+    }
+
+    async fn create_setting(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError> {
+        let client = self.get_object().await?;
+        let query = "INSERT INTO setting(guild_id) VALUES($1) ON CONFLICT DO NOTHING;";
+
+        client.query(query, &[&(guild_id.get() as i64)]).await?;
+
+        Ok(())
+    }
+
+    async fn delete_channel(&self, guild_id: Id<GuildMarker>, channel_id: Id<ChannelMarker>) -> Result<(), StorageError> {
+        let mut client = self.get_object().await?;
+        let transaction = client.transaction().await?;
+        let row = transaction.query_opt("SELECT * FROM setting WHERE guild_id = $1 FOR UPDATE;", &[&(guild_id.get() as i64)]).await?;
+
+        if let Some(setting) = row.map(Setting::from) {
+            let query = "UPDATE setting SET category_channel_ids = $1, ignored_channel_ids = $2, results_channel_id = $3 WHERE guild_id = $4;";
+            let results_channel_id = match setting.results_channel_id {
+                Some(results_channel_id) => if results_channel_id == channel_id { None::<i64> } else { Some(results_channel_id.get() as i64) },
+                None => None,
+            };
+
+            setting.category_channel_ids.remove(&channel_id);
+            setting.ignored_channel_ids.remove(&channel_id);
+            transaction.execute(
+                query,
+                &[
+                    &setting.category_channel_ids.into_iter().map(|id| id.get() as i64).collect::<Vec<i64>>(),
+                    &setting.ignored_channel_ids.into_iter().map(|id| id.get() as i64).collect::<Vec<i64>>(),
+                    &results_channel_id,
+                    &(guild_id.get() as i64)
+                ]
+            ).await?;
+            transaction.execute(
+                "DELETE FROM channel_scan_depth WHERE guild_id = $1 AND channel_id = $2;",
+                &[&(guild_id.get() as i64), &(channel_id.get() as i64)]
+            ).await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn read_category_channel_ids(&self, guild_id: Id<GuildMarker>) -> Result<DashSet<Id<ChannelMarker>>, StorageError> {
+        let client = self.get_object().await?;
+        let query = "SELECT category_channel_ids FROM setting WHERE guild_id = $1;";
+        let row = client.query_one(query, &[&(guild_id.get() as i64)]).await?;
+
+        Ok(row.get::<_, Vec<i64>>(0).into_iter().map(|id| Id::new(id as u64)).collect())
+    }
+
+    async fn read_ignored_channel_ids(&self, guild_id: Id<GuildMarker>) -> Result<DashSet<Id<ChannelMarker>>, StorageError> {
+        let client = self.get_object().await?;
+        let query = "SELECT ignored_channel_ids FROM setting WHERE guild_id = $1;";
+        let row = client.query_one(query, &[&(guild_id.get() as i64)]).await?;
+
+        Ok(row.get::<_, Vec<i64>>(0).into_iter().map(|id| Id::new(id as u64)).collect())
+    }
+
+    async fn read_setting(&self, guild_id: Id<GuildMarker>) -> Result<Option<Setting>, StorageError> {
+        let client = self.get_object().await?;
+        let query = "SELECT * FROM setting WHERE guild_id = $1;";
+
+        Ok(client.query_opt(query, &[&(guild_id.get() as i64)]).await?.map(Setting::from))
+    }
+
+    async fn update_results_channel_id(&self, guild_id: Id<GuildMarker>, channel_id: Option<Id<ChannelMarker>>) -> Result<(), StorageError> {
+        let client = self.get_object().await?;
+        let query = "UPDATE setting SET results_channel_id = $1 WHERE guild_id = $2;";
+
+        client.query(query, &[&channel_id.map(|id| id.get() as i64), &(guild_id.get() as i64)]).await?;
+
+        Ok(())
+    }
+
+    async fn update_category_channel_ids(&self, guild_id: Id<GuildMarker>, channel_ids: DashSet<Id<ChannelMarker>>) -> Result<(), StorageError> {
+        let client = self.get_object().await?;
+        let query = "UPDATE setting SET category_channel_ids = $1 WHERE guild_id = $2;";
+
+        client.query(
+            query,
+            &[
+                &channel_ids.into_iter().map(|id| id.get() as i64).collect::<Vec<i64>>(),
+                &(guild_id.get() as i64)
+            ]
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn update_ignored_channel_ids(&self, guild_id: Id<GuildMarker>, channel_ids: DashSet<Id<ChannelMarker>>) -> Result<(), StorageError> {
+        let client = self.get_object().await?;
+        let query = "UPDATE setting SET ignored_channel_ids = $1 WHERE guild_id = $2;";
+
+        client.query(
+            query,
+            &[
+                &channel_ids.into_iter().map(|id| id.get() as i64).collect::<Vec<i64>>(),
+                &(guild_id.get() as i64)
+            ]
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn update_embed_color(&self, guild_id: Id<GuildMarker>, color: u32) -> Result<(), StorageError> {
+        let client = self.get_object().await?;
+        let query = "UPDATE setting SET embed_color = $1 WHERE guild_id = $2;";
+
+        client.query(query, &[&(color as i32), &(guild_id.get() as i64)]).await?;
+
+        Ok(())
+    }
+
+    async fn update_scan_depth(&self, guild_id: Id<GuildMarker>, scan_depth: u16) -> Result<(), StorageError> {
+        let client = self.get_object().await?;
+        let query = "UPDATE setting SET scan_depth = $1 WHERE guild_id = $2;";
+
+        client.query(query, &[&(scan_depth as i32), &(guild_id.get() as i64)]).await?;
+
+        Ok(())
+    }
+
+    async fn update_check_cooldown(&self, guild_id: Id<GuildMarker>, check_cooldown: Option<Duration>) -> Result<(), StorageError> {
+        let client = self.get_object().await?;
+        let query = "UPDATE setting SET check_cooldown = $1 WHERE guild_id = $2;";
+
+        client.query(query, &[&check_cooldown.map(|check_cooldown| check_cooldown.as_secs() as i32), &(guild_id.get() as i64)]).await?;
+
+        Ok(())
+    }
+
+    async fn update_last_check(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError> {
+        let client = self.get_object().await?;
+        let query = "UPDATE setting SET last_check = NOW()::TIMESTAMP WHERE guild_id = $1;";
+
+        client.query(query, &[&(guild_id.get() as i64)]).await?;
+
+        Ok(())
+    }
+
+    async fn clear_last_check(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError> {
+        let client = self.get_object().await?;
+        let query = "UPDATE setting SET last_check = NULL WHERE guild_id = $1;";
+
+        client.query(query, &[&(guild_id.get() as i64)]).await?;
+
+        Ok(())
+    }
+
+    async fn update_schedule(&self, guild_id: Id<GuildMarker>, schedule: Option<CheckSchedule>, next_scheduled_check: Option<NaiveDateTime>) -> Result<(), StorageError> {
+        let client = self.get_object().await?;
+        let query = "UPDATE setting SET schedule = $1, next_scheduled_check = $2 WHERE guild_id = $3;";
+
+        client.query(query, &[&schedule.map(|schedule| schedule.to_string()), &next_scheduled_check, &(guild_id.get() as i64)]).await?;
+
+        Ok(())
+    }
+
+    async fn read_due_schedules(&self, now: NaiveDateTime) -> Result<Vec<Setting>, StorageError> {
+        let client = self.get_object().await?;
+        let query = "SELECT * FROM setting WHERE schedule IS NOT NULL AND next_scheduled_check <= $1;";
+        let rows = client.query(query, &[&now]).await?;
+
+        Ok(rows.into_iter().map(Setting::from).collect())
+    }
+
+    async fn update_in_check(&self, guild_id: Id<GuildMarker>, in_check: bool) -> Result<bool, StorageError> {
+        let client = self.get_object().await?;
+        let query = "
+            UPDATE setting
+            SET in_check = $1, check_started_at = CASE WHEN $1 THEN NOW()::TIMESTAMP ELSE NULL END
+            WHERE guild_id = $2 AND in_check <> $1;
+        ";
+        let updated = client.execute(query, &[&in_check, &(guild_id.get() as i64)]).await?;
+
+        Ok(updated > 0)
+    }
+
+    async fn read_interrupted_checks(&self, started_before: Option<NaiveDateTime>) -> Result<Vec<Setting>, StorageError> {
+        let client = self.get_object().await?;
+        let query = "
+            SELECT * FROM setting
+            WHERE in_check = TRUE AND ($1::TIMESTAMP IS NULL OR check_started_at IS NULL OR check_started_at < $1);
+        ";
+        let rows = client.query(query, &[&started_before]).await?;
+
+        Ok(rows.into_iter().map(Setting::from).collect())
+    }
+
+    async fn recover_check(&self, guild_id: Id<GuildMarker>, clear_last_check: bool) -> Result<(), StorageError> {
+        let client = self.get_object().await?;
+        let query = "
+            UPDATE setting
+            SET in_check = FALSE, check_started_at = NULL, last_check = CASE WHEN $1 THEN NULL ELSE last_check END
+            WHERE guild_id = $2;
+        ";
+
+        client.query(query, &[&clear_last_check, &(guild_id.get() as i64)]).await?;
+
+        Ok(())
+    }
+
+    async fn delete_setting(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError> {
+        let mut client = self.get_object().await?;
+        let transaction = client.transaction().await?;
+        let query = "DELETE FROM setting WHERE guild_id = $1;";
+
+        transaction.execute(query, &[&(guild_id.get() as i64)]).await?;
+        transaction.execute("DELETE FROM command_access WHERE guild_id = $1;", &[&(guild_id.get() as i64)]).await?;
+        transaction.execute("DELETE FROM channel_scan_depth WHERE guild_id = $1;", &[&(guild_id.get() as i64)]).await?;
+        transaction.execute("DELETE FROM check_run WHERE guild_id = $1;", &[&(guild_id.get() as i64)]).await?;
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn read_command_access(&self, guild_id: Id<GuildMarker>) -> Result<Vec<CommandAccess>, StorageError> {
+        let client = self.get_object().await?;
+        let query = "SELECT guild_id, command, role_ids, permissions FROM command_access WHERE guild_id = $1 ORDER BY command;";
+        let rows = client.query(query, &[&(guild_id.get() as i64)]).await?;
+
+        Ok(rows.into_iter().map(CommandAccess::from).collect())
+    }
+
+    async fn upsert_command_access(&self, access: CommandAccess) -> Result<(), StorageError> {
+        let client = self.get_object().await?;
+        let query = "
+            INSERT INTO command_access(guild_id, command, role_ids, permissions)
+            VALUES($1, $2, $3, $4)
+            ON CONFLICT (guild_id, command)
+            DO
+            UPDATE SET
+                role_ids = EXCLUDED.role_ids,
+                permissions = EXCLUDED.permissions
+        ";
+
+        client.query(
+            query,
+            &[
+                &(access.guild_id.get() as i64),
+                &access.command,
+                &access.role_ids.into_iter().map(|id| id.get() as i64).collect::<Vec<i64>>(),
+                &(access.permissions.bits() as i64)
+            ]
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn delete_command_access(&self, guild_id: Id<GuildMarker>, command: String) -> Result<(), StorageError> {
+        let client = self.get_object().await?;
+        let query = "DELETE FROM command_access WHERE guild_id = $1 AND command = $2;";
+
+        client.query(query, &[&(guild_id.get() as i64), &command]).await?;
+
+        Ok(())
+    }
+
+    async fn read_channel_scan_depths(&self, guild_id: Id<GuildMarker>) -> Result<HashMap<Id<ChannelMarker>, u16>, StorageError> {
+        let client = self.get_object().await?;
+        let query = "SELECT channel_id, scan_depth FROM channel_scan_depth WHERE guild_id = $1;";
+        let rows = client.query(query, &[&(guild_id.get() as i64)]).await?;
+
+        Ok(rows.into_iter().map(|row| (Id::new(row.get::<_, i64>(0) as u64), row.get::<_, i32>(1) as u16)).collect())
+    }
+
+    async fn update_channel_scan_depth(&self, guild_id: Id<GuildMarker>, channel_id: Id<ChannelMarker>, scan_depth: Option<u16>) -> Result<(), StorageError> {
+        let client = self.get_object().await?;
+
+        match scan_depth {
+            Some(scan_depth) => {
+                let query = "
+                    INSERT INTO channel_scan_depth(guild_id, channel_id, scan_depth)
+                    VALUES($1, $2, $3)
+                    ON CONFLICT (guild_id, channel_id)
+                    DO
+                    UPDATE SET scan_depth = EXCLUDED.scan_depth
+                ";
+
+                client.query(query, &[&(guild_id.get() as i64), &(channel_id.get() as i64), &(scan_depth as i32)]).await?;
+            },
+            None => {
+                let query = "DELETE FROM channel_scan_depth WHERE guild_id = $1 AND channel_id = $2;";
+
+                client.query(query, &[&(guild_id.get() as i64), &(channel_id.get() as i64)]).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn create_check_run(&self, guild_id: Id<GuildMarker>, user_id: Id<UserMarker>) -> Result<i64, StorageError> {
+        let client = self.get_object().await?;
+        let query = "INSERT INTO check_run(guild_id, user_id) VALUES($1, $2) RETURNING id;";
+        let row = client.query_one(query, &[&(guild_id.get() as i64), &(user_id.get() as i64)]).await?;
+
+        Ok(row.get(0))
+    }
+
+    async fn create_category_result(&self, run_id: i64, position: usize, result: CategoryResult) -> Result<(), StorageError> {
+        let mut client = self.get_object().await?;
+        let transaction = client.transaction().await?;
+        let to_ids = |ids: &[Id<ChannelMarker>]| ids.iter().map(|id| id.get() as i64).collect::<Vec<i64>>();
+
+        transaction.execute(
+            "
+                INSERT INTO check_category_result(run_id, position, category_id, name, issue_channel_ids, manual_channel_ids)
+                VALUES($1, $2, $3, $4, $5, $6);
+            ",
+            &[
+                &run_id,
+                &(position as i32),
+                &(result.category_id.get() as i64),
+                &result.name,
+                &to_ids(&result.issue_channel_ids),
+                &to_ids(&result.manual_channel_ids)
+            ]
+        ).await?;
+
+        for (channel_position, channel) in result.channel_results.iter().enumerate() {
+            transaction.execute(
+                "
+                    INSERT INTO check_channel_result(run_id, category_position, position, channel_id, good, bad, unresolved, messages)
+                    VALUES($1, $2, $3, $4, $5, $6, $7, $8);
+                ",
+                &[
+                    &run_id,
+                    &(position as i32),
+                    &(channel_position as i32),
+                    &(channel.channel_id.get() as i64),
+                    &(channel.good as i32),
+                    &(channel.bad as i32),
+                    &(channel.unresolved as i32),
+                    &(channel.messages as i32)
+                ]
+            ).await?;
+
+            for (invite_position, invite) in channel.invites.iter().enumerate() {
+                transaction.execute(
+                    "
+                        INSERT INTO check_invite_result(run_id, category_position, channel_position, position, message_id, code, status, is_permanent, expires_at)
+                        VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9);
+                    ",
+                    &[
+                        &run_id,
+                        &(position as i32),
+                        &(channel_position as i32),
+                        &(invite_position as i32),
+                        &(invite.message_id.get() as i64),
+                        &invite.code,
+                        &invite.status.as_str(),
+                        &invite.is_permanent,
+                        &invite.expires_at
+                    ]
+                ).await?;
+            }
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn finish_check_run(&self, run_id: i64, status: CheckStatus) -> Result<(), StorageError> {
+        let client = self.get_object().await?;
+        let query = "UPDATE check_run SET status = $1, finished_at = NOW()::TIMESTAMP WHERE id = $2;";
+
+        client.query(query, &[&status.as_str(), &run_id]).await?;
+
+        Ok(())
+    }
+
+    async fn abort_check_runs(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError> {
+        let client = self.get_object().await?;
+        let query = "UPDATE check_run SET status = $1, finished_at = NOW()::TIMESTAMP WHERE guild_id = $2 AND status = $3;";
+
+        client.query(
+            query,
+            &[&CheckStatus::Aborted.as_str(), &(guild_id.get() as i64), &CheckStatus::Running.as_str()]
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn read_check_run(&self, guild_id: Id<GuildMarker>, run_id: i64) -> Result<Option<CheckRun>, StorageError> {
+        let client = self.get_object().await?;
+        let query = "SELECT id, guild_id, user_id, status, started_at, finished_at FROM check_run WHERE guild_id = $1 AND id = $2;";
+        let row = client.query_opt(query, &[&(guild_id.get() as i64), &run_id]).await?;
+
+        Ok(row.map(CheckRun::from))
+    }
+
+    async fn read_previous_check_run(&self, guild_id: Id<GuildMarker>, run_id: i64) -> Result<Option<CheckRun>, StorageError> {
+        let client = self.get_object().await?;
+        let query = "
+            SELECT id, guild_id, user_id, status, started_at, finished_at
+            FROM check_run
+            WHERE guild_id = $1 AND id < $2 AND status = $3
+            ORDER BY id DESC
+            LIMIT 1;
+        ";
+        let row = client.query_opt(query, &[&(guild_id.get() as i64), &run_id, &CheckStatus::Completed.as_str()]).await?;
+
+        Ok(row.map(CheckRun::from))
+    }
+
+    async fn read_check_runs(&self, guild_id: Id<GuildMarker>, amount: u16) -> Result<Vec<(CheckRun, CheckTotals)>, StorageError> {
+        let client = self.get_object().await?;
+        let query = "
+            SELECT
+                run.id, run.guild_id, run.user_id, run.status, run.started_at, run.finished_at,
+                (COALESCE(category.channels, 0) + COALESCE(channel.channels, 0))::INT8,
+                COALESCE(channel.good, 0)::INT8,
+                COALESCE(channel.bad, 0)::INT8,
+                COALESCE(channel.unresolved, 0)::INT8
+            FROM check_run run
+            LEFT JOIN (
+                SELECT run_id, SUM(CARDINALITY(issue_channel_ids) + CARDINALITY(manual_channel_ids)) AS channels
+                FROM check_category_result
+                WHERE run_id IN (SELECT id FROM check_run WHERE guild_id = $1)
+                GROUP BY run_id
+            ) category ON category.run_id = run.id
+            LEFT JOIN (
+                SELECT run_id, COUNT(*) AS channels, SUM(good) AS good, SUM(bad) AS bad, SUM(unresolved) AS unresolved
+                FROM check_channel_result
+                WHERE run_id IN (SELECT id FROM check_run WHERE guild_id = $1)
+                GROUP BY run_id
+            ) channel ON channel.run_id = run.id
+            WHERE run.guild_id = $1
+            ORDER BY run.started_at DESC, run.id DESC
+            LIMIT $2;
+        ";
+        let rows = client.query(query, &[&(guild_id.get() as i64), &(amount as i64)]).await?;
+
+        Ok(rows.into_iter().map(|row| {
+            let totals = CheckTotals::from(&row);
+
+            (CheckRun::from(row), totals)
+        }).collect())
+    }
+
+    async fn read_category_results(&self, run_id: i64) -> Result<Vec<CategoryResult>, StorageError> {
+        let client = self.get_object().await?;
+        let category_rows = client.query(
+            "
+                SELECT position, category_id, name, issue_channel_ids, manual_channel_ids
+                FROM check_category_result
+                WHERE run_id = $1
+                ORDER BY position;
+            ",
+            &[&run_id]
+        ).await?;
+        let channel_rows = client.query(
+            "
+                SELECT channel_id, good, bad, unresolved, category_position, position, messages
+                FROM check_channel_result
+                WHERE run_id = $1
+                ORDER BY category_position, position;
+            ",
+            &[&run_id]
+        ).await?;
+        let invite_rows = client.query(
+            "
+                SELECT message_id, code, status, is_permanent, expires_at, category_position, channel_position
+                FROM check_invite_result
+                WHERE run_id = $1
+                ORDER BY category_position, channel_position, position;
+            ",
+            &[&run_id]
+        ).await?;
+
+        Ok(category_rows.iter().map(|category_row| {
+            let position: i32 = category_row.get(0);
+            let mut result = CategoryResult::from(category_row);
+
+            result.channel_results = channel_rows
+                .iter()
+                .filter(|channel_row| channel_row.get::<_, i32>(4) == position)
+                .map(|channel_row| {
+                    let channel_position: i32 = channel_row.get(5);
+                    let mut channel_result = ChannelResult::from(channel_row);
+
+                    channel_result.invites = invite_rows
+                        .iter()
+                        .filter(|invite_row| invite_row.get::<_, i32>(5) == position && invite_row.get::<_, i32>(6) == channel_position)
+                        .map(InviteResult::from)
+                        .collect();
+
+                    channel_result
+                })
+                .collect();
+
+            result
+        }).collect())
+    }
+
+    async fn count_unchecked_codes(&self) -> Result<u64, StorageError> {
+        let client = self.get_object().await?;
+        let row = client.query_one("SELECT COUNT(*) FROM invite WHERE is_checked = FALSE;", &[]).await?;
+
+        Ok(row.get::<_, i64>(0) as u64)
+    }
+
+    async fn create_invites(&self, guild_id: Id<GuildMarker>, codes: HashSet<String>) -> Result<IngestReport, StorageError> {
+        let client = self.get_object().await?;
+        let query = "INSERT INTO invite(guild_id, code) SELECT $1, UNNEST($2::TEXT[]) ON CONFLICT DO NOTHING;";
+        let codes = codes.into_iter().collect::<Vec<String>>();
+        let mut report = IngestReport::default();
+
+        for chunk in codes.chunks(INVITE_BATCH_SIZE) {
+            let new = client.execute(query, &[&(guild_id.get() as i64), &chunk]).await? as usize;
+
+            report += IngestReport { new, known: chunk.len() - new };
+        }
+
+        Ok(report)
+    }
+
+    async fn read_checked_codes(&self, amount: u16) -> Result<HashSet<Code>, StorageError> {
+        let client = self.get_object().await?;
+        let query = "SELECT guild_id, code FROM invite WHERE is_checked = TRUE and is_valid = TRUE ORDER BY updated_at LIMIT $1;";
+        let rows = client.query(query, &[&(amount as i64)]).await?;
+
+        Ok(rows.into_iter().map(Code::from).collect())
+    }
+
+    async fn read_guild_invites(&self, guild_id: Id<GuildMarker>) -> Result<HashMap<String, Invite>, StorageError> {
+        let client = self.get_object().await?;
+        let query = "SELECT * FROM invite WHERE guild_id = $1;";
+        let rows = client.query(query, &[&(guild_id.get() as i64)]).await?;
+
+        Ok(rows.into_iter().map(|row| (row.get(1), Invite::from(row))).collect())
+    }
+
+    async fn read_unchecked_codes(&self, amount: u16) -> Result<HashSet<Code>, StorageError> {
+        let client = self.get_object().await?;
+        let query = "SELECT guild_id, code FROM invite WHERE is_checked = FALSE ORDER BY created_at LIMIT $1;";
+        let rows = client.query(query, &[&(amount as i64)]).await?;
+
+        Ok(rows.into_iter().map(Code::from).collect())
+    }
+
+    async fn upsert_code(&self, guild_id: Id<GuildMarker>, code: String, expires_at: Option<Timestamp>, is_permanent: bool, is_valid: bool) -> Result<(), StorageError> {
+        let client = self.get_object().await?;
+        let query = "
+            INSERT INTO invite(guild_id, code, expires_at, is_permanent, is_valid, is_checked)
+            VALUES($1, $2, $3, $4, $5, TRUE)
+            ON CONFLICT (guild_id, code)
+            DO
+            UPDATE SET
+                expires_at = EXCLUDED.expires_at,
+                is_permanent = EXCLUDED.is_permanent,
+                is_valid = EXCLUDED.is_valid,
+                is_checked = TRUE,
+                updated_at = CURRENT_TIMESTAMP
+        ";
+
+        client.query(query, &[&(guild_id.get() as i64), &code, &to_naive_date_time(expires_at), &is_permanent, &is_valid]).await?;
+
+        Ok(())
+    }
+}
@@ -1,161 +1,162 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, NaiveTime};
 use dashmap::DashSet;
-use super::Database;
+use std::{fmt, str::FromStr, time::Duration};
 use tokio_postgres::Row;
 use twilight_model::id::{
     Id,
     marker::{ChannelMarker, GuildMarker}
 };
 
-#[derive(Debug)]
-pub struct Setting {
-    pub guild_id: Id<GuildMarker>,
-    pub results_channel_id: Option<Id<ChannelMarker>>,
-    pub category_channel_ids: DashSet<Id<ChannelMarker>>,
-    pub ignored_channel_ids: DashSet<Id<ChannelMarker>>,
-    pub embed_color: u32,
-    pub last_check: Option<NaiveDateTime>,
-    pub in_check: bool
+pub const DEFAULT_SCAN_DEPTH: u16 = 15;
+// Discord returns at most 100 messages per request, so this is five requests per channel
+pub const MAX_SCAN_DEPTH: u16 = 500;
+// A year, since anything longer is as good as never
+pub const MAX_SCHEDULE_HOURS: u32 = 24 * 365;
+
+// Stored as `daily:HH:MM` or `every:N`, always in UTC
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CheckSchedule {
+    Daily { hour: u32, minute: u32 },
+    Every { hours: u32 }
 }
 
-impl From<Row> for Setting {
-    fn from(row: Row) -> Self {
-        Self {
-            guild_id: Id::new(row.get::<_, i64>(0) as u64), 
-            results_channel_id: match row.try_get::<_, i64>(1) {
-                Ok(channel_id) => Some(Id::new(channel_id as u64)),
-                Err(_) => None
-            },
-            category_channel_ids: row.get::<_, Vec<i64>>(2).into_iter().map(|id| Id::new(id as u64)).collect(),
-            ignored_channel_ids: row.get::<_, Vec<i64>>(3).into_iter().map(|id| Id::new(id as u64)).collect(),
-            embed_color: row.get::<_, i32>(4) as u32,
-            last_check: match row.try_get::<_, chrono::NaiveDateTime>(5) {
-                Ok(ndt) => Some(ndt),
-                Err(_) => None   
-            },
-            in_check: row.get(6)  
+impl CheckSchedule {
+    // The shortest time between two scheduled checks
+    pub fn period(self) -> Duration {
+        match self {
+            Self::Daily { .. } => Duration::from_secs(86_400),
+            Self::Every { hours } => Duration::from_secs(hours as u64 * 3_600)
         }
     }
-}
 
-impl Database {
-    pub async fn create_setting(&self, guild_id: Id<GuildMarker>) {
-        let client = self.get_object().await;
-        let query = "INSERT INTO setting(guild_id) VALUES($1) ON CONFLICT DO NOTHING;";
+    pub fn next_after(self, now: NaiveDateTime) -> NaiveDateTime {
+        match self {
+            Self::Daily { hour, minute } => {
+                let today = now.date().and_time(NaiveTime::from_hms_opt(hour, minute, 0).unwrap_or_default());
 
-        client.query(query, &[&(guild_id.get() as i64)]).await.unwrap();
-    }
-
-    pub async fn delete_channel(&self, guild_id: Id<GuildMarker>, channel_id: Id<ChannelMarker>) {
-        if let Some(setting) = self.read_setting(guild_id).await {
-            let client = self.get_object().await;
-            let query = "UPDATE setting SET category_channel_ids = $1, ignored_channel_ids = $2, results_channel_id = $3 WHERE guild_id = $4;";
-            let results_channel_id = match setting.results_channel_id {
-                Some(results_channel_id) => if results_channel_id == channel_id { None::<i64> } else { Some(results_channel_id.get() as i64) },
-                None => Some(channel_id.get() as i64),
-            };
-
-            client.query(
-                query,
-                &[
-                    &setting.category_channel_ids.remove(&channel_id).into_iter().map(|id| id.get() as i64).collect::<Vec<i64>>(),
-                    &setting.ignored_channel_ids.remove(&channel_id).into_iter().map(|id| id.get() as i64).collect::<Vec<i64>>(),
-                    &results_channel_id,
-                    &(guild_id.get() as i64)
-                ]
-            ).await.unwrap();
+                if today > now { today } else { today + chrono::Duration::days(1) }
+            },
+            // Stored schedules aren't capped, so one far enough out saturates instead of overflowing
+            Self::Every { hours } => now.checked_add_signed(chrono::Duration::hours(hours as i64)).unwrap_or(NaiveDateTime::MAX)
         }
     }
+}
 
-    pub async fn read_category_channel_ids(&self, guild_id: Id<GuildMarker>) -> DashSet<Id<ChannelMarker>> {
-        let client = self.get_object().await;
-        let query = "SELECT category_channel_ids FROM setting WHERE guild_id = $1;";
-        let row = client.query_one(query, &[&(guild_id.get() as i64)]).await.unwrap();
-        let category_channel_ids = row.get::<_, Vec<i64>>(0).into_iter().map(|id| Id::new(id as u64)).collect();
-
-        category_channel_ids
+impl fmt::Display for CheckSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Daily { hour, minute } => write!(f, "daily:{hour:02}:{minute:02}"),
+            Self::Every { hours } => write!(f, "every:{hours}")
+        }
     }
+}
 
-    pub async fn read_ignored_channel_ids(&self, guild_id: Id<GuildMarker>) -> DashSet<Id<ChannelMarker>> {
-        let client = self.get_object().await;
-        let query = "SELECT ignored_channel_ids FROM setting WHERE guild_id = $1;";
-        let row = client.query_one(query, &[&(guild_id.get() as i64)]).await.unwrap();
-        let ignored_channel_ids = row.get::<_, Vec<i64>>(0).into_iter().map(|id| Id::new(id as u64)).collect();
-
-        ignored_channel_ids
-    }
+impl FromStr for CheckSchedule {
+    type Err = ();
 
-    pub async fn read_setting(&self, guild_id: Id<GuildMarker>) -> Option<Setting> {
-        let client = self.get_object().await;
-        let query = "SELECT * FROM setting WHERE guild_id = $1;";
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split(':').collect::<Vec<&str>>()[..] {
+            ["daily", hour, minute] => {
+                let (hour, minute) = (hour.parse().map_err(|_| ())?, minute.parse().map_err(|_| ())?);
 
-        match client.query_one(query, &[&(guild_id.get() as i64)]).await {
-            Ok(row) => Some(row.into()),
-            Err(_) => None
+                NaiveTime::from_hms_opt(hour, minute, 0).map(|_| Self::Daily { hour, minute }).ok_or(())
+            },
+            ["every", hours] => match hours.parse() {
+                Ok(hours) if hours > 0 => Ok(Self::Every { hours }),
+                _ => Err(())
+            },
+            _ => Err(())
         }
     }
+}
 
-    pub async fn update_results_channel_id(&self, guild_id: Id<GuildMarker>, channel_id: Option<Id<ChannelMarker>>) {
-        let client = self.get_object().await;
-        let query = "UPDATE setting SET results_channel_id = $1 WHERE guild_id = $2;".to_string();
-        
-        match channel_id {
-            Some(channel_id) => client.query(&query, &[&(channel_id.get() as i64), &(guild_id.get() as i64)]).await.unwrap(),
-            None => client.query(&query, &[&None::<&[i64]>, &(guild_id.get() as i64)]).await.unwrap()
-        };
-    }
-
-    pub async fn update_category_channel_ids(&self, guild_id: Id<GuildMarker>, channel_ids: DashSet<Id<ChannelMarker>>) {
-        let client = self.get_object().await;
-        let query = "UPDATE setting SET category_channel_ids = $1 WHERE guild_id = $2;".to_string();
-
-        client.query(
-            &query,
-            &[
-                &channel_ids.into_iter().map(|id| id.get() as i64).collect::<Vec<i64>>(),
-                &(guild_id.get() as i64)
-            ]
-        ).await.unwrap();
-    }
-
-    pub async fn update_ignored_channel_ids(&self, guild_id: Id<GuildMarker>, channel_ids: DashSet<Id<ChannelMarker>>) {
-        let client = self.get_object().await;
-        let query = "UPDATE setting SET ignored_channel_ids = $1 WHERE guild_id = $2;".to_string();
-
-        client.query(
-            &query,
-            &[
-                &channel_ids.into_iter().map(|id| id.get() as i64).collect::<Vec<i64>>(),
-                &(guild_id.get() as i64)
-            ]
-        ).await.unwrap();
-    }
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+pub struct Setting {
+    pub guild_id: Id<GuildMarker>,
+    pub results_channel_id: Option<Id<ChannelMarker>>,
+    pub category_channel_ids: DashSet<Id<ChannelMarker>>,
+    pub ignored_channel_ids: DashSet<Id<ChannelMarker>>,
+    pub embed_color: u32,
+    pub last_check: Option<NaiveDateTime>,
+    pub in_check: bool,
+    // When the current check took the in_check lock, so locks left behind by a crash can be told apart
+    pub check_started_at: Option<NaiveDateTime>,
+    // How many of each channel's latest messages a check reads, unless the channel overrides it
+    pub scan_depth: u16,
+    // None follows the configured default
+    pub check_cooldown: Option<Duration>,
+    pub schedule: Option<CheckSchedule>,
+    pub next_scheduled_check: Option<NaiveDateTime>
+}
 
-    pub async fn update_embed_color(&self, guild_id: Id<GuildMarker>, color: u32) {
-        let client = self.get_object().await;
-        let query = "UPDATE setting SET embed_color = $1 WHERE guild_id = $2;".to_string();
-        
-        client.query(&query, &[&(color as i32), &(guild_id.get() as i64)]).await.unwrap();
+impl Setting {
+    pub fn new(guild_id: Id<GuildMarker>) -> Self {
+        Self {
+            guild_id,
+            results_channel_id: None,
+            category_channel_ids: DashSet::new(),
+            ignored_channel_ids: DashSet::new(),
+            embed_color: 0xF8F8FF,
+            last_check: None,
+            in_check: false,
+            check_started_at: None,
+            scan_depth: DEFAULT_SCAN_DEPTH,
+            check_cooldown: None,
+            schedule: None,
+            next_scheduled_check: None
+        }
     }
 
-    pub async fn update_last_check(&self, guild_id: Id<GuildMarker>) {
-        let client = self.get_object().await;
-        let query = "UPDATE setting SET last_check = NOW()::TIMESTAMP WHERE guild_id = $1;".to_string();
+    // When the cooldown from the last check ends, or None if there's nothing to wait for
+    pub fn next_check(&self, cooldown: Duration, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let next_check = self.last_check? + chrono::Duration::from_std(cooldown).ok()?;
 
-        client.query(&query, &[&(guild_id.get() as i64)]).await.unwrap();
+        (next_check > now).then_some(next_check)
     }
+}
 
-    pub async fn update_in_check(&self, guild_id: Id<GuildMarker>, in_check: bool) {
-        let client = self.get_object().await;
-        let query = "UPDATE setting SET in_check = $1 WHERE guild_id = $2;".to_string();
-
-        client.query(&query, &[&in_check, &(guild_id.get() as i64)]).await.unwrap();
+impl From<Row> for Setting {
+    fn from(row: Row) -> Self {
+        Self {
+            guild_id: Id::new(row.get::<_, i64>(0) as u64), 
+            results_channel_id: match row.try_get::<_, i64>(1) {
+                Ok(channel_id) => Some(Id::new(channel_id as u64)),
+                Err(_) => None
+            },
+            category_channel_ids: row.get::<_, Vec<i64>>(2).into_iter().map(|id| Id::new(id as u64)).collect(),
+            ignored_channel_ids: row.get::<_, Vec<i64>>(3).into_iter().map(|id| Id::new(id as u64)).collect(),
+            embed_color: row.get::<_, i32>(4) as u32,
+            last_check: row.try_get::<_, NaiveDateTime>(5).ok(),
+            in_check: row.get(6),
+            check_started_at: row.get(7),
+            scan_depth: row.get::<_, i32>(8) as u16,
+            check_cooldown: row.get::<_, Option<i32>>(9).map(|seconds| Duration::from_secs(seconds as u64)),
+            schedule: row.get::<_, Option<String>>(10).and_then(|schedule| schedule.parse().ok()),
+            next_scheduled_check: row.get(11)
+        }
     }
+}
 
-    pub async fn delete_setting(&self, guild_id: Id<GuildMarker>) {
-        let client = self.get_object().await;
-        let query = "DELETE FROM setting WHERE guild_id = $1;";
-
-        client.query(query, &[&(guild_id.get() as i64)]).await.unwrap();
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime};
+    use super::CheckSchedule;
+
+    #[test]
+    fn schedules_round_trip_and_find_their_next_slot() {
+        let now = NaiveDate::from_ymd_opt(2022, 1, 1).unwrap().and_hms_opt(18, 30, 0).unwrap();
+        let daily = CheckSchedule::Daily { hour: 18, minute: 0 };
+        let every = CheckSchedule::Every { hours: 6 };
+
+        assert_eq!(daily.to_string().parse::<CheckSchedule>(), Ok(daily));
+        assert_eq!(every.to_string().parse::<CheckSchedule>(), Ok(every));
+        assert!("daily:24:00".parse::<CheckSchedule>().is_err());
+        assert!("every:0".parse::<CheckSchedule>().is_err());
+
+        assert_eq!(daily.next_after(now), NaiveDate::from_ymd_opt(2022, 1, 2).unwrap().and_hms_opt(18, 0, 0).unwrap());
+        assert_eq!(CheckSchedule::Daily { hour: 19, minute: 15 }.next_after(now), now + chrono::Duration::minutes(45));
+        assert_eq!(every.next_after(now), now + chrono::Duration::hours(6));
+        assert_eq!(CheckSchedule::Every { hours: u32::MAX }.next_after(now), NaiveDateTime::MAX);
     }
-}
\ No newline at end of file
+}
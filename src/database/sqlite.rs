@@ -0,0 +1,975 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime};
+use dashmap::DashSet;
+use rusqlite::{params, types::Type, Connection, OptionalExtension, Row};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration
+};
+use super::{
+    access::CommandAccess,
+    check::{CategoryResult, ChannelResult, CheckRun, CheckStatus, CheckTotals, InviteResult, InviteStatus},
+    invite::{Code, IngestReport, Invite},
+    migration::{latest_version, Migration, MigrationError, SQLITE_MIGRATIONS},
+    setting::{CheckSchedule, Setting},
+    Storage,
+    StorageError,
+    INVITE_BATCH_SIZE
+};
+use twilight_model::{
+    datetime::Timestamp,
+    guild::Permissions,
+    id::{Id, marker::{ChannelMarker, GuildMarker, UserMarker}}
+};
+
+const NOW: &str = "strftime('%Y-%m-%d %H:%M:%f', 'now')";
+const SETTING_COLUMNS: &str = "guild_id, results_channel_id, category_channel_ids, ignored_channel_ids, embed_color, last_check, in_check, check_started_at, scan_depth, check_cooldown, schedule, next_scheduled_check";
+
+pub struct SqliteStorage {
+    connection: Arc<Mutex<Connection>>
+}
+
+impl SqliteStorage {
+    pub fn new(url: &str) -> Result<Self, StorageError> {
+        let path = url.trim_start_matches("sqlite:").trim_start_matches("//");
+        let connection = match path {
+            "" | ":memory:" => Connection::open_in_memory()?,
+            path => Connection::open(path)?
+        };
+
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "busy_timeout", 5_000)?;
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection))
+        })
+    }
+
+    async fn call<T, F, E>(&self, function: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Connection) -> Result<T, E> + Send + 'static,
+        T: Send + 'static,
+        E: From<StorageError> + Send + 'static
+    {
+        let connection = self.connection.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+            function(&mut connection)
+        })
+            .await
+            .map_err(|error| StorageError::Setup(error.to_string()))?
+    }
+}
+
+// SQLite has no array type, so id lists are stored as JSON arrays in TEXT columns
+fn to_ids<T, C: FromIterator<Id<T>>>(row: &Row, index: usize) -> rusqlite::Result<C> {
+    let text: String = row.get(index)?;
+    let ids: Vec<u64> = serde_json::from_str(&text)
+        .map_err(|error| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(error)))?;
+
+    Ok(ids.into_iter().filter_map(Id::new_checked).collect())
+}
+
+fn from_ids<T: Copy>(ids: &DashSet<Id<T>>) -> String {
+    serde_json::to_string(&ids.iter().map(|id| id.get()).collect::<Vec<u64>>()).unwrap()
+}
+
+fn from_id_list<T: Copy>(ids: &[Id<T>]) -> String {
+    serde_json::to_string(&ids.iter().map(|id| id.get()).collect::<Vec<u64>>()).unwrap()
+}
+
+fn to_naive_date_time(timestamp: Option<Timestamp>) -> Option<NaiveDateTime> {
+    timestamp
+        .and_then(|timestamp| DateTime::from_timestamp(timestamp.as_secs(), 0))
+        .map(|date_time| date_time.naive_utc())
+}
+
+fn setting_from_row(row: &Row) -> rusqlite::Result<Setting> {
+    Ok(Setting {
+        guild_id: Id::new(row.get::<_, i64>(0)? as u64),
+        results_channel_id: row.get::<_, Option<i64>>(1)?.map(|id| Id::new(id as u64)),
+        category_channel_ids: to_ids(row, 2)?,
+        ignored_channel_ids: to_ids(row, 3)?,
+        embed_color: row.get::<_, i64>(4)? as u32,
+        last_check: row.get(5)?,
+        in_check: row.get(6)?,
+        check_started_at: row.get(7)?,
+        scan_depth: row.get(8)?,
+        check_cooldown: row.get::<_, Option<i64>>(9)?.map(|seconds| Duration::from_secs(seconds as u64)),
+        schedule: row.get::<_, Option<String>>(10)?.and_then(|schedule| schedule.parse().ok()),
+        next_scheduled_check: row.get(11)?
+    })
+}
+
+fn invite_from_row(row: &Row) -> rusqlite::Result<Invite> {
+    Ok(Invite {
+        guild_id: Id::new(row.get::<_, i64>(0)? as u64),
+        code: row.get(1)?,
+        expires_at: row.get(2)?,
+        is_permanent: row.get(3)?,
+        is_valid: row.get(4)?,
+        is_checked: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?
+    })
+}
+
+fn command_access_from_row(row: &Row) -> rusqlite::Result<CommandAccess> {
+    Ok(CommandAccess {
+        guild_id: Id::new(row.get::<_, i64>(0)? as u64),
+        command: row.get(1)?,
+        role_ids: to_ids(row, 2)?,
+        permissions: Permissions::from_bits_truncate(row.get::<_, i64>(3)? as u64)
+    })
+}
+
+fn code_from_row(row: &Row) -> rusqlite::Result<Code> {
+    Ok(Code {
+        guild_id: Id::new(row.get::<_, i64>(0)? as u64),
+        code: row.get(1)?
+    })
+}
+
+fn check_run_from_row(row: &Row) -> rusqlite::Result<CheckRun> {
+    Ok(CheckRun {
+        id: row.get(0)?,
+        guild_id: Id::new(row.get::<_, i64>(1)? as u64),
+        user_id: Id::new(row.get::<_, i64>(2)? as u64),
+        status: row.get::<_, String>(3)?.parse().unwrap_or(CheckStatus::Failed),
+        started_at: row.get(4)?,
+        finished_at: row.get(5)?
+    })
+}
+
+fn check_run_with_totals_from_row(row: &Row) -> rusqlite::Result<(CheckRun, CheckTotals)> {
+    Ok((check_run_from_row(row)?, CheckTotals {
+        channels: row.get(6)?,
+        good: row.get(7)?,
+        bad: row.get(8)?,
+        unresolved: row.get(9)?
+    }))
+}
+
+fn category_result_from_row(row: &Row) -> rusqlite::Result<(i64, CategoryResult)> {
+    Ok((row.get(0)?, CategoryResult {
+        category_id: Id::new(row.get::<_, i64>(1)? as u64),
+        name: row.get(2)?,
+        channel_results: vec![],
+        issue_channel_ids: to_ids(row, 3)?,
+        manual_channel_ids: to_ids(row, 4)?
+    }))
+}
+
+fn channel_result_from_row(row: &Row) -> rusqlite::Result<(i64, ChannelResult)> {
+    Ok((row.get(4)?, ChannelResult {
+        channel_id: Id::new(row.get::<_, i64>(0)? as u64),
+        good: row.get(1)?,
+        bad: row.get(2)?,
+        unresolved: row.get(3)?,
+        messages: row.get(5)?,
+        invites: vec![]
+    }))
+}
+
+fn invite_result_from_row(row: &Row) -> rusqlite::Result<(i64, i64, InviteResult)> {
+    Ok((row.get(5)?, row.get(6)?, InviteResult {
+        message_id: Id::new(row.get::<_, i64>(0)? as u64),
+        code: row.get(1)?,
+        status: row.get::<_, String>(2)?.parse().unwrap_or(InviteStatus::Unresolved),
+        is_permanent: row.get(3)?,
+        expires_at: row.get(4)?
+    }))
+}
+
+fn read_setting(connection: &Connection, guild_id: Id<GuildMarker>) -> rusqlite::Result<Option<Setting>> {
+    connection
+        .query_row(
+            &format!("SELECT {SETTING_COLUMNS} FROM setting WHERE guild_id = ?1;"),
+            params![guild_id.get() as i64],
+            setting_from_row
+        )
+        .optional()
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn migrate(&self) -> Result<Vec<&'static Migration>, MigrationError> {
+        self.call(|connection| {
+            let transaction = connection.transaction()?;
+
+            transaction.execute_batch("
+                CREATE TABLE IF NOT EXISTS schema_version (
+                    version INTEGER NOT NULL,
+                    name TEXT NOT NULL,
+                    applied_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+                    CONSTRAINT pk_schema_version PRIMARY KEY (version)
+                );
+            ")?;
+
+            let database_version: i32 = transaction.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version;", [], |row| row.get(0))?;
+            let latest_version = latest_version(SQLITE_MIGRATIONS);
+
+            if database_version > latest_version {
+                return Err(MigrationError::SchemaTooNew { database_version, latest_version })
+            }
+
+            let pending = SQLITE_MIGRATIONS.iter().filter(|migration| migration.version > database_version).collect::<Vec<_>>();
+
+            for migration in &pending {
+                transaction.execute_batch(migration.sql)?;
+                transaction.execute(
+                    "INSERT INTO schema_version(version, name) VALUES(?1, ?2);",
+                    params![migration.version, migration.name]
+                )?;
+            }
+
+            transaction.commit()?;
+
+            Ok(pending)
+        }).await
+    }
+
+    async fn ping(&self) -> Result<(), StorageError> {
+        self.call(|connection| {
+            connection.query_row("SELECT 1;", [], |_| Ok(()))?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn create_setting(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            connection.execute("INSERT INTO setting(guild_id) VALUES(?1) ON CONFLICT DO NOTHING;", params![guild_id.get() as i64])?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn delete_channel(&self, guild_id: Id<GuildMarker>, channel_id: Id<ChannelMarker>) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            let transaction = connection.transaction()?;
+
+            if let Some(setting) = read_setting(&transaction, guild_id)? {
+                let results_channel_id = setting.results_channel_id.filter(|id| *id != channel_id);
+
+                setting.category_channel_ids.remove(&channel_id);
+                setting.ignored_channel_ids.remove(&channel_id);
+                transaction.execute(
+                    "UPDATE setting SET category_channel_ids = ?1, ignored_channel_ids = ?2, results_channel_id = ?3 WHERE guild_id = ?4;",
+                    params![
+                        from_ids(&setting.category_channel_ids),
+                        from_ids(&setting.ignored_channel_ids),
+                        results_channel_id.map(|id| id.get() as i64),
+                        guild_id.get() as i64
+                    ]
+                )?;
+                transaction.execute(
+                    "DELETE FROM channel_scan_depth WHERE guild_id = ?1 AND channel_id = ?2;",
+                    params![guild_id.get() as i64, channel_id.get() as i64]
+                )?;
+            }
+
+            transaction.commit()?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn read_category_channel_ids(&self, guild_id: Id<GuildMarker>) -> Result<DashSet<Id<ChannelMarker>>, StorageError> {
+        self.call(move |connection| {
+            connection
+                .query_row("SELECT category_channel_ids FROM setting WHERE guild_id = ?1;", params![guild_id.get() as i64], |row| to_ids(row, 0))
+                .optional()?
+                .ok_or(StorageError::MissingSetting(guild_id))
+        }).await
+    }
+
+    async fn read_ignored_channel_ids(&self, guild_id: Id<GuildMarker>) -> Result<DashSet<Id<ChannelMarker>>, StorageError> {
+        self.call(move |connection| {
+            connection
+                .query_row("SELECT ignored_channel_ids FROM setting WHERE guild_id = ?1;", params![guild_id.get() as i64], |row| to_ids(row, 0))
+                .optional()?
+                .ok_or(StorageError::MissingSetting(guild_id))
+        }).await
+    }
+
+    async fn read_setting(&self, guild_id: Id<GuildMarker>) -> Result<Option<Setting>, StorageError> {
+        self.call(move |connection| Ok(read_setting(connection, guild_id)?)).await
+    }
+
+    async fn update_results_channel_id(&self, guild_id: Id<GuildMarker>, channel_id: Option<Id<ChannelMarker>>) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            connection.execute(
+                "UPDATE setting SET results_channel_id = ?1 WHERE guild_id = ?2;",
+                params![channel_id.map(|id| id.get() as i64), guild_id.get() as i64]
+            )?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn update_category_channel_ids(&self, guild_id: Id<GuildMarker>, channel_ids: DashSet<Id<ChannelMarker>>) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            connection.execute(
+                "UPDATE setting SET category_channel_ids = ?1 WHERE guild_id = ?2;",
+                params![from_ids(&channel_ids), guild_id.get() as i64]
+            )?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn update_ignored_channel_ids(&self, guild_id: Id<GuildMarker>, channel_ids: DashSet<Id<ChannelMarker>>) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            connection.execute(
+                "UPDATE setting SET ignored_channel_ids = ?1 WHERE guild_id = ?2;",
+                params![from_ids(&channel_ids), guild_id.get() as i64]
+            )?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn update_embed_color(&self, guild_id: Id<GuildMarker>, color: u32) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            connection.execute("UPDATE setting SET embed_color = ?1 WHERE guild_id = ?2;", params![color, guild_id.get() as i64])?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn update_scan_depth(&self, guild_id: Id<GuildMarker>, scan_depth: u16) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            connection.execute("UPDATE setting SET scan_depth = ?1 WHERE guild_id = ?2;", params![scan_depth, guild_id.get() as i64])?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn update_check_cooldown(&self, guild_id: Id<GuildMarker>, check_cooldown: Option<Duration>) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            connection.execute(
+                "UPDATE setting SET check_cooldown = ?1 WHERE guild_id = ?2;",
+                params![check_cooldown.map(|check_cooldown| check_cooldown.as_secs() as i64), guild_id.get() as i64]
+            )?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn update_last_check(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            connection.execute(&format!("UPDATE setting SET last_check = {NOW} WHERE guild_id = ?1;"), params![guild_id.get() as i64])?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn clear_last_check(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            connection.execute("UPDATE setting SET last_check = NULL WHERE guild_id = ?1;", params![guild_id.get() as i64])?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn update_schedule(&self, guild_id: Id<GuildMarker>, schedule: Option<CheckSchedule>, next_scheduled_check: Option<NaiveDateTime>) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            connection.execute(
+                "UPDATE setting SET schedule = ?1, next_scheduled_check = ?2 WHERE guild_id = ?3;",
+                params![schedule.map(|schedule| schedule.to_string()), next_scheduled_check, guild_id.get() as i64]
+            )?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn read_due_schedules(&self, now: NaiveDateTime) -> Result<Vec<Setting>, StorageError> {
+        self.call(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {SETTING_COLUMNS} FROM setting WHERE schedule IS NOT NULL AND next_scheduled_check <= ?1;"
+            ))?;
+            let settings = statement.query_map(params![now], setting_from_row)?.collect::<rusqlite::Result<Vec<Setting>>>()?;
+
+            Ok(settings)
+        }).await
+    }
+
+    async fn update_in_check(&self, guild_id: Id<GuildMarker>, in_check: bool) -> Result<bool, StorageError> {
+        self.call(move |connection| {
+            let updated = connection.execute(
+                &format!("UPDATE setting SET in_check = ?1, check_started_at = CASE WHEN ?1 THEN {NOW} ELSE NULL END WHERE guild_id = ?2 AND in_check <> ?1;"),
+                params![in_check, guild_id.get() as i64]
+            )?;
+
+            Ok(updated > 0)
+        }).await
+    }
+
+    async fn read_interrupted_checks(&self, started_before: Option<NaiveDateTime>) -> Result<Vec<Setting>, StorageError> {
+        self.call(move |connection| {
+            let mut statement = connection.prepare(&format!("
+                SELECT {SETTING_COLUMNS} FROM setting
+                WHERE in_check = 1 AND (?1 IS NULL OR check_started_at IS NULL OR check_started_at < ?1);
+            "))?;
+            let settings = statement.query_map(params![started_before], setting_from_row)?.collect::<rusqlite::Result<Vec<Setting>>>()?;
+
+            Ok(settings)
+        }).await
+    }
+
+    async fn recover_check(&self, guild_id: Id<GuildMarker>, clear_last_check: bool) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            connection.execute(
+                "UPDATE setting SET in_check = 0, check_started_at = NULL, last_check = CASE WHEN ?1 THEN NULL ELSE last_check END WHERE guild_id = ?2;",
+                params![clear_last_check, guild_id.get() as i64]
+            )?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn delete_setting(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            let transaction = connection.transaction()?;
+
+            transaction.execute("DELETE FROM setting WHERE guild_id = ?1;", params![guild_id.get() as i64])?;
+            transaction.execute("DELETE FROM command_access WHERE guild_id = ?1;", params![guild_id.get() as i64])?;
+            transaction.execute("DELETE FROM channel_scan_depth WHERE guild_id = ?1;", params![guild_id.get() as i64])?;
+            transaction.execute(
+                "DELETE FROM check_invite_result WHERE run_id IN (SELECT id FROM check_run WHERE guild_id = ?1);",
+                params![guild_id.get() as i64]
+            )?;
+            transaction.execute(
+                "DELETE FROM check_channel_result WHERE run_id IN (SELECT id FROM check_run WHERE guild_id = ?1);",
+                params![guild_id.get() as i64]
+            )?;
+            transaction.execute(
+                "DELETE FROM check_category_result WHERE run_id IN (SELECT id FROM check_run WHERE guild_id = ?1);",
+                params![guild_id.get() as i64]
+            )?;
+            transaction.execute("DELETE FROM check_run WHERE guild_id = ?1;", params![guild_id.get() as i64])?;
+            transaction.commit()?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn read_command_access(&self, guild_id: Id<GuildMarker>) -> Result<Vec<CommandAccess>, StorageError> {
+        self.call(move |connection| {
+            let mut statement = connection.prepare("SELECT guild_id, command, role_ids, permissions FROM command_access WHERE guild_id = ?1 ORDER BY command;")?;
+            let access = statement
+                .query_map(params![guild_id.get() as i64], command_access_from_row)?
+                .collect::<rusqlite::Result<Vec<CommandAccess>>>()?;
+
+            Ok(access)
+        }).await
+    }
+
+    async fn upsert_command_access(&self, access: CommandAccess) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            connection.execute(
+                "
+                    INSERT INTO command_access(guild_id, command, role_ids, permissions)
+                    VALUES(?1, ?2, ?3, ?4)
+                    ON CONFLICT (guild_id, command)
+                    DO
+                    UPDATE SET
+                        role_ids = excluded.role_ids,
+                        permissions = excluded.permissions
+                ",
+                params![access.guild_id.get() as i64, access.command, from_ids(&access.role_ids), access.permissions.bits() as i64]
+            )?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn delete_command_access(&self, guild_id: Id<GuildMarker>, command: String) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            connection.execute("DELETE FROM command_access WHERE guild_id = ?1 AND command = ?2;", params![guild_id.get() as i64, command])?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn read_channel_scan_depths(&self, guild_id: Id<GuildMarker>) -> Result<HashMap<Id<ChannelMarker>, u16>, StorageError> {
+        self.call(move |connection| {
+            let mut statement = connection.prepare("SELECT channel_id, scan_depth FROM channel_scan_depth WHERE guild_id = ?1;")?;
+            let scan_depths = statement
+                .query_map(params![guild_id.get() as i64], |row| Ok((Id::new(row.get::<_, i64>(0)? as u64), row.get(1)?)))?
+                .collect::<rusqlite::Result<HashMap<Id<ChannelMarker>, u16>>>()?;
+
+            Ok(scan_depths)
+        }).await
+    }
+
+    async fn update_channel_scan_depth(&self, guild_id: Id<GuildMarker>, channel_id: Id<ChannelMarker>, scan_depth: Option<u16>) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            match scan_depth {
+                Some(scan_depth) => connection.execute(
+                    "
+                        INSERT INTO channel_scan_depth(guild_id, channel_id, scan_depth)
+                        VALUES(?1, ?2, ?3)
+                        ON CONFLICT (guild_id, channel_id)
+                        DO
+                        UPDATE SET scan_depth = excluded.scan_depth
+                    ",
+                    params![guild_id.get() as i64, channel_id.get() as i64, scan_depth]
+                )?,
+                None => connection.execute(
+                    "DELETE FROM channel_scan_depth WHERE guild_id = ?1 AND channel_id = ?2;",
+                    params![guild_id.get() as i64, channel_id.get() as i64]
+                )?
+            };
+
+            Ok(())
+        }).await
+    }
+
+    async fn create_check_run(&self, guild_id: Id<GuildMarker>, user_id: Id<UserMarker>) -> Result<i64, StorageError> {
+        self.call(move |connection| {
+            connection.execute(
+                "INSERT INTO check_run(guild_id, user_id) VALUES(?1, ?2);",
+                params![guild_id.get() as i64, user_id.get() as i64]
+            )?;
+
+            Ok(connection.last_insert_rowid())
+        }).await
+    }
+
+    async fn create_category_result(&self, run_id: i64, position: usize, result: CategoryResult) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            let transaction = connection.transaction()?;
+
+            transaction.execute(
+                "
+                    INSERT INTO check_category_result(run_id, position, category_id, name, issue_channel_ids, manual_channel_ids)
+                    VALUES(?1, ?2, ?3, ?4, ?5, ?6);
+                ",
+                params![
+                    run_id,
+                    position as i64,
+                    result.category_id.get() as i64,
+                    result.name,
+                    from_id_list(&result.issue_channel_ids),
+                    from_id_list(&result.manual_channel_ids)
+                ]
+            )?;
+
+            {
+                let mut statement = transaction.prepare("
+                    INSERT INTO check_channel_result(run_id, category_position, position, channel_id, good, bad, unresolved, messages)
+                    VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8);
+                ")?;
+
+                for (channel_position, channel) in result.channel_results.iter().enumerate() {
+                    statement.execute(params![
+                        run_id,
+                        position as i64,
+                        channel_position as i64,
+                        channel.channel_id.get() as i64,
+                        channel.good,
+                        channel.bad,
+                        channel.unresolved,
+                        channel.messages
+                    ])?;
+                }
+            }
+
+            {
+                let mut statement = transaction.prepare("
+                    INSERT INTO check_invite_result(run_id, category_position, channel_position, position, message_id, code, status, is_permanent, expires_at)
+                    VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9);
+                ")?;
+
+                for (channel_position, channel) in result.channel_results.iter().enumerate() {
+                    for (invite_position, invite) in channel.invites.iter().enumerate() {
+                        statement.execute(params![
+                            run_id,
+                            position as i64,
+                            channel_position as i64,
+                            invite_position as i64,
+                            invite.message_id.get() as i64,
+                            invite.code,
+                            invite.status.as_str(),
+                            invite.is_permanent,
+                            invite.expires_at
+                        ])?;
+                    }
+                }
+            }
+
+            transaction.commit()?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn finish_check_run(&self, run_id: i64, status: CheckStatus) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            connection.execute(
+                &format!("UPDATE check_run SET status = ?1, finished_at = {NOW} WHERE id = ?2;"),
+                params![status.as_str(), run_id]
+            )?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn abort_check_runs(&self, guild_id: Id<GuildMarker>) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            connection.execute(
+                &format!("UPDATE check_run SET status = ?1, finished_at = {NOW} WHERE guild_id = ?2 AND status = ?3;"),
+                params![CheckStatus::Aborted.as_str(), guild_id.get() as i64, CheckStatus::Running.as_str()]
+            )?;
+
+            Ok(())
+        }).await
+    }
+
+    async fn read_check_run(&self, guild_id: Id<GuildMarker>, run_id: i64) -> Result<Option<CheckRun>, StorageError> {
+        self.call(move |connection| {
+            let run = connection
+                .query_row(
+                    "SELECT id, guild_id, user_id, status, started_at, finished_at FROM check_run WHERE guild_id = ?1 AND id = ?2;",
+                    params![guild_id.get() as i64, run_id],
+                    check_run_from_row
+                )
+                .optional()?;
+
+            Ok(run)
+        }).await
+    }
+
+    async fn read_previous_check_run(&self, guild_id: Id<GuildMarker>, run_id: i64) -> Result<Option<CheckRun>, StorageError> {
+        self.call(move |connection| {
+            let run = connection
+                .query_row(
+                    "
+                        SELECT id, guild_id, user_id, status, started_at, finished_at
+                        FROM check_run
+                        WHERE guild_id = ?1 AND id < ?2 AND status = ?3
+                        ORDER BY id DESC
+                        LIMIT 1;
+                    ",
+                    params![guild_id.get() as i64, run_id, CheckStatus::Completed.as_str()],
+                    check_run_from_row
+                )
+                .optional()?;
+
+            Ok(run)
+        }).await
+    }
+
+    async fn read_check_runs(&self, guild_id: Id<GuildMarker>, amount: u16) -> Result<Vec<(CheckRun, CheckTotals)>, StorageError> {
+        self.call(move |connection| {
+            let mut statement = connection.prepare("
+                SELECT
+                    run.id, run.guild_id, run.user_id, run.status, run.started_at, run.finished_at,
+                    COALESCE(category.channels, 0) + COALESCE(channel.channels, 0),
+                    COALESCE(channel.good, 0),
+                    COALESCE(channel.bad, 0),
+                    COALESCE(channel.unresolved, 0)
+                FROM check_run run
+                LEFT JOIN (
+                    SELECT run_id, SUM(json_array_length(issue_channel_ids) + json_array_length(manual_channel_ids)) AS channels
+                    FROM check_category_result
+                    WHERE run_id IN (SELECT id FROM check_run WHERE guild_id = ?1)
+                    GROUP BY run_id
+                ) category ON category.run_id = run.id
+                LEFT JOIN (
+                    SELECT run_id, COUNT(*) AS channels, SUM(good) AS good, SUM(bad) AS bad, SUM(unresolved) AS unresolved
+                    FROM check_channel_result
+                    WHERE run_id IN (SELECT id FROM check_run WHERE guild_id = ?1)
+                    GROUP BY run_id
+                ) channel ON channel.run_id = run.id
+                WHERE run.guild_id = ?1
+                ORDER BY run.started_at DESC, run.id DESC
+                LIMIT ?2;
+            ")?;
+            let runs = statement
+                .query_map(params![guild_id.get() as i64, amount], check_run_with_totals_from_row)?
+                .collect::<rusqlite::Result<Vec<(CheckRun, CheckTotals)>>>()?;
+
+            Ok(runs)
+        }).await
+    }
+
+    async fn read_category_results(&self, run_id: i64) -> Result<Vec<CategoryResult>, StorageError> {
+        self.call(move |connection| {
+            let mut statement = connection.prepare("
+                SELECT position, category_id, name, issue_channel_ids, manual_channel_ids
+                FROM check_category_result
+                WHERE run_id = ?1
+                ORDER BY position;
+            ")?;
+            let mut categories = statement
+                .query_map(params![run_id], category_result_from_row)?
+                .collect::<rusqlite::Result<Vec<(i64, CategoryResult)>>>()?;
+            let mut statement = connection.prepare("
+                SELECT channel_id, good, bad, unresolved, category_position, messages
+                FROM check_channel_result
+                WHERE run_id = ?1
+                ORDER BY category_position, position;
+            ")?;
+            let channels = statement
+                .query_map(params![run_id], channel_result_from_row)?
+                .collect::<rusqlite::Result<Vec<(i64, ChannelResult)>>>()?;
+
+            let mut statement = connection.prepare("
+                SELECT message_id, code, status, is_permanent, expires_at, category_position, channel_position
+                FROM check_invite_result
+                WHERE run_id = ?1
+                ORDER BY category_position, channel_position, position;
+            ")?;
+            let invites = statement
+                .query_map(params![run_id], invite_result_from_row)?
+                .collect::<rusqlite::Result<Vec<(i64, i64, InviteResult)>>>()?;
+
+            for (category_position, channel) in channels {
+                if let Some((_, category)) = categories.iter_mut().find(|(position, _)| *position == category_position) {
+                    category.channel_results.push(channel);
+                }
+            }
+
+            // Channel positions are their index in the category, as written by create_category_result
+            for (category_position, channel_position, invite) in invites {
+                let channel = categories
+                    .iter_mut()
+                    .find(|(position, _)| *position == category_position)
+                    .and_then(|(_, category)| category.channel_results.get_mut(channel_position as usize));
+
+                if let Some(channel) = channel {
+                    channel.invites.push(invite);
+                }
+            }
+
+            Ok(categories.into_iter().map(|(_, category)| category).collect())
+        }).await
+    }
+
+    async fn count_unchecked_codes(&self) -> Result<u64, StorageError> {
+        self.call(|connection| {
+            let count = connection.query_row("SELECT COUNT(*) FROM invite WHERE is_checked = 0;", [], |row| row.get::<_, i64>(0))?;
+
+            Ok(count as u64)
+        }).await
+    }
+
+    async fn create_invites(&self, guild_id: Id<GuildMarker>, codes: HashSet<String>) -> Result<IngestReport, StorageError> {
+        self.call(move |connection| {
+            let codes = codes.into_iter().collect::<Vec<String>>();
+            let mut report = IngestReport::default();
+
+            for chunk in codes.chunks(INVITE_BATCH_SIZE) {
+                let transaction = connection.transaction()?;
+                let mut new = 0;
+
+                {
+                    let mut statement = transaction.prepare("INSERT INTO invite(guild_id, code) VALUES(?1, ?2) ON CONFLICT DO NOTHING;")?;
+
+                    for code in chunk {
+                        new += statement.execute(params![guild_id.get() as i64, code])?;
+                    }
+                }
+
+                transaction.commit()?;
+                report += IngestReport { new, known: chunk.len() - new };
+            }
+
+            Ok(report)
+        }).await
+    }
+
+    async fn read_checked_codes(&self, amount: u16) -> Result<HashSet<Code>, StorageError> {
+        self.call(move |connection| {
+            let mut statement = connection.prepare("SELECT guild_id, code FROM invite WHERE is_checked = 1 AND is_valid = 1 ORDER BY updated_at LIMIT ?1;")?;
+            let codes = statement.query_map(params![amount], code_from_row)?.collect::<rusqlite::Result<HashSet<Code>>>()?;
+
+            Ok(codes)
+        }).await
+    }
+
+    async fn read_guild_invites(&self, guild_id: Id<GuildMarker>) -> Result<HashMap<String, Invite>, StorageError> {
+        self.call(move |connection| {
+            let mut statement = connection.prepare("
+                SELECT guild_id, code, expires_at, is_permanent, is_valid, is_checked, created_at, updated_at
+                FROM invite
+                WHERE guild_id = ?1;
+            ")?;
+            let invites = statement
+                .query_map(params![guild_id.get() as i64], invite_from_row)?
+                .map(|invite| invite.map(|invite| (invite.code.clone(), invite)))
+                .collect::<rusqlite::Result<HashMap<String, Invite>>>()?;
+
+            Ok(invites)
+        }).await
+    }
+
+    async fn read_unchecked_codes(&self, amount: u16) -> Result<HashSet<Code>, StorageError> {
+        self.call(move |connection| {
+            let mut statement = connection.prepare("SELECT guild_id, code FROM invite WHERE is_checked = 0 ORDER BY created_at LIMIT ?1;")?;
+            let codes = statement.query_map(params![amount], code_from_row)?.collect::<rusqlite::Result<HashSet<Code>>>()?;
+
+            Ok(codes)
+        }).await
+    }
+
+    async fn upsert_code(&self, guild_id: Id<GuildMarker>, code: String, expires_at: Option<Timestamp>, is_permanent: bool, is_valid: bool) -> Result<(), StorageError> {
+        self.call(move |connection| {
+            connection.execute(
+                &format!("
+                    INSERT INTO invite(guild_id, code, expires_at, is_permanent, is_valid, is_checked)
+                    VALUES(?1, ?2, ?3, ?4, ?5, 1)
+                    ON CONFLICT (guild_id, code)
+                    DO
+                    UPDATE SET
+                        expires_at = excluded.expires_at,
+                        is_permanent = excluded.is_permanent,
+                        is_valid = excluded.is_valid,
+                        is_checked = 1,
+                        updated_at = {NOW}
+                "),
+                params![guild_id.get() as i64, code, to_naive_date_time(expires_at), is_permanent, is_valid]
+            )?;
+
+            Ok(())
+        }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dashmap::DashSet;
+    use std::collections::HashSet;
+    use twilight_model::{datetime::Timestamp, id::Id};
+    use crate::database::{
+        check::{CategoryResult, ChannelResult, CheckStatus, CheckTotals, InviteResult, InviteStatus},
+        migration::{latest_version, MigrationError, SQLITE_MIGRATIONS},
+        Storage,
+        StorageError
+    };
+    use super::SqliteStorage;
+
+    async fn storage() -> SqliteStorage {
+        let storage = SqliteStorage::new("sqlite::memory:").unwrap();
+
+        storage.migrate().await.unwrap();
+        storage
+    }
+
+    #[tokio::test]
+    async fn migrations_apply_once_and_record_every_version() {
+        let storage = SqliteStorage::new("sqlite::memory:").unwrap();
+        let latest_version = latest_version(SQLITE_MIGRATIONS);
+
+        assert_eq!(storage.migrate().await.unwrap().len(), SQLITE_MIGRATIONS.len());
+        assert!(storage.migrate().await.unwrap().is_empty());
+
+        let versions = storage.call(|connection| {
+            let mut statement = connection.prepare("SELECT version FROM schema_version ORDER BY version;")?;
+            let versions = statement.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<Vec<i32>>>()?;
+
+            Ok::<_, StorageError>(versions)
+        }).await.unwrap();
+
+        assert_eq!(versions, (1..=latest_version).collect::<Vec<i32>>());
+    }
+
+    #[tokio::test]
+    async fn migrations_refuse_a_database_ahead_of_this_build() {
+        let storage = storage().await;
+        let latest_version = latest_version(SQLITE_MIGRATIONS);
+
+        storage.call(move |connection| {
+            connection.execute("INSERT INTO schema_version(version, name) VALUES(?1, 'from_the_future');", [latest_version + 1])?;
+
+            Ok::<_, StorageError>(())
+        }).await.unwrap();
+
+        assert!(matches!(
+            storage.migrate().await,
+            Err(MigrationError::SchemaTooNew { database_version, latest_version: known_version })
+                if database_version == latest_version + 1 && known_version == latest_version
+        ));
+    }
+
+    #[tokio::test]
+    async fn settings_round_trip_both_channel_id_lists() {
+        let storage = storage().await;
+        let guild_id = Id::new(100);
+
+        storage.create_setting(guild_id).await.unwrap();
+        storage.update_category_channel_ids(guild_id, DashSet::from_iter([Id::new(300), Id::new(301)])).await.unwrap();
+        storage.update_ignored_channel_ids(guild_id, DashSet::from_iter([Id::new(302)])).await.unwrap();
+
+        let setting = storage.read_setting(guild_id).await.unwrap().unwrap();
+        let category_channel_ids = setting.category_channel_ids.iter().map(|id| *id).collect::<HashSet<_>>();
+        let ignored_channel_ids = setting.ignored_channel_ids.iter().map(|id| *id).collect::<HashSet<_>>();
+
+        assert_eq!(category_channel_ids, HashSet::from([Id::new(300), Id::new(301)]));
+        assert_eq!(ignored_channel_ids, HashSet::from([Id::new(302)]));
+        assert!(storage.read_setting(Id::new(101)).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn upserting_a_code_twice_keeps_the_latest_result() {
+        let storage = storage().await;
+        let guild_id = Id::new(100);
+        let expires_at = Timestamp::from_secs(1_700_000_000).unwrap();
+
+        let report = storage.create_invites(guild_id, HashSet::from(["sakura".to_string()])).await.unwrap();
+        assert_eq!((report.new, report.known), (1, 0));
+
+        storage.upsert_code(guild_id, "sakura".to_string(), None, true, true).await.unwrap();
+        storage.upsert_code(guild_id, "sakura".to_string(), Some(expires_at), false, false).await.unwrap();
+
+        let invites = storage.read_guild_invites(guild_id).await.unwrap();
+        let invite = &invites["sakura"];
+
+        assert_eq!(invites.len(), 1);
+        assert_eq!(invite.is_valid, Some(false));
+        assert_eq!(invite.is_permanent, Some(false));
+        assert_eq!(invite.expires_at.map(|expires_at| expires_at.and_utc().timestamp()), Some(1_700_000_000));
+        assert!(invite.is_checked);
+        assert!(invite.updated_at >= invite.created_at);
+    }
+
+    #[tokio::test]
+    async fn check_runs_read_back_with_their_totals_and_results() {
+        let storage = storage().await;
+        let guild_id = Id::new(100);
+        let run_id = storage.create_check_run(guild_id, Id::new(11)).await.unwrap();
+        let mut channel_result = ChannelResult::new(Id::new(301));
+        let mut category_result = CategoryResult::new(Id::new(300), "Partners".to_string());
+
+        channel_result.good = 1;
+        channel_result.bad = 1;
+        channel_result.messages = 2;
+        channel_result.invites = vec![
+            InviteResult { message_id: Id::new(1), code: "good".to_string(), status: InviteStatus::Valid, is_permanent: Some(true), expires_at: None },
+            InviteResult { message_id: Id::new(2), code: "bad".to_string(), status: InviteStatus::Invalid, is_permanent: None, expires_at: None }
+        ];
+        category_result.channel_results = vec![channel_result];
+        category_result.issue_channel_ids = vec![Id::new(302)];
+        category_result.manual_channel_ids = vec![Id::new(303)];
+
+        storage.create_category_result(run_id, 0, category_result.clone()).await.unwrap();
+        storage.finish_check_run(run_id, CheckStatus::Completed).await.unwrap();
+
+        let runs = storage.read_check_runs(guild_id, 10).await.unwrap();
+        let (run, totals) = &runs[0];
+
+        assert_eq!(runs.len(), 1);
+        assert_eq!(run.id, run_id);
+        assert_eq!(run.status, CheckStatus::Completed);
+        assert!(run.finished_at.unwrap() >= run.started_at);
+        assert_eq!(*totals, CheckTotals { channels: 3, good: 1, bad: 1, unresolved: 0 });
+        assert_eq!(storage.read_check_run(guild_id, run_id).await.unwrap().map(|run| run.id), Some(run_id));
+        assert_eq!(storage.read_category_results(run_id).await.unwrap(), vec![category_result]);
+    }
+}
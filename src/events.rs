@@ -1,16 +1,94 @@
 use crate::{
-    commands::*,
-    constants::CLIENT_ID,
+    commands::{*, error::{report, CommandError, SHUTDOWN_MESSAGE}},
+    database::access::is_allowed,
     util::{context::Context, invite::extract_codes_from_message}
 };
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
+use tracing::{error, info, info_span, Instrument};
 use twilight_model::{
-    application::interaction::Interaction,
+    application::interaction::{ApplicationCommand, Interaction, MessageComponentInteraction},
     channel::Channel,
     gateway::event::Event,
-    guild::Permissions
+    guild::{PartialMember, Permissions},
+    id::{Id, marker::GuildMarker}
 };
 
+// Interactions carry the member's roles and channel permissions, so this works even when the member isn't cached
+async fn is_user_allowed(context: &Context, guild_id: Id<GuildMarker>, member: Option<&PartialMember>, name: &str) -> Result<bool, CommandError> {
+    let member = member.ok_or_else(|| CommandError::Internal("guild interaction without member data".to_string()))?;
+    let member_permissions = member.permissions.unwrap_or_else(Permissions::empty);
+
+    Ok(match name {
+        "access" => member_permissions.contains(Permissions::ADMINISTRATOR),
+        "reset-cooldown" => context.config.owner_id.is_some() && member.user.as_ref().map(|user| user.id) == context.config.owner_id,
+        name => {
+            // Cancelling a check takes the same access as starting one
+            let name = if name == "cancel-check" { "check" } else { name };
+            let rules = context.database.read_command_access(guild_id).await?;
+
+            is_allowed(rules.iter().find(|access| access.command == name), &member.roles, member_permissions)
+        }
+    })
+}
+
+async fn dispatch(command: ApplicationCommand, context: Arc<Context>) -> Result<(), CommandError> {
+    if context.shutdown.is_started() {
+        return Err(CommandError::Validation(SHUTDOWN_MESSAGE.to_string()))
+    }
+
+    let guild_id = command.guild_id.unwrap();
+    let is_user_allowed = is_user_allowed(&context, guild_id, command.member.as_ref(), &command.data.name).await?;
+    let minimum_client_permissions = Permissions::EMBED_LINKS | Permissions::READ_MESSAGE_HISTORY | Permissions::SEND_MESSAGES | Permissions::USE_SLASH_COMMANDS | Permissions::VIEW_CHANNEL;
+    let client_can_see_channel = match context.cache.permissions().in_channel(context.client_id, command.channel_id) {
+        Ok(permissions) => permissions.contains(minimum_client_permissions),
+        Err(_) => false
+    };
+
+    if !is_user_allowed {
+        return Err(CommandError::Permission("You don't have access to this command.".to_string()))
+    }
+
+    if !client_can_see_channel {
+        return Err(CommandError::Permission(
+            "Sakura needs the View Channel, Send Messages, Embed Links and Read Message History permissions in this channel.".to_string()
+        ))
+    }
+
+    match command.data.name.as_str() {
+        "access" => AccessCommand::run(command, context).await,
+        "cancel-check" => CancelCheckCommand::run(command, context).await,
+        "category" => CategoryCommand::run(command, context).await,
+        "check" => CheckCommand::run(command, context).await,
+        "history" => HistoryCommand::run(command, context).await,
+        "ignore" => IgnoreCommand::run(command, context).await,
+        "ping" => PingCommand::run(command, context).await,
+        "reset-cooldown" => ResetCooldownCommand::run(command, context).await,
+        "set" => SetCommand::run(command, context).await,
+        "settings" => SettingsCommand::run(command, context).await,
+        "stats" => StatsCommand::run(command, context).await,
+        _ => Ok(())
+    }
+}
+
+// Buttons are prefixed with the command that made them, and need the same access as running it
+async fn dispatch_component(component: MessageComponentInteraction, context: Arc<Context>) -> Result<(), CommandError> {
+    if context.shutdown.is_started() {
+        return Err(CommandError::Validation(SHUTDOWN_MESSAGE.to_string()))
+    }
+
+    let guild_id = component.guild_id.unwrap();
+    let name = component.data.custom_id.split(':').next().unwrap_or_default().to_string();
+
+    if !is_user_allowed(&context, guild_id, component.member.as_ref(), &name).await? {
+        return Err(CommandError::Permission("You don't have access to this command.".to_string()))
+    }
+
+    match name.as_str() {
+        "history" => HistoryCommand::paginate(component, context).await,
+        _ => Ok(())
+    }
+}
+
 pub async fn handle(event: Event, context: Arc<Context>) {
     context.cache.update(&event);
 
@@ -18,51 +96,200 @@ pub async fn handle(event: Event, context: Arc<Context>) {
         Event::ChannelDelete(channel) => {
             if let Channel::Guild(guild_channel) = channel.0 {
                 if let Some(guild_id) = guild_channel.guild_id() {
-                    context.database.delete_channel(guild_id, guild_channel.id()).await
+                    let channel_id = guild_channel.id();
+
+                    if let Err(error) = context.database.delete_channel(guild_id, channel_id).await {
+                        error!(%guild_id, %channel_id, %error, "could not remove a deleted channel");
+                    }
                 }
             }
         },
-        Event::GuildCreate(guild) => context.database.create_setting(guild.id).await,
-        Event::GuildDelete(guild) => context.database.delete_setting(guild.id).await,
+        Event::GuildCreate(guild) => {
+            if let Err(error) = context.database.create_setting(guild.id).await {
+                error!(guild_id = %guild.id, %error, "could not create settings");
+            }
+        },
+        Event::GuildDelete(guild) => {
+            if let Err(error) = context.database.delete_setting(guild.id).await {
+                error!(guild_id = %guild.id, %error, "could not delete settings");
+            }
+        },
         Event::InteractionCreate(interaction) => {
-            if let Some(guild_id) = interaction.guild_id() {
-                if let Interaction::ApplicationCommand(command) = interaction.0 {
-                    let user_id = command.clone().member.unwrap().user.unwrap().id;
-                    let is_user_admin = match context.cache.permissions().root(user_id, guild_id) {
-                        Ok(permissions) => permissions.contains(Permissions::ADMINISTRATOR),
-                        Err(_) => false
-                    };
-                    let minimum_client_permissions = Permissions::EMBED_LINKS | Permissions::READ_MESSAGE_HISTORY | Permissions::SEND_MESSAGES | Permissions::USE_SLASH_COMMANDS | Permissions::VIEW_CHANNEL;
-                    let client_can_see_channel = match context.cache.permissions().in_channel(CLIENT_ID.clone(), command.channel_id) {
-                        Ok(permissions) => permissions.contains(minimum_client_permissions),
-                        Err(_) => false
-                    };
-
-                    if is_user_admin & client_can_see_channel {
-                        match command.data.name.as_str() {
-                            "category" => CategoryCommand::run(*command, context).await,
-                            "check" => CheckCommand::run(*command, context).await.unwrap(),
-                            "ignore" => IgnoreCommand::run(*command, context).await,
-                            "ping" => PingCommand::run(*command, context).await,
-                            "set" => SetCommand::run(*command, context).await,
-                            "settings" => SettingsCommand::run(*command, context).await,
-                            "stats" => StatsCommand::run(*command, context).await,
-                            _ => {}
-                        }                        
-                    }
-                }
+            if interaction.guild_id().is_none() {
+                return
+            }
+
+            match interaction.0 {
+                Interaction::ApplicationCommand(command) => {
+                    let (interaction_id, token) = (command.id, command.token.clone());
+                    let span = info_span!(
+                        "command",
+                        command = %command.data.name,
+                        guild_id = ?command.guild_id,
+                        user_id = ?command.member.as_ref().and_then(|member| member.user.as_ref()).map(|user| user.id)
+                    );
+
+                    let name = command.data.name.clone();
+
+                    async {
+                        let started_at = Instant::now();
+                        let result = dispatch(*command, context.clone()).await;
+                        let duration = started_at.elapsed();
+                        let duration_ms = duration.as_millis() as u64;
+                        let outcome = match &result {
+                            Ok(()) => "ok",
+                            Err(error) if error.is_user_error() => "rejected",
+                            Err(_) => "failed"
+                        };
+
+                        match &result {
+                            Err(error) if outcome == "failed" => error!(duration_ms, outcome, %error, "command finished"),
+                            Err(error) => info!(duration_ms, outcome, %error, "command finished"),
+                            Ok(()) => info!(duration_ms, outcome, "command finished")
+                        }
+
+                        context.metrics.commands.with_label_values(&[&name, outcome]).inc();
+                        context.metrics.command_duration.with_label_values(&[&name]).observe(duration.as_secs_f64());
+
+                        if let Err(error) = result {
+                            report(&context, interaction_id, &token, &error).await;
+                        }
+                    }.instrument(span).await;
+                },
+                Interaction::MessageComponent(component) => {
+                    let (interaction_id, token) = (component.id, component.token.clone());
+                    let span = info_span!(
+                        "component",
+                        custom_id = %component.data.custom_id,
+                        guild_id = ?component.guild_id,
+                        user_id = ?component.member.as_ref().and_then(|member| member.user.as_ref()).map(|user| user.id)
+                    );
+
+                    async {
+                        match dispatch_component(*component, context.clone()).await {
+                            Err(error) if error.is_user_error() => {
+                                info!(%error, "component rejected");
+                                report(&context, interaction_id, &token, &error).await;
+                            },
+                            Err(error) => {
+                                error!(%error, "component failed");
+                                report(&context, interaction_id, &token, &error).await;
+                            },
+                            Ok(()) => {}
+                        }
+                    }.instrument(span).await;
+                },
+                _ => {}
             }
         },
         Event::MessageCreate(message) => {
             if let Some(guild_id) = message.guild_id {
                 let codes = extract_codes_from_message(message.0);
                 
-                if codes.len() > 0 {
-                    context.database.create_invites(guild_id, codes).await;
+                if !codes.is_empty() {
+                    context.invite_buffer.push(guild_id, codes);
                 }
             }
         },
-        Event::Ready(ready) => println!("{}#{} is online!", ready.user.name, ready.user.discriminator),
+        Event::Ready(ready) => info!(user = %format!("{}#{}", ready.user.name, ready.user.discriminator), guilds = ready.guilds.len(), "online"),
         _ => {}
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        database::access::CommandAccess,
+        testing::{self, discord::MockDiscord}
+    };
+    use serde_json::json;
+    use twilight_model::{
+        application::interaction::Interaction,
+        gateway::{event::Event, payload::incoming::InteractionCreate},
+        guild::Permissions,
+        id::Id
+    };
+    use super::handle;
+
+    #[tokio::test]
+    async fn configured_roles_can_use_commands_without_being_admins() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+        let partner_manager = Id::new(50);
+        let event = |name: &str| Event::InteractionCreate(Box::new(InteractionCreate(
+            testing::interaction(name, testing::RESULTS_CHANNEL_ID, json!([]), &[partner_manager], Permissions::SEND_MESSAGES)
+        )));
+
+        context.database.create_setting(testing::GUILD_ID).await.unwrap();
+        handle(event("settings"), context.clone()).await;
+
+        let access = CommandAccess::new(testing::GUILD_ID, "settings".to_string());
+
+        access.role_ids.insert(partner_manager);
+        context.database.upsert_command_access(access).await.unwrap();
+        handle(event("settings"), context.clone()).await;
+        handle(event("access"), context.clone()).await;
+
+        let callbacks = mock.callbacks();
+
+        assert_eq!(callbacks.len(), 3);
+        assert_eq!(callbacks[0]["data"]["flags"], 64);
+        assert!(callbacks[1]["data"]["flags"].is_null());
+        assert_eq!(callbacks[1]["data"]["embeds"][0]["fields"].as_array().unwrap().len(), 7);
+        assert_eq!(callbacks[2]["data"]["flags"], 64);
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_check_takes_the_same_access_as_running_one() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+        let partner_manager = Id::new(50);
+        let event = || Event::InteractionCreate(Box::new(InteractionCreate(
+            testing::interaction("cancel-check", testing::RESULTS_CHANNEL_ID, json!([]), &[partner_manager], Permissions::SEND_MESSAGES)
+        )));
+
+        context.database.create_setting(testing::GUILD_ID).await.unwrap();
+        handle(event(), context.clone()).await;
+
+        let access = CommandAccess::new(testing::GUILD_ID, "check".to_string());
+
+        access.role_ids.insert(partner_manager);
+        context.database.upsert_command_access(access).await.unwrap();
+        handle(event(), context.clone()).await;
+
+        let callbacks = mock.callbacks();
+
+        assert_eq!(callbacks[0]["data"]["embeds"][0]["description"], "You don't have access to this command.");
+        assert_eq!(callbacks[1]["data"]["embeds"][0]["description"], "There is no invite check in progress for this guild.");
+    }
+
+    #[tokio::test]
+    async fn only_the_bot_owner_can_reset_a_cooldown() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+        let event = |user_id: u64| {
+            let mut interaction = testing::interaction("reset-cooldown", testing::RESULTS_CHANNEL_ID, json!([]), &[], Permissions::ADMINISTRATOR);
+
+            if let Interaction::ApplicationCommand(command) = &mut interaction {
+                command.member.as_mut().unwrap().user.as_mut().unwrap().id = Id::new(user_id);
+            }
+
+            Event::InteractionCreate(Box::new(InteractionCreate(interaction)))
+        };
+
+        context.database.create_setting(testing::GUILD_ID).await.unwrap();
+        context.database.update_last_check(testing::GUILD_ID).await.unwrap();
+        handle(event(12), context.clone()).await;
+
+        assert!(context.database.read_setting(testing::GUILD_ID).await.unwrap().unwrap().last_check.is_some());
+
+        handle(event(testing::OWNER_ID), context.clone()).await;
+
+        let callbacks = mock.callbacks();
+
+        assert_eq!(callbacks.len(), 2);
+        assert_eq!(callbacks[0]["data"]["flags"], 64);
+        assert_eq!(callbacks[1]["data"]["embeds"][0]["description"], "Guild 100 can run an invite check again right away.");
+        assert!(context.database.read_setting(testing::GUILD_ID).await.unwrap().unwrap().last_check.is_none());
+    }
+}
@@ -1,31 +1,63 @@
 extern crate onig;
 
 mod commands;
+mod config;
 mod constants;
 mod database;
 mod events;
+mod server;
 mod tasks;
+#[cfg(test)]
+mod testing;
 mod util;
 
-use commands::*;
+use commands::registry;
+use config::Config;
 use constants::*;
 use dotenv::dotenv;
 use futures_util::stream::StreamExt;
-use std::{error::Error, sync::Arc};
+use std::{env, error::Error, process, sync::Arc};
+use tokio::sync::oneshot;
 use twilight_gateway::cluster::{ClusterBuilder, ShardScheme};
+use tracing::{info, info_span, Instrument};
 use twilight_http::client::ClientBuilder;
-use twilight_interactions::command::CreateCommand;
-use util::context::Context;
+use util::{context::Context, logging, shutdown};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
     dotenv().ok();
 
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{error}");
+            process::exit(1);
+        }
+    };
 
+    logging::init(&config);
     let client = ClientBuilder::new()
-        .token(TOKEN.to_string())
+        .token(config.bot_token.clone())
         .build();
     let client = Arc::new(client);
+
+    // `sakura sync-commands` publishes the command definitions and exits without connecting to the gateway
+    match env::args().nth(1).as_deref() {
+        Some("sync-commands") => {
+            let scope = config.command_scope();
+            let report = registry::sync(&client.interaction(config.application_id), scope).await?;
+
+            println!("Synced commands {scope}: {report}");
+
+            return Ok(())
+        },
+        Some(action) => {
+            eprintln!("Unknown action {action:?}, expected sync-commands");
+            process::exit(2);
+        },
+        None => {}
+    }
+
     let client_clone = client.clone();
     let gateway_info = client
         .gateway()
@@ -43,42 +75,63 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
         },
         Err(_) => ShardScheme::Auto,
     };
-    let (cluster, mut events) = ClusterBuilder::new(TOKEN.to_string(), *INTENTS)
+    let client_id = match config.client_id {
+        Some(client_id) => client_id,
+        None => client.current_user().exec().await?.model().await?.id
+    };
+    let (cluster, mut events) = ClusterBuilder::new(config.bot_token.clone(), *INTENTS)
         // .event_types(EventTypeFlags::SHARD_PAYLOAD)
         .http_client(client_clone)
         .shard_scheme(shard_scheme)
         .build()
         .await?;
-    let context = Arc::new(Context::new(client, cluster));
+    let database = database::connect(&config.database_url)?;
+    let context = Arc::new(Context::new(config, client, client_id, cluster, database));
     let context_clone = context.clone();
+
+    for migration in context.database.migrate().await? {
+        info!(version = migration.version, name = %migration.name, "applied migration");
+    }
+
+    // Before the gateway is up, so no new check can take a lock that this would then clear
+    tasks::recover::interrupted_checks(context.clone(), true).await;
   
     tokio::spawn(async move {
         context_clone.cluster.up().await;
-        context_clone.database.create_tables().await;
     });
     tokio::spawn(tasks::start(context.clone()));
+    tokio::spawn(server::serve(context.clone()));
 
+    let scope = context.config.command_scope();
+    let report = registry::sync(&context.get_interaction_client(), scope).await?;
 
+    info!(%scope, %report, "synced commands");
 
-    context
-        .get_interaction_client()
-        .set_guild_commands(
-            *TEST_GUILD_ID,
-            &[
-                CategoryCommand::create_command().into(),
-                CheckCommand::create_command().into(),
-                IgnoreCommand::create_command().into(),
-                PingCommand::create_command().into(),
-                SetCommand::create_command().into(),
-                SettingsCommand::create_command().into(),
-                StatsCommand::create_command().into()
-            ]
-        )
-        .exec()
-        .await?;
+    // Events keep flowing while shutting down, so commands sent in the meantime are told Sakura is restarting
+    let (stopped, mut is_stopped) = oneshot::channel::<()>();
+    let shutdown_context = context.clone();
+
+    tokio::spawn(async move {
+        shutdown::signal().await;
+        shutdown::run(shutdown_context).await;
+        stopped.send(()).ok();
+    });
+
+    loop {
+        let (shard_id, event) = tokio::select! {
+            _ = &mut is_stopped => break,
+            event = events.next() => match event {
+                Some(event) => event,
+                None => break
+            }
+        };
+        let span = info_span!("event", shard_id, kind = ?event.kind());
+
+        context.metrics.gateway_events
+            .with_label_values(&[&shard_id.to_string(), event.kind().name().unwrap_or("UNKNOWN")])
+            .inc();
 
-    while let Some((_, event)) = events.next().await {
-        tokio::spawn(events::handle(event, context.clone()));
+        tokio::spawn(events::handle(event, context.clone()).instrument(span));
     }
 
     Ok(())
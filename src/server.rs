@@ -0,0 +1,210 @@
+use crate::util::context::Context;
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body,
+    Method,
+    Request,
+    Response,
+    Server,
+    StatusCode
+};
+use serde_json::{json, Map, Value};
+use std::{convert::Infallible, sync::Arc, time::Duration};
+use tokio::time;
+use tracing::{error, info, warn};
+use twilight_gateway::shard::Stage;
+
+// Long enough for a busy pool, short enough that the orchestrator's probe doesn't time out first
+const DATABASE_PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub async fn serve(context: Arc<Context>) {
+    let address = context.config.http_address;
+    let make_service = make_service_fn(move |_| {
+        let context = context.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let context = context.clone();
+
+                async move { Ok::<_, Infallible>(respond(&context, request).await) }
+            }))
+        }
+    });
+    let server = match Server::try_bind(&address) {
+        Ok(builder) => builder.serve(make_service),
+        Err(error) => {
+            error!(%address, %error, "could not start the http server");
+            return
+        }
+    };
+
+    info!(%address, "http server listening");
+
+    if let Err(error) = server.await {
+        error!(%error, "http server stopped");
+    }
+}
+
+async fn respond(context: &Context, request: Request<Body>) -> Response<Body> {
+    match (request.method(), request.uri().path()) {
+        (&Method::GET, "/health") => health(context),
+        (&Method::GET, "/ready") => ready(context).await,
+        (&Method::GET, "/metrics") => Response::builder()
+            .header(CONTENT_TYPE, "text/plain; version=0.0.4")
+            .body(Body::from(metrics(context).await))
+            .unwrap(),
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap()
+    }
+}
+
+fn json_response(is_healthy: bool, mut body: Map<String, Value>) -> Response<Body> {
+    let status = if is_healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+
+    body.insert("status".to_string(), json!(if is_healthy { "ok" } else { "unavailable" }));
+
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(Value::Object(body).to_string()))
+        .unwrap()
+}
+
+// Liveness only fails when a background loop has stalled, since restarting is the only fix for that
+fn health(context: &Context) -> Response<Body> {
+    let statuses = context.heartbeats.statuses();
+    let tasks = statuses
+        .iter()
+        .map(|(task, elapsed, is_looping)| (task.to_string(), json!({ "is_looping": is_looping, "seconds_since_beat": elapsed.as_secs() })))
+        .collect::<Map<String, Value>>();
+    let is_healthy = statuses.iter().all(|(_, _, is_looping)| *is_looping);
+    let mut body = Map::new();
+
+    body.insert("tasks".to_string(), Value::Object(tasks));
+
+    json_response(is_healthy, body)
+}
+
+// Readiness fails while Sakura can't serve commands, which shards reconnecting or the database coming back will fix
+async fn ready(context: &Context) -> Response<Body> {
+    let mut shards = Map::new();
+    let mut are_shards_connected = true;
+
+    for shard in context.cluster.shards() {
+        let stage = shard.info().map(|info| info.stage());
+
+        are_shards_connected &= matches!(stage, Ok(Stage::Connected));
+        shards.insert(
+            shard.config().shard()[0].to_string(),
+            json!(stage.map_or_else(|_| "Inactive".to_string(), |stage| stage.to_string()))
+        );
+    }
+
+    let database = match time::timeout(DATABASE_PING_TIMEOUT, context.database.ping()).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(error)) => Err(error.to_string()),
+        Err(_) => Err("timed out".to_string())
+    };
+    let is_ready = are_shards_connected && !shards.is_empty() && database.is_ok();
+    let mut body = Map::new();
+
+    body.insert("database".to_string(), match database {
+        Ok(()) => json!({ "is_reachable": true }),
+        Err(error) => json!({ "is_reachable": false, "error": error })
+    });
+    body.insert("shards".to_string(), Value::Object(shards));
+
+    json_response(is_ready, body)
+}
+
+// Gauges that live in the database are refreshed on scrape instead of on a timer
+async fn metrics(context: &Context) -> String {
+    match context.database.count_unchecked_codes().await {
+        Ok(count) => context.metrics.invite_backlog.set(count as i64),
+        Err(error) => warn!(%error, "could not count unchecked codes")
+    }
+
+    if let Some(status) = context.database.pool_status() {
+        let connections = &context.metrics.database_connections;
+
+        connections.with_label_values(&["max"]).set(status.max_size as i64);
+        connections.with_label_values(&["open"]).set(status.size as i64);
+        connections.with_label_values(&["idle"]).set(status.available.max(0) as i64);
+        connections.with_label_values(&["waiting"]).set((-status.available).max(0) as i64);
+    }
+
+    context.metrics.render()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        events::handle,
+        testing::{self, discord::MockDiscord, GUILD_ID, RESULTS_CHANNEL_ID},
+        util::context::Context
+    };
+    use hyper::{body, Body, Request, StatusCode};
+    use serde_json::json;
+    use serde_json::Value;
+    use std::{collections::HashSet, time::Duration};
+    use twilight_model::{
+        gateway::{event::Event, payload::incoming::InteractionCreate},
+        guild::Permissions
+    };
+    use super::respond;
+
+    async fn get(context: &Context, path: &str) -> (StatusCode, String) {
+        let response = respond(context, Request::get(path).body(Body::empty()).unwrap()).await;
+        let status = response.status();
+
+        (status, String::from_utf8(body::to_bytes(response.into_body()).await.unwrap().to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn metrics_reports_commands_and_the_invite_backlog() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+
+        context.database.create_invites(GUILD_ID, HashSet::from(["a".to_string(), "b".to_string()])).await.unwrap();
+        handle(
+            Event::InteractionCreate(Box::new(InteractionCreate(testing::interaction("settings", RESULTS_CHANNEL_ID, json!([]), &[], Permissions::empty())))),
+            context.clone()
+        ).await;
+
+        let (_, body) = get(&context, "/metrics").await;
+
+        assert!(body.contains("sakura_commands_total{command=\"settings\",outcome=\"rejected\"} 1"));
+        assert!(body.contains("sakura_invite_backlog 2"));
+        assert_eq!(get(&context, "/other").await.0, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn health_tracks_task_heartbeats_and_ready_requires_connected_shards() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+
+        context.heartbeats.beat("revalidation", Duration::from_secs(60));
+        assert_eq!(get(&context, "/health").await.0, StatusCode::OK);
+
+        context.heartbeats.beat("invite_buffer_flush", Duration::ZERO);
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        let (status, body) = get(&context, "/health").await;
+        let body = serde_json::from_str::<Value>(&body).unwrap();
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["tasks"]["invite_buffer_flush"]["is_looping"], false);
+        assert_eq!(body["tasks"]["revalidation"]["is_looping"], true);
+
+        // The test cluster is never brought up, so its shard can't be connected
+        let (status, body) = get(&context, "/ready").await;
+        let body = serde_json::from_str::<Value>(&body).unwrap();
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["database"]["is_reachable"], true);
+        assert_eq!(body["shards"]["0"], "Inactive");
+    }
+}
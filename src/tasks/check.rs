@@ -1,29 +1,66 @@
-use crate::{database::invite::Code, util::context::Context};
+use crate::{
+    database::invite::Code,
+    util::{context::Context, resolver::Priority}
+};
 use futures_util::stream::{self, StreamExt};
-use std::sync::Arc;
-
-pub async fn unchecked_codes(context: Arc<Context>, amount: u16) {  
-    let context_clone = context.clone();
-    if let Some(unchecked_codes) = context.database.read_unchecked_codes(amount).await {
-        stream::iter(unchecked_codes)
-            .for_each_concurrent(
-                2,
-                move |Code { guild_id, code }| {
-                    let context_clone = context_clone.clone();
-
-                    async move {
-                        let (expires_at, is_permanent, is_valid, ) = match context_clone.client.invite(&code).with_expiration().exec().await {
-                            Ok(response) => {
-                                let invite = response.model().await.unwrap();
-
-                                (invite.expires_at, invite.expires_at.is_none() && invite.max_age.is_none() && invite.max_uses.is_none(), true)
-                            },
-                            Err(_) => (None, false, false),
-                        };
-
-                        context_clone.database.upsert_code(guild_id, code, expires_at, is_permanent, is_valid).await;
+use std::{sync::Arc, time::Instant};
+use tracing::{info, instrument, warn};
+
+#[instrument(name = "check_unchecked_codes", skip(context))]
+pub async fn unchecked_codes(context: Arc<Context>, amount: u16) {
+    let codes = match context.database.read_unchecked_codes(amount).await {
+        Ok(codes) => codes,
+        Err(error) => {
+            warn!(%error, "could not read codes");
+            return
+        }
+    };
+    let count = codes.len();
+    let started_at = Instant::now();
+
+    context.metrics.task_batch_size.with_label_values(&["check_unchecked_codes"]).observe(count as f64);
+
+    stream::iter(codes)
+        .for_each_concurrent(
+            2,
+            |Code { guild_id, code }| {
+                let context = context.clone();
+
+                async move {
+                    if let Err(error) = context.resolver.check(context.database.as_ref(), guild_id, code.clone(), Priority::Background).await {
+                        warn!(%guild_id, code, %error, "could not record invite");
                     }
                 }
-            ).await;
+            }
+        ).await;
+
+    info!(count, duration_ms = started_at.elapsed().as_millis() as u64, "batch finished");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{self, discord::{InviteState, MockDiscord}, GUILD_ID};
+    use std::collections::HashSet;
+
+    #[tokio::test]
+    async fn unchecked_codes_only_records_conclusive_lookups() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+
+        mock.set_invite("valid", InviteState::Valid { expires_at: None, max_age: Some(86_400), max_uses: None });
+        mock.set_invite("unknown", InviteState::Unknown);
+        mock.set_invite("limited", InviteState::RateLimited);
+        context.database.create_invites(GUILD_ID, HashSet::from(["valid", "unknown", "limited"].map(String::from))).await.unwrap();
+
+        super::unchecked_codes(context.clone(), 10).await;
+
+        let invites = context.database.read_guild_invites(GUILD_ID).await.unwrap();
+        assert!(invites["valid"].is_checked);
+        assert_eq!(invites["valid"].is_valid, Some(true));
+        assert_eq!(invites["valid"].is_permanent, Some(false));
+        assert!(invites["unknown"].is_checked);
+        assert_eq!(invites["unknown"].is_valid, Some(false));
+        assert!(!invites["limited"].is_checked);
+        assert_eq!(mock.invite_lookups("limited"), 3);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,26 @@
+use crate::util::context::Context;
+use std::{sync::Arc, time::Duration};
+use tokio::time;
+use tracing::{debug, info_span, warn, Instrument};
+
+pub async fn invite_buffer(context: Arc<Context>, period: Duration) {
+    let mut interval = time::interval(period);
+
+    loop {
+        interval.tick().await;
+        context.heartbeats.beat("invite_buffer_flush", period * 2 + Duration::from_secs(30));
+        let span = info_span!("flush_invite_buffer");
+
+        async {
+            match context.invite_buffer.flush(context.database.as_ref()).await {
+                // Most ticks find the buffer empty, which would drown out the real batches
+                Ok(report) if report.new + report.known > 0 => {
+                    context.metrics.task_batch_size.with_label_values(&["flush_invite_buffer"]).observe((report.new + report.known) as f64);
+                    debug!(new = report.new, known = report.known, "flushed invite buffer");
+                },
+                Ok(_) => {},
+                Err(error) => warn!(%error, "could not flush invite buffer")
+            }
+        }.instrument(span).await;
+    }
+}
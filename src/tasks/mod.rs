@@ -1,11 +1,19 @@
 mod check;
+mod flush;
+pub mod recover;
+mod schedule;
 mod update;
 
 use chrono::{Duration, Timelike, Utc};
 use crate::util::context::Context;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration as StdDuration};
 use tokio::time::{Instant, self};
 
+const SCHEDULE_INTERVAL: StdDuration = StdDuration::from_secs(60);
+
+// Each phase waits for the next ten minute threshold, then however long its lookups take
+const REVALIDATION_MAX_GAP: StdDuration = StdDuration::from_secs(1_800);
+
 fn next_threshold(ms: i64) -> Instant {
     let instant = Instant::now();
     let now = Utc::now();
@@ -19,10 +27,17 @@ fn next_threshold(ms: i64) -> Instant {
 
 
 pub async fn start(context: Arc<Context>) {
+    tokio::spawn(flush::invite_buffer(context.clone(), context.config.invite_flush_interval));
+    tokio::spawn(schedule::checks(context.clone(), SCHEDULE_INTERVAL));
+
     loop {
+        context.heartbeats.beat("revalidation", REVALIDATION_MAX_GAP);
+        recover::interrupted_checks(context.clone(), false).await;
         time::sleep_until(next_threshold(600_000)).await;
         check::unchecked_codes(context.clone(), 4).await;
+        context.heartbeats.beat("revalidation", REVALIDATION_MAX_GAP);
         time::sleep_until(next_threshold(600_000)).await;
         update::checked_codes(context.clone(), 4).await;
+        context.resolver.prune();
     }
 }
\ No newline at end of file
@@ -0,0 +1,132 @@
+use chrono::{Duration, Utc};
+use crate::{
+    commands::error::CommandError,
+    database::setting::Setting,
+    util::context::Context
+};
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
+use twilight_embed_builder::EmbedBuilder;
+
+// At startup nothing in this process can be checking yet, so every held lock is recovered. While running, only stuck ones are.
+#[instrument(name = "recover_interrupted_checks", skip(context))]
+pub async fn interrupted_checks(context: Arc<Context>, is_startup: bool) {
+    let started_before = if is_startup {
+        None
+    } else {
+        Duration::from_std(context.config.check_lock_timeout)
+            .ok()
+            .map(|timeout| (Utc::now() - timeout).naive_utc())
+    };
+    let settings = match context.database.read_interrupted_checks(started_before).await {
+        Ok(settings) => settings,
+        Err(error) => {
+            warn!(%error, "could not read interrupted checks");
+            return
+        }
+    };
+
+    for setting in settings {
+        let guild_id = setting.guild_id;
+
+        // Deep scans can outlast check_lock_timeout, and a check this process is still running isn't stuck
+        if context.shutdown.is_check_active(guild_id) {
+            continue
+        }
+
+        // last_check is only written once a check finishes, so one written after this check started belongs to it
+        let has_used_cooldown = matches!(
+            (setting.last_check, setting.check_started_at),
+            (Some(last_check), Some(check_started_at)) if last_check >= check_started_at
+        );
+        let is_refunded = has_used_cooldown && context.config.refund_interrupted_checks;
+
+        if let Err(error) = context.database.recover_check(guild_id, is_refunded).await {
+            warn!(%guild_id, %error, "could not recover interrupted check");
+            continue
+        }
+
+        if let Err(error) = context.database.abort_check_runs(guild_id).await {
+            warn!(%guild_id, %error, "could not mark check runs as aborted");
+        }
+
+        info!(%guild_id, is_refunded, "recovered interrupted check");
+
+        if let Err(error) = post_notice(&context, &setting, has_used_cooldown, is_refunded).await {
+            warn!(%guild_id, %error, "could not post the interrupted check notice");
+        }
+    }
+}
+
+async fn post_notice(context: &Context, setting: &Setting, has_used_cooldown: bool, is_refunded: bool) -> Result<(), CommandError> {
+    let results_channel_id = match setting.results_channel_id {
+        Some(channel_id) => channel_id,
+        None => return Ok(())
+    };
+    let cooldown = match (has_used_cooldown, is_refunded) {
+        (true, true) => "The cooldown it started has been refunded, so you can run it again right away.",
+        (true, false) => "You can run another check once your cooldown ends.",
+        (false, _) => "It didn't count towards your cooldown, so you can run it again right away."
+    };
+    let embed = EmbedBuilder::new()
+        .color(setting.embed_color)
+        .description(format!("Sakura stopped before your last invite check could finish. {cooldown}"))
+        .build()?;
+
+    context.client.create_message(results_channel_id).embeds(&[embed])?.exec().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{self, discord::MockDiscord, GUILD_ID, RESULTS_CHANNEL_ID};
+    use twilight_model::id::Id;
+
+    #[tokio::test]
+    async fn startup_recovers_every_held_lock_and_refunds_the_cooldown() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+        let idle_guild_id = Id::new(101);
+
+        for guild_id in [GUILD_ID, idle_guild_id] {
+            context.database.create_setting(guild_id).await.unwrap();
+        }
+        context.database.update_results_channel_id(GUILD_ID, Some(RESULTS_CHANNEL_ID)).await.unwrap();
+        context.database.update_in_check(GUILD_ID, true).await.unwrap();
+        context.database.update_last_check(GUILD_ID).await.unwrap();
+
+        // A lock taken moments ago isn't stale yet, so the periodic sweep leaves it alone
+        super::interrupted_checks(context.clone(), false).await;
+
+        assert!(context.database.read_setting(GUILD_ID).await.unwrap().unwrap().in_check);
+
+        super::interrupted_checks(context.clone(), true).await;
+
+        let setting = context.database.read_setting(GUILD_ID).await.unwrap().unwrap();
+        assert!(!setting.in_check);
+        assert!(setting.check_started_at.is_none());
+        assert!(setting.last_check.is_none());
+
+        let messages = mock.created_messages(RESULTS_CHANNEL_ID);
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0]["embeds"][0]["description"].as_str().unwrap().contains("refunded"));
+        assert!(context.database.read_interrupted_checks(None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn checks_still_running_in_this_process_are_left_alone() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+
+        context.database.create_setting(GUILD_ID).await.unwrap();
+        context.database.update_results_channel_id(GUILD_ID, Some(RESULTS_CHANNEL_ID)).await.unwrap();
+        context.database.update_in_check(GUILD_ID, true).await.unwrap();
+        context.shutdown.track_check(GUILD_ID, RESULTS_CHANNEL_ID);
+
+        super::interrupted_checks(context.clone(), true).await;
+
+        assert!(context.database.read_setting(GUILD_ID).await.unwrap().unwrap().in_check);
+        assert!(mock.created_messages(RESULTS_CHANNEL_ID).is_empty());
+    }
+}
@@ -0,0 +1,169 @@
+use chrono::Utc;
+use crate::{
+    commands::{error::CommandError, CheckCommand},
+    database::setting::Setting,
+    util::context::Context
+};
+use std::{sync::Arc, time::Duration};
+use tokio::time;
+use tracing::{info, info_span, warn, Instrument};
+use twilight_embed_builder::EmbedBuilder;
+
+pub async fn checks(context: Arc<Context>, period: Duration) {
+    let mut interval = time::interval(period);
+
+    loop {
+        interval.tick().await;
+        context.heartbeats.beat("scheduled_checks", period * 2 + Duration::from_secs(30));
+
+        if context.shutdown.is_started() {
+            continue
+        }
+
+        start_due_checks(context.clone()).await;
+    }
+}
+
+// Each due check runs on its own task, so one slow guild doesn't hold up the others
+pub async fn start_due_checks(context: Arc<Context>) -> Vec<tokio::task::JoinHandle<()>> {
+    let now = Utc::now().naive_utc();
+    let settings = match context.database.read_due_schedules(now).await {
+        Ok(settings) => settings,
+        Err(error) => {
+            warn!(%error, "could not read scheduled checks");
+            return vec![]
+        }
+    };
+    let mut handles = vec![];
+
+    for setting in settings {
+        let guild_id = setting.guild_id;
+        let schedule = match setting.schedule {
+            Some(schedule) => schedule,
+            None => continue
+        };
+
+        // Moved on before the check starts, so a check that fails or can't run waits for the next slot instead of retrying every tick
+        if let Err(error) = context.database.update_schedule(guild_id, Some(schedule), Some(schedule.next_after(now))).await {
+            warn!(%guild_id, %error, "could not advance the check schedule");
+            continue
+        }
+
+        let context = context.clone();
+        let span = info_span!("scheduled_check", %guild_id);
+
+        handles.push(tokio::spawn(async move {
+            let known_codes = match CheckCommand::prepare(&context, &setting, None).await {
+                Ok(known_codes) => known_codes,
+                Err(error) => {
+                    warn!(%guild_id, %error, "skipped scheduled check");
+
+                    if error.is_user_error() {
+                        if let Err(error) = post_skipped_notice(&context, &setting, &error).await {
+                            warn!(%guild_id, %error, "could not post the skipped check notice");
+                        }
+                    }
+
+                    return
+                }
+            };
+
+            match CheckCommand::start(context.clone(), setting, known_codes, context.client_id, None, false).await {
+                Ok(()) => info!("scheduled check finished"),
+                // Another check took the guild's lock after prepare
+                Err(error) if error.is_user_error() => info!(%error, "skipped scheduled check"),
+                Err(error) => warn!(%error, "scheduled check failed")
+            }
+        }.instrument(span)));
+    }
+
+    handles
+}
+
+// Nobody ran the command, so the results channel is the only place to say why the slot was skipped
+async fn post_skipped_notice(context: &Context, setting: &Setting, error: &CommandError) -> Result<(), CommandError> {
+    let results_channel_id = match setting.results_channel_id {
+        Some(channel_id) => channel_id,
+        None => return Ok(())
+    };
+    let embed = EmbedBuilder::new()
+        .color(setting.embed_color)
+        .description(format!("Sakura skipped this scheduled invite check. {}", error.user_message()))
+        .build()?;
+
+    context.client.create_message(results_channel_id).embeds(&[embed])?.exec().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use crate::{
+        database::setting::CheckSchedule,
+        testing::{self, discord::{InviteState, MockDiscord}, BOT_ID, CATEGORY_ID, GUILD_ID, PARTNER_CHANNEL_IDS, RESULTS_CHANNEL_ID}
+    };
+    use dashmap::DashSet;
+    use super::start_due_checks;
+
+    #[tokio::test]
+    async fn due_schedules_run_a_check_without_waiting_for_the_cooldown() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+        let schedule = CheckSchedule::Every { hours: 6 };
+
+        context.database.create_setting(GUILD_ID).await.unwrap();
+        context.database.update_results_channel_id(GUILD_ID, Some(RESULTS_CHANNEL_ID)).await.unwrap();
+        context.database.update_category_channel_ids(GUILD_ID, DashSet::from_iter([CATEGORY_ID])).await.unwrap();
+        context.database.update_last_check(GUILD_ID).await.unwrap();
+        context.database.update_schedule(GUILD_ID, Some(schedule), Some((Utc::now() - Duration::minutes(1)).naive_utc())).await.unwrap();
+        mock.set_messages(PARTNER_CHANNEL_IDS[0], &["discord.gg/good"]);
+        mock.set_invite("good", InviteState::permanent());
+
+        for handle in start_due_checks(context.clone()).await {
+            handle.await.unwrap();
+        }
+
+        let messages = mock.created_messages(RESULTS_CHANNEL_ID);
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0]["embeds"][0]["description"], "Sakura is running a scheduled invite check now!");
+        assert_eq!(messages[2]["embeds"][0]["description"], format!("Run by <@{BOT_ID}>"));
+        assert!(mock.callbacks().is_empty());
+        assert_eq!(
+            mock.edited_messages(RESULTS_CHANNEL_ID).last().unwrap()["embeds"][0]["description"],
+            "Sakura finished this invite check. The results are below."
+        );
+
+        let setting = context.database.read_setting(GUILD_ID).await.unwrap().unwrap();
+        assert!(!setting.in_check);
+        assert!(setting.next_scheduled_check.unwrap() > (Utc::now() + Duration::hours(5)).naive_utc());
+
+        assert!(start_due_checks(context.clone()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn skipped_schedules_say_why_in_the_results_channel() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+        let schedule = CheckSchedule::Every { hours: 6 };
+
+        context.database.create_setting(GUILD_ID).await.unwrap();
+        context.database.update_results_channel_id(GUILD_ID, Some(RESULTS_CHANNEL_ID)).await.unwrap();
+        context.database.update_schedule(GUILD_ID, Some(schedule), Some((Utc::now() - Duration::minutes(1)).naive_utc())).await.unwrap();
+
+        for handle in start_due_checks(context.clone()).await {
+            handle.await.unwrap();
+        }
+
+        let messages = mock.created_messages(RESULTS_CHANNEL_ID);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            messages[0]["embeds"][0]["description"],
+            "Sakura skipped this scheduled invite check. There are no categories to check. Please add some before running an invite check."
+        );
+
+        let setting = context.database.read_setting(GUILD_ID).await.unwrap().unwrap();
+        assert!(!setting.in_check);
+        assert!(setting.next_scheduled_check.unwrap() > Utc::now().naive_utc());
+    }
+}
@@ -1,30 +1,64 @@
-use crate::{database::invite::Code, util::context::Context};
+use crate::{
+    database::invite::Code,
+    util::{context::Context, resolver::Priority}
+};
 use futures_util::stream::{self, StreamExt};
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
+use tracing::{info, instrument, warn};
 
+#[instrument(name = "recheck_checked_codes", skip(context))]
 pub async fn checked_codes(context: Arc<Context>, amount: u16) {
-    let context_clone = context.clone();
-
-    if let Some(checked_code) = context.database.read_checked_codes(amount).await {
-        stream::iter(checked_code)
-            .for_each_concurrent(
-                2,
-                move |Code { guild_id, code }| {
-                    let context_clone = context_clone.clone();
-
-                    async move {
-                        let (expires_at, is_permanent, is_valid, ) = match context_clone.client.invite(&code).with_expiration().exec().await {
-                            Ok(response) => {
-                                let invite = response.model().await.unwrap();
-
-                                (invite.expires_at, invite.expires_at.is_none() && invite.max_age.is_none() && invite.max_uses.is_none(), true)
-                            },
-                            Err(_) => (None, false, false),
-                        };
-
-                        context_clone.database.update_code(guild_id, code, expires_at, is_permanent, is_valid).await;
+    let codes = match context.database.read_checked_codes(amount).await {
+        Ok(codes) => codes,
+        Err(error) => {
+            warn!(%error, "could not read codes");
+            return
+        }
+    };
+    let count = codes.len();
+    let started_at = Instant::now();
+
+    context.metrics.task_batch_size.with_label_values(&["recheck_checked_codes"]).observe(count as f64);
+
+    stream::iter(codes)
+        .for_each_concurrent(
+            2,
+            |Code { guild_id, code }| {
+                let context = context.clone();
+
+                async move {
+                    if let Err(error) = context.resolver.check(context.database.as_ref(), guild_id, code.clone(), Priority::Background).await {
+                        warn!(%guild_id, code, %error, "could not record invite");
                     }
                 }
-            ).await;
+            }
+        ).await;
+
+    info!(count, duration_ms = started_at.elapsed().as_millis() as u64, "batch finished");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{self, discord::{InviteState, MockDiscord}, GUILD_ID};
+
+    #[tokio::test]
+    async fn checked_codes_keeps_invites_valid_through_transient_failures() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+
+        for code in ["revoked", "flaky", "recovers"] {
+            context.database.upsert_code(GUILD_ID, code.to_string(), None, true, true).await.unwrap();
+        }
+        mock.set_invite("revoked", InviteState::Unknown);
+        mock.set_invite("flaky", InviteState::ServerError);
+        mock.script_invite("recovers", vec![InviteState::ServerError, InviteState::permanent()]);
+
+        super::checked_codes(context.clone(), 10).await;
+
+        let invites = context.database.read_guild_invites(GUILD_ID).await.unwrap();
+        assert_eq!(invites["revoked"].is_valid, Some(false));
+        assert_eq!(invites["flaky"].is_valid, Some(true));
+        assert_eq!(invites["recovers"].is_valid, Some(true));
+        assert_eq!(mock.invite_lookups("recovers"), 2);
     }
-}
\ No newline at end of file
+}
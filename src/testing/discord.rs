@@ -0,0 +1,414 @@
+use dashmap::{DashMap, DashSet};
+use hyper::{
+    body,
+    service::{make_service_fn, service_fn},
+    Body,
+    Method,
+    Request,
+    Response,
+    Server,
+    StatusCode
+};
+use serde_json::{json, Value};
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+        Mutex
+    }
+};
+use tokio::sync::{oneshot, Semaphore};
+use twilight_model::{
+    datetime::Timestamp,
+    id::{Id, marker::ChannelMarker}
+};
+
+#[derive(Clone, Debug)]
+pub enum InviteState {
+    Valid { expires_at: Option<Timestamp>, max_age: Option<u64>, max_uses: Option<u64> },
+    Unknown,
+    RateLimited,
+    ServerError
+}
+
+impl InviteState {
+    pub fn permanent() -> Self {
+        Self::Valid { expires_at: None, max_age: None, max_uses: None }
+    }
+}
+
+// Uploads come as multipart forms, so record their JSON payload with the files added under "files"
+fn parse_multipart(boundary: &str, bytes: &[u8]) -> Value {
+    let text = String::from_utf8_lossy(bytes);
+    let mut payload = json!({});
+    let mut files = vec![];
+
+    for part in text.split(&format!("--{boundary}")) {
+        let (headers, content) = match part.split_once("\r\n\r\n") {
+            Some((headers, content)) => (headers, content.strip_suffix("\r\n").unwrap_or(content)),
+            None => continue
+        };
+
+        if headers.contains("name=\"payload_json\"") {
+            payload = serde_json::from_str(content).unwrap_or(Value::Null);
+        } else if let Some((_, filename)) = headers.split_once("filename=\"") {
+            files.push(json!({ "content": content, "filename": filename.trim_end_matches('"') }));
+        }
+    }
+
+    payload["files"] = Value::Array(files);
+    payload
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordedMessage {
+    pub channel_id: Id<ChannelMarker>,
+    pub body: Value
+}
+
+#[derive(Default)]
+pub struct MockState {
+    // Callback type for each interaction token that has been responded to
+    acknowledged: DashMap<String, u64>,
+    callbacks: Mutex<Vec<Value>>,
+    command_overwrites: AtomicUsize,
+    // Registered commands, keyed by guild for guild commands and None for global ones
+    commands: DashMap<Option<u64>, Value>,
+    created_messages: Mutex<Vec<RecordedMessage>>,
+    edited_messages: Mutex<Vec<RecordedMessage>>,
+    edited_originals: DashMap<String, Value>,
+    fail_message_creation: AtomicBool,
+    followups: Mutex<Vec<Value>>,
+    // Channels whose history Sakura isn't allowed to read
+    forbidden_channels: DashSet<Id<ChannelMarker>>,
+    // Channels whose history requests each wait for a permit, so a test can act while a check is on them
+    held_channels: DashMap<Id<ChannelMarker>, Arc<Semaphore>>,
+    invite_lookups: DashMap<String, usize>,
+    invites: DashMap<String, VecDeque<InviteState>>,
+    // Newest first with ids fixed up front, like a channel's history, so before cursors line up across requests
+    messages: DashMap<Id<ChannelMarker>, Vec<(u64, String)>>,
+    next_id: AtomicU64
+}
+
+impl MockState {
+    fn next_id(&self) -> u64 {
+        // Snowflakes need a plausible timestamp component for code that decodes them
+        (1_000_000 + self.next_id.fetch_add(1, Ordering::SeqCst)) << 22
+    }
+
+    fn message(&self, channel_id: u64, message_id: u64, content: &str, embeds: Value) -> Value {
+        json!({
+            "attachments": [],
+            "author": { "avatar": null, "discriminator": "0001", "id": "1", "username": "mock" },
+            "channel_id": channel_id.to_string(),
+            "content": content,
+            "edited_timestamp": null,
+            "embeds": embeds,
+            "id": message_id.to_string(),
+            "mention_everyone": false,
+            "mention_roles": [],
+            "mentions": [],
+            "pinned": false,
+            "timestamp": "2022-01-01T00:00:00.000000+00:00",
+            "tts": false,
+            "type": 0
+        })
+    }
+
+    fn invite(&self, code: &str) -> (StatusCode, Value) {
+        *self.invite_lookups.entry(code.to_string()).or_default() += 1;
+
+        let state = match self.invites.get_mut(code) {
+            Some(mut states) if states.len() > 1 => states.pop_front(),
+            Some(states) => states.front().cloned(),
+            None => None
+        };
+
+        match state.unwrap_or(InviteState::Unknown) {
+            InviteState::Valid { expires_at, max_age, max_uses } => (StatusCode::OK, json!({
+                "channel": null,
+                "code": code,
+                "expires_at": expires_at,
+                "max_age": max_age,
+                "max_uses": max_uses
+            })),
+            InviteState::Unknown => (StatusCode::NOT_FOUND, json!({ "code": 10006, "message": "Unknown Invite" })),
+            InviteState::RateLimited => (StatusCode::TOO_MANY_REQUESTS, json!({ "global": false, "message": "You are being rate limited.", "retry_after": 0.01 })),
+            InviteState::ServerError => (StatusCode::INTERNAL_SERVER_ERROR, json!({ "code": 0, "message": "500: Internal Server Error" }))
+        }
+    }
+
+    fn commands(&self, guild_id: Option<u64>) -> Value {
+        self.commands.get(&guild_id).map_or(json!([]), |commands| commands.clone())
+    }
+
+    fn overwrite_commands(&self, guild_id: Option<u64>, mut commands: Value) -> Value {
+        self.command_overwrites.fetch_add(1, Ordering::SeqCst);
+
+        for command in commands.as_array_mut().into_iter().flatten() {
+            command["id"] = json!(self.next_id().to_string());
+            command["version"] = json!("1");
+        }
+
+        self.commands.insert(guild_id, commands.clone());
+
+        commands
+    }
+
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        let method = request.method().clone();
+        let path = request.uri().path().trim_start_matches("/api/v9/").to_string();
+        let query = request.uri().query().unwrap_or_default().to_string();
+        let content_type = request
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let bytes = body::to_bytes(request.into_body()).await.unwrap_or_default();
+        let body = match content_type.strip_prefix("multipart/form-data; boundary=") {
+            Some(boundary) => parse_multipart(boundary, &bytes),
+            None => serde_json::from_slice(&bytes).unwrap_or(Value::Null)
+        };
+        let segments = path.split('/').collect::<Vec<&str>>();
+
+        let (status, value) = match (&method, segments.as_slice()) {
+            (&Method::GET, ["gateway", "bot"]) => (StatusCode::OK, json!({
+                "session_start_limit": { "max_concurrency": 1, "remaining": 1000, "reset_after": 0, "total": 1000 },
+                "shards": 1,
+                "url": "wss://gateway.discord.gg"
+            })),
+            (&Method::GET, ["invites", code]) => self.invite(code),
+            (&Method::GET, ["channels", channel_id, "messages"])
+                if self.forbidden_channels.iter().any(|forbidden| forbidden.to_string() == *channel_id) => {
+                (StatusCode::FORBIDDEN, json!({ "code": 50001, "message": "Missing Access" }))
+            },
+            (&Method::GET, ["channels", channel_id, "messages"]) => {
+                let channel_id = channel_id.parse::<u64>().unwrap();
+
+                let gate = self.held_channels.get(&Id::new(channel_id)).map(|gate| gate.clone());
+
+                if let Some(gate) = gate {
+                    gate.acquire().await.unwrap().forget();
+                }
+
+                let parameter = |name: &str| query.split('&').find_map(|pair| pair.strip_prefix(name)?.strip_prefix('=')?.parse::<u64>().ok());
+                let limit = parameter("limit").unwrap_or(50).min(100) as usize;
+                let before = parameter("before");
+                let messages = self.messages
+                    .get(&Id::new(channel_id))
+                    .map(|contents| contents
+                        .iter()
+                        .skip_while(|(message_id, _)| before.is_some_and(|before| *message_id >= before))
+                        .take(limit)
+                        .map(|(message_id, content)| self.message(channel_id, *message_id, content, json!([])))
+                        .collect())
+                    .unwrap_or_default();
+
+                (StatusCode::OK, Value::Array(messages))
+            },
+            (&Method::POST, ["channels", _, "messages"]) if self.fail_message_creation.load(Ordering::SeqCst) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, json!({ "code": 0, "message": "500: Internal Server Error" }))
+            },
+            (&Method::POST, ["channels", channel_id, "messages"]) => {
+                let channel_id = channel_id.parse::<u64>().unwrap();
+                let message = self.message(channel_id, self.next_id(), body["content"].as_str().unwrap_or_default(), body["embeds"].clone());
+
+                self.created_messages.lock().unwrap().push(RecordedMessage { channel_id: Id::new(channel_id), body });
+
+                (StatusCode::OK, message)
+            },
+            (&Method::PATCH, ["channels", channel_id, "messages", message_id]) => {
+                let channel_id = channel_id.parse::<u64>().unwrap();
+                let message = self.message(channel_id, message_id.parse().unwrap(), body["content"].as_str().unwrap_or_default(), body["embeds"].clone());
+
+                self.edited_messages.lock().unwrap().push(RecordedMessage { channel_id: Id::new(channel_id), body });
+
+                (StatusCode::OK, message)
+            },
+            (&Method::POST, ["interactions", _, token, "callback"]) => {
+                if self.acknowledged.contains_key(*token) {
+                    (StatusCode::BAD_REQUEST, json!({ "code": 40060, "message": "Interaction has already been acknowledged." }))
+                } else {
+                    self.acknowledged.insert(token.to_string(), body["type"].as_u64().unwrap_or_default());
+                    self.callbacks.lock().unwrap().push(body);
+
+                    (StatusCode::NO_CONTENT, Value::Null)
+                }
+            },
+            (&Method::GET, ["webhooks", _, token, "messages", "@original"]) => {
+                let mut message = self.message(1, self.next_id(), "", json!([]));
+                let is_loading = self.acknowledged.get(*token).is_some_and(|kind| *kind == 5) && !self.edited_originals.contains_key(*token);
+
+                if is_loading {
+                    message["flags"] = json!(128);
+                }
+
+                (StatusCode::OK, message)
+            },
+            (&Method::PATCH, ["webhooks", _, token, "messages", "@original"]) => {
+                self.edited_originals.insert(token.to_string(), body.clone());
+
+                (StatusCode::OK, self.message(1, self.next_id(), body["content"].as_str().unwrap_or_default(), body["embeds"].clone()))
+            },
+            (&Method::POST, ["webhooks", _, _]) => {
+                let message = self.message(1, self.next_id(), body["content"].as_str().unwrap_or_default(), body["embeds"].clone());
+
+                self.followups.lock().unwrap().push(body);
+
+                (StatusCode::OK, message)
+            },
+            (_, ["webhooks", _, _, "messages", _]) => {
+                (StatusCode::OK, self.message(1, self.next_id(), body["content"].as_str().unwrap_or_default(), body["embeds"].clone()))
+            },
+            (&Method::GET, ["applications", _, "commands"]) => (StatusCode::OK, self.commands(None)),
+            (&Method::GET, ["applications", _, "guilds", guild_id, "commands"]) => (StatusCode::OK, self.commands(guild_id.parse().ok())),
+            (&Method::PUT, ["applications", _, "commands"]) => (StatusCode::OK, self.overwrite_commands(None, body)),
+            (&Method::PUT, ["applications", _, "guilds", guild_id, "commands"]) => (StatusCode::OK, self.overwrite_commands(guild_id.parse().ok(), body)),
+            _ => (StatusCode::NOT_FOUND, json!({ "code": 0, "message": "404: Not Found" }))
+        };
+
+        let body = match value {
+            Value::Null => Body::empty(),
+            value => Body::from(value.to_string())
+        };
+
+        Response::builder()
+            .status(status)
+            .header("content-type", "application/json")
+            .body(body)
+            .unwrap()
+    }
+}
+
+// A local stand-in for the parts of the Discord REST API that Sakura uses
+pub struct MockDiscord {
+    address: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    state: Arc<MockState>
+}
+
+impl MockDiscord {
+    pub async fn start() -> Self {
+        let state = Arc::new(MockState::default());
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let state = state.clone();
+
+                    async move { Ok::<_, Infallible>(state.handle(request).await) }
+                }))
+            }
+        });
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let address = server.local_addr();
+        let (shutdown, receiver) = oneshot::channel::<()>();
+
+        tokio::spawn(server.with_graceful_shutdown(async {
+            receiver.await.ok();
+        }));
+
+        Self {
+            address,
+            shutdown: Some(shutdown),
+            state
+        }
+    }
+
+    pub fn address(&self) -> String {
+        self.address.to_string()
+    }
+
+    pub fn set_invite(&self, code: &str, state: InviteState) {
+        self.state.invites.insert(code.to_string(), VecDeque::from([state]));
+    }
+
+    // Each lookup consumes one state until only the last one is left, which then repeats
+    pub fn script_invite(&self, code: &str, states: Vec<InviteState>) {
+        self.state.invites.insert(code.to_string(), VecDeque::from(states));
+    }
+
+    pub fn set_commands(&self, guild_id: Option<u64>, commands: Value) {
+        self.state.commands.insert(guild_id, commands);
+    }
+
+    pub fn command_overwrites(&self) -> usize {
+        self.state.command_overwrites.load(Ordering::SeqCst)
+    }
+
+    pub fn set_messages(&self, channel_id: Id<ChannelMarker>, contents: &[&str]) {
+        let message_ids = contents.iter().map(|_| self.state.next_id()).collect::<Vec<u64>>();
+
+        // Contents are listed newest first, like Discord returns them, so the first gets the newest id
+        self.state.messages.insert(
+            channel_id,
+            message_ids.into_iter().rev().zip(contents.iter().map(|content| content.to_string())).collect()
+        );
+    }
+
+    // Requests for the channel's history wait until the test adds permits to the returned semaphore
+    pub fn hold_messages(&self, channel_id: Id<ChannelMarker>) -> Arc<Semaphore> {
+        let gate = Arc::new(Semaphore::new(0));
+
+        self.state.held_channels.insert(channel_id, gate.clone());
+        gate
+    }
+
+    pub fn forbid_messages(&self, channel_id: Id<ChannelMarker>) {
+        self.state.forbidden_channels.insert(channel_id);
+    }
+
+    pub fn invite_lookups(&self, code: &str) -> usize {
+        self.state.invite_lookups.get(code).map_or(0, |count| *count)
+    }
+
+    pub fn callbacks(&self) -> Vec<Value> {
+        self.state.callbacks.lock().unwrap().clone()
+    }
+
+    pub fn edited_messages(&self, channel_id: Id<ChannelMarker>) -> Vec<Value> {
+        self.state.edited_messages
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|message| message.channel_id == channel_id)
+            .map(|message| message.body.clone())
+            .collect()
+    }
+
+    pub fn edited_original(&self, token: &str) -> Option<Value> {
+        self.state.edited_originals.get(token).map(|body| body.clone())
+    }
+
+    pub fn followups(&self) -> Vec<Value> {
+        self.state.followups.lock().unwrap().clone()
+    }
+
+    pub fn fail_message_creation(&self, fail: bool) {
+        self.state.fail_message_creation.store(fail, Ordering::SeqCst);
+    }
+
+    pub fn created_messages(&self, channel_id: Id<ChannelMarker>) -> Vec<Value> {
+        self.state.created_messages
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|message| message.channel_id == channel_id)
+            .map(|message| message.body.clone())
+            .collect()
+    }
+}
+
+impl Drop for MockDiscord {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
@@ -0,0 +1,235 @@
+pub mod discord;
+
+use crate::{
+    config::{Config, Environment, LogFormat},
+    database::{memory::MemoryStorage, Storage},
+    util::context::Context
+};
+use discord::MockDiscord;
+use serde_json::json;
+use std::{
+    net::SocketAddr,
+    sync::{atomic::{AtomicU64, Ordering}, Arc},
+    time::Duration
+};
+use twilight_gateway::{
+    cluster::{ClusterBuilder, ShardScheme},
+    Intents
+};
+use twilight_http::Client;
+use twilight_model::{
+    application::interaction::{ApplicationCommand, Interaction, MessageComponentInteraction},
+    gateway::{event::Event, payload::incoming::GuildCreate},
+    guild::{Guild, Permissions},
+    id::{Id, marker::{ChannelMarker, GuildMarker, RoleMarker}}
+};
+
+pub const BOT_ID: u64 = 10;
+pub const OWNER_ID: u64 = 11;
+pub const GUILD_ID: Id<GuildMarker> = Id::new(100);
+pub const RESULTS_CHANNEL_ID: Id<ChannelMarker> = Id::new(200);
+pub const CATEGORY_ID: Id<ChannelMarker> = Id::new(300);
+pub const PARTNER_CHANNEL_IDS: [Id<ChannelMarker>; 2] = [Id::new(301), Id::new(302)];
+
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(1);
+
+pub fn config() -> Config {
+    Config {
+        application_id: Id::new(BOT_ID),
+        bot_token: "mock-token".to_string(),
+        check_lock_timeout: Duration::from_secs(10_800),
+        client_id: Some(Id::new(BOT_ID)),
+        database_url: "memory:".to_string(),
+        environment: Environment::Development,
+        http_address: SocketAddr::from(([127, 0, 0, 1], 0)),
+        invite_cache_ttl: Duration::from_secs(60),
+        invite_check_cooldown: Duration::from_secs(86_400),
+        invite_flush_interval: Duration::from_secs(5),
+        invite_lookup_concurrency: 4,
+        log_filter: "info".to_string(),
+        log_format: LogFormat::Pretty,
+        max_invite_check_cooldown: Duration::from_secs(604_800),
+        min_invite_check_cooldown: Duration::from_secs(3_600),
+        owner_id: Some(Id::new(OWNER_ID)),
+        refund_interrupted_checks: true,
+        shutdown_grace_period: Duration::from_millis(200),
+        test_guild_id: Some(GUILD_ID)
+    }
+}
+
+pub async fn context(mock: &MockDiscord) -> Arc<Context> {
+    context_with_storage(mock, Arc::new(MemoryStorage::new())).await
+}
+
+pub async fn context_with_storage(mock: &MockDiscord, database: Arc<dyn Storage>) -> Arc<Context> {
+    let client = Arc::new(
+        Client::builder()
+            .token("mock-token".to_string())
+            .proxy(mock.address(), true)
+            .ratelimiter(None)
+            .build()
+    );
+    let (cluster, _events) = ClusterBuilder::new("mock-token".to_string(), Intents::GUILDS | Intents::GUILD_MESSAGES)
+        .http_client(client.clone())
+        .shard_scheme(ShardScheme::Range { from: 0, to: 0, total: 1 })
+        .build()
+        .await
+        .unwrap();
+    let context = Arc::new(Context::new(config(), client, Id::new(BOT_ID), cluster, database));
+
+    context.cache.update(&Event::GuildCreate(Box::new(GuildCreate(guild()))));
+
+    context
+}
+
+fn text_channel(id: Id<ChannelMarker>, parent_id: Option<Id<ChannelMarker>>, position: i64) -> serde_json::Value {
+    json!({
+        "guild_id": GUILD_ID.to_string(),
+        "id": id.to_string(),
+        "last_message_id": "1",
+        "name": format!("channel-{id}"),
+        "nsfw": false,
+        "parent_id": parent_id.map(|id| id.to_string()),
+        "permission_overwrites": [],
+        "position": position,
+        "type": 0
+    })
+}
+
+pub fn guild() -> Guild {
+    // VIEW_CHANNEL, SEND_MESSAGES, EMBED_LINKS, READ_MESSAGE_HISTORY and USE_SLASH_COMMANDS
+    let everyone_permissions = (1024u64 | 2048 | 16384 | 65536 | 2147483648).to_string();
+    let mut channels = vec![
+        text_channel(RESULTS_CHANNEL_ID, None, 0),
+        json!({
+            "guild_id": GUILD_ID.to_string(),
+            "id": CATEGORY_ID.to_string(),
+            "name": "Partners",
+            "permission_overwrites": [],
+            "position": 1,
+            "type": 4
+        })
+    ];
+
+    for (position, channel_id) in PARTNER_CHANNEL_IDS.iter().enumerate() {
+        channels.push(text_channel(*channel_id, Some(CATEGORY_ID), position as i64));
+    }
+
+    serde_json::from_value(json!({
+        "afk_channel_id": null,
+        "afk_timeout": 300,
+        "application_id": null,
+        "banner": null,
+        "channels": channels,
+        "default_message_notifications": 0,
+        "description": null,
+        "discovery_splash": null,
+        "emojis": [],
+        "explicit_content_filter": 0,
+        "features": [],
+        "icon": null,
+        "id": GUILD_ID.to_string(),
+        "large": false,
+        "members": [{
+            "deaf": false,
+            "guild_id": GUILD_ID.to_string(),
+            "joined_at": "2022-01-01T00:00:00.000000+00:00",
+            "mute": false,
+            "roles": [],
+            "user": { "avatar": null, "bot": true, "discriminator": "0001", "id": BOT_ID.to_string(), "username": "Sakura" }
+        }],
+        "mfa_level": 0,
+        "name": "Sakura Test Guild",
+        "nsfw_level": 0,
+        "owner_id": OWNER_ID.to_string(),
+        "preferred_locale": "en-US",
+        "premium_progress_bar_enabled": false,
+        "roles": [{
+            "color": 0,
+            "hoist": false,
+            "id": GUILD_ID.to_string(),
+            "managed": false,
+            "mentionable": false,
+            "name": "@everyone",
+            "permissions": everyone_permissions,
+            "position": 0
+        }],
+        "rules_channel_id": null,
+        "splash": null,
+        "system_channel_flags": 0,
+        "system_channel_id": null,
+        "vanity_url_code": null,
+        "verification_level": 0
+    })).unwrap()
+}
+
+pub fn command(name: &str, channel_id: Id<ChannelMarker>, options: serde_json::Value) -> ApplicationCommand {
+    match interaction(name, channel_id, options, &[], Permissions::ADMINISTRATOR) {
+        Interaction::ApplicationCommand(command) => *command,
+        _ => unreachable!()
+    }
+}
+
+pub fn interaction(name: &str, channel_id: Id<ChannelMarker>, options: serde_json::Value, role_ids: &[Id<RoleMarker>], permissions: Permissions) -> Interaction {
+    serde_json::from_value(json!({
+        "application_id": BOT_ID.to_string(),
+        "channel_id": channel_id.to_string(),
+        "data": { "id": "1", "name": name, "options": options, "type": 1 },
+        "guild_id": GUILD_ID.to_string(),
+        "id": "4194304000",
+        "locale": "en-US",
+        "member": {
+            "deaf": false,
+            "joined_at": "2022-01-01T00:00:00.000000+00:00",
+            "mute": false,
+            "permissions": permissions.bits().to_string(),
+            "roles": role_ids,
+            "user": { "avatar": null, "discriminator": "0001", "id": OWNER_ID.to_string(), "username": "owner" }
+        },
+        // Every interaction gets its own token, like on Discord, so responses to one don't acknowledge another
+        "token": format!("interaction-token-{}", NEXT_TOKEN.fetch_add(1, Ordering::SeqCst)),
+        "type": 2
+    })).unwrap()
+}
+
+pub fn component(custom_id: &str, channel_id: Id<ChannelMarker>) -> MessageComponentInteraction {
+    let interaction = serde_json::from_value(json!({
+        "application_id": BOT_ID.to_string(),
+        "channel_id": channel_id.to_string(),
+        "data": { "component_type": 2, "custom_id": custom_id },
+        "guild_id": GUILD_ID.to_string(),
+        "id": "4194304001",
+        "locale": "en-US",
+        "member": {
+            "deaf": false,
+            "joined_at": "2022-01-01T00:00:00.000000+00:00",
+            "mute": false,
+            "permissions": Permissions::ADMINISTRATOR.bits().to_string(),
+            "roles": [],
+            "user": { "avatar": null, "discriminator": "0001", "id": OWNER_ID.to_string(), "username": "owner" }
+        },
+        "message": {
+            "attachments": [],
+            "author": { "avatar": null, "bot": true, "discriminator": "0001", "id": BOT_ID.to_string(), "username": "Sakura" },
+            "channel_id": channel_id.to_string(),
+            "content": "",
+            "edited_timestamp": null,
+            "embeds": [],
+            "id": "4194304002",
+            "mention_everyone": false,
+            "mention_roles": [],
+            "mentions": [],
+            "pinned": false,
+            "timestamp": "2022-01-01T00:00:00.000000+00:00",
+            "tts": false,
+            "type": 0
+        },
+        "token": format!("interaction-token-{}", NEXT_TOKEN.fetch_add(1, Ordering::SeqCst)),
+        "type": 3
+    })).unwrap();
+
+    match interaction {
+        Interaction::MessageComponent(component) => *component,
+        _ => unreachable!()
+    }
+}
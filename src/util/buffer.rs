@@ -0,0 +1,42 @@
+use crate::database::{invite::IngestReport, Storage, StorageError};
+use dashmap::DashMap;
+use std::collections::HashSet;
+use twilight_model::id::{Id, marker::GuildMarker};
+
+#[derive(Default)]
+pub struct InviteBuffer {
+    codes: DashMap<Id<GuildMarker>, HashSet<String>>
+}
+
+impl InviteBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&self, guild_id: Id<GuildMarker>, codes: HashSet<String>) {
+        self.codes.entry(guild_id).or_default().extend(codes);
+    }
+
+    pub async fn flush(&self, database: &dyn Storage) -> Result<IngestReport, StorageError> {
+        let guild_ids = self.codes.iter().map(|entry| *entry.key()).collect::<Vec<_>>();
+        let mut report = IngestReport::default();
+
+        for guild_id in guild_ids {
+            let codes = match self.codes.remove(&guild_id) {
+                Some((_, codes)) => codes,
+                None => continue
+            };
+
+            match database.create_invites(guild_id, codes.clone()).await {
+                Ok(guild_report) => report += guild_report,
+                Err(error) => {
+                    // Put the codes back so the next flush can retry them
+                    self.push(guild_id, codes);
+                    return Err(error)
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
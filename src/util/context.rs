@@ -1,41 +1,64 @@
 use crate::{
-    constants::APPLICATION_ID,
-    database::Database
+    config::Config,
+    database::Storage,
+    util::{
+        buffer::InviteBuffer,
+        heartbeat::Heartbeats,
+        metrics::Metrics,
+        resolver::InviteResolver,
+        shutdown::Shutdown
+    }
 };
 use std::sync::Arc;
 use twilight_cache_inmemory::{InMemoryCache, ResourceType};
 use twilight_gateway::Cluster;
 use twilight_http::client::{Client, InteractionClient};
+use twilight_model::id::{Id, marker::UserMarker};
 
 pub struct Context {
     pub cache: InMemoryCache,
     pub client: Arc<Client>,
+    pub client_id: Id<UserMarker>,
     pub cluster: Cluster,
-    pub database: Database
+    pub config: Config,
+    pub database: Arc<dyn Storage>,
+    pub heartbeats: Heartbeats,
+    pub invite_buffer: InviteBuffer,
+    pub metrics: Arc<Metrics>,
+    pub resolver: InviteResolver,
+    pub shutdown: Shutdown
 }
 
 
 impl Context {
-    pub fn new(client: Arc<Client>, cluster: Cluster) -> Self {
+    pub fn new(config: Config, client: Arc<Client>, client_id: Id<UserMarker>, cluster: Cluster, database: Arc<dyn Storage>) -> Self {
         let resource_types = ResourceType::CHANNEL 
             | ResourceType::GUILD
             | ResourceType::MEMBER
             | ResourceType::MESSAGE
             | ResourceType::ROLE 
             | ResourceType::USER_CURRENT;
-        
+        let metrics = Arc::new(Metrics::new());
+
         Self {
             cache: InMemoryCache::builder()
                 .message_cache_size(15)
                 .resource_types(resource_types)
                 .build(),
+            resolver: InviteResolver::new(client.clone(), config.invite_lookup_concurrency, config.invite_cache_ttl, metrics.clone()),
             client,
+            client_id,
             cluster,
-            database: Database::new()
+            config,
+            database,
+            heartbeats: Heartbeats::new(),
+            invite_buffer: InviteBuffer::new(),
+            metrics,
+            shutdown: Shutdown::new()
         }
     }
 
-    pub fn get_interaction_client(&self) -> InteractionClient {
-        self.client.interaction(*APPLICATION_ID)
+    pub fn get_interaction_client(&self) -> InteractionClient<'_> {
+        self.client.interaction(self.config.application_id)
     }
 }
\ No newline at end of file
@@ -0,0 +1,117 @@
+use crate::database::check::{CategoryResult, CheckRun};
+use serde_json::{json, Value};
+
+const CSV_HEADER: &str = "category_id,category,channel_id,code,status,is_permanent,expires_at,message_id,message_url";
+
+// One row per invite occurrence, flattened out of the category and channel it was found in
+fn rows(run: &CheckRun, category_results: &[CategoryResult]) -> Vec<Value> {
+    category_results
+        .iter()
+        .flat_map(|category_result| category_result.channel_results.iter().map(move |channel_result| (category_result, channel_result)))
+        .flat_map(|(category_result, channel_result)| channel_result.invites.iter().map(move |invite| json!({
+            "category_id": category_result.category_id.to_string(),
+            "category": category_result.name,
+            "channel_id": channel_result.channel_id.to_string(),
+            "code": invite.code,
+            "status": invite.status.as_str(),
+            "is_permanent": invite.is_permanent,
+            "expires_at": invite.expires_at.map(|expires_at| expires_at.and_utc().to_rfc3339()),
+            "message_id": invite.message_id.to_string(),
+            "message_url": format!("https://discord.com/channels/{}/{}/{}", run.guild_id, channel_result.channel_id, invite.message_id)
+        })))
+        .collect()
+}
+
+pub fn json(run: &CheckRun, category_results: &[CategoryResult]) -> String {
+    let export = json!({
+        "check": run.id,
+        "guild_id": run.guild_id.to_string(),
+        "user_id": run.user_id.to_string(),
+        "status": run.status.as_str(),
+        "started_at": run.started_at.and_utc().to_rfc3339(),
+        "finished_at": run.finished_at.map(|finished_at| finished_at.and_utc().to_rfc3339()),
+        "invites": rows(run, category_results)
+    });
+
+    serde_json::to_string_pretty(&export).unwrap()
+}
+
+fn csv_field(value: &Value) -> String {
+    let text = match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        value => value.to_string()
+    };
+
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+pub fn csv(run: &CheckRun, category_results: &[CategoryResult]) -> String {
+    let mut lines = vec![CSV_HEADER.to_string()];
+
+    for row in rows(run, category_results) {
+        lines.push(CSV_HEADER.split(',').map(|column| csv_field(&row[column])).collect::<Vec<String>>().join(","));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+// File names and contents for both formats, ready to attach
+pub fn files(run: &CheckRun, category_results: &[CategoryResult]) -> [(String, String); 2] {
+    [
+        (format!("sakura-check-{}.json", run.id), json(run, category_results)),
+        (format!("sakura-check-{}.csv", run.id), csv(run, category_results))
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::database::check::{CategoryResult, ChannelResult, CheckRun, CheckStatus, InviteResult, InviteStatus};
+    use twilight_model::id::Id;
+    use super::{csv, json};
+
+    #[test]
+    fn exports_one_row_per_invite_occurrence() {
+        let started_at = NaiveDate::from_ymd_opt(2022, 1, 1).unwrap().and_hms_opt(12, 0, 0).unwrap();
+        let run = CheckRun {
+            id: 3,
+            guild_id: Id::new(100),
+            user_id: Id::new(11),
+            status: CheckStatus::Completed,
+            started_at,
+            finished_at: Some(started_at)
+        };
+        let invite = |message_id, code: &str, status| InviteResult {
+            message_id: Id::new(message_id),
+            code: code.to_string(),
+            status,
+            is_permanent: None,
+            expires_at: None
+        };
+        let mut channel_result = ChannelResult::new(Id::new(301));
+        let mut category_result = CategoryResult::new(Id::new(300), "Partners, \"big\"".to_string());
+
+        channel_result.invites = vec![invite(5, "good", InviteStatus::Valid), invite(6, "good", InviteStatus::Valid), invite(6, "gone", InviteStatus::Invalid)];
+        channel_result.invites[0].is_permanent = Some(true);
+        category_result.channel_results.push(channel_result);
+
+        let csv = csv(&run, &[category_result.clone()]);
+        let lines = csv.lines().collect::<Vec<&str>>();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[1], "300,\"Partners, \"\"big\"\"\",301,good,valid,true,,5,https://discord.com/channels/100/301/5");
+        assert_eq!(lines[3], "300,\"Partners, \"\"big\"\"\",301,gone,invalid,,,6,https://discord.com/channels/100/301/6");
+
+        let json: serde_json::Value = serde_json::from_str(&json(&run, &[category_result])).unwrap();
+
+        assert_eq!(json["check"], 3);
+        assert_eq!(json["invites"].as_array().unwrap().len(), 3);
+        assert_eq!(json["invites"][2]["status"], "invalid");
+        assert_eq!(json["invites"][0]["is_permanent"], true);
+    }
+}
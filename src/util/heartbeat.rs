@@ -0,0 +1,35 @@
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+
+// Background loops beat on every pass, so one that stops beating has died or is stuck
+#[derive(Default)]
+pub struct Heartbeats {
+    beats: DashMap<&'static str, (Instant, Duration)>
+}
+
+impl Heartbeats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // `max_gap` is how long the task may go before its next beat without being considered stalled
+    pub fn beat(&self, task: &'static str, max_gap: Duration) {
+        self.beats.insert(task, (Instant::now(), max_gap));
+    }
+
+    // Tasks that haven't started yet aren't listed, so nothing is reported before the first beat
+    pub fn statuses(&self) -> Vec<(&'static str, Duration, bool)> {
+        let mut statuses = self.beats
+            .iter()
+            .map(|entry| {
+                let (beat_at, max_gap) = *entry.value();
+                let elapsed = beat_at.elapsed();
+
+                (*entry.key(), elapsed, elapsed <= max_gap)
+            })
+            .collect::<Vec<_>>();
+        statuses.sort_by_key(|(task, _, _)| *task);
+
+        statuses
+    }
+}
@@ -1,16 +1,100 @@
-use crate::{constants::DISCORD_INVITE_REGEX, util::context::Context};
-use std::{collections::HashSet, sync::Arc};
+use crate::{
+    commands::error::CommandError,
+    constants::DISCORD_INVITE_REGEX,
+    database::{invite::IngestReport, setting::DEFAULT_SCAN_DEPTH, StorageError},
+    util::context::Context
+};
+use std::{cmp, collections::HashSet, sync::Arc, time::Duration};
+use tokio::time;
+use twilight_http::{api_error::ApiError, error::ErrorType, Client};
 use twilight_model::{
     channel::{GuildChannel, Message},
+    datetime::Timestamp,
     id::{
         Id,
         marker::{ChannelMarker, GuildMarker}
     }
 };
 
-pub async fn extract_codes_from_category(guild_id: Id<GuildMarker>, category_id: Id<ChannelMarker>, context: Arc<Context>) {
+const UNKNOWN_INVITE_ERROR_CODE: u64 = 10006;
+const MAX_LOOKUP_ATTEMPTS: u32 = 3;
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+const MESSAGES_PER_REQUEST: usize = 100;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InviteOutcome {
+    Valid { expires_at: Option<Timestamp>, is_permanent: bool },
+    Unknown,
+    RateLimited { retry_after: Duration },
+    Transient
+}
+
+impl InviteOutcome {
+    fn from_error(error: &twilight_http::Error) -> Self {
+        match error.kind() {
+            ErrorType::Response { error: ApiError::General(general), .. } if general.code == UNKNOWN_INVITE_ERROR_CODE => Self::Unknown,
+            ErrorType::Response { error: ApiError::Ratelimited(ratelimited), .. } => Self::RateLimited {
+                retry_after: Duration::from_secs_f64(ratelimited.retry_after.max(0.0))
+            },
+            ErrorType::Response { status, .. } if status.raw() == 404 => Self::Unknown,
+            ErrorType::Response { status, .. } if status.raw() == 429 => Self::RateLimited { retry_after: Duration::from_secs(1) },
+            _ => Self::Transient
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Valid { .. } => "valid",
+            Self::Unknown => "unknown",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::Transient => "transient"
+        }
+    }
+
+    // Only a confirmed lookup result may change what is stored for an invite
+    pub fn is_conclusive(&self) -> bool {
+        matches!(self, Self::Valid { .. } | Self::Unknown)
+    }
+}
+
+pub async fn fetch_invite(client: &Client, code: &str) -> InviteOutcome {
+    match client.invite(code).with_expiration().exec().await {
+        Ok(response) => match response.model().await {
+            Ok(invite) => InviteOutcome::Valid {
+                expires_at: invite.expires_at,
+                is_permanent: invite.expires_at.is_none() && invite.max_age.is_none() && invite.max_uses.is_none()
+            },
+            Err(_) => InviteOutcome::Transient
+        },
+        Err(error) => InviteOutcome::from_error(&error)
+    }
+}
+
+pub async fn resolve_invite(client: &Client, code: &str) -> InviteOutcome {
+    let mut attempt = 0;
+
+    loop {
+        let outcome = fetch_invite(client, code).await;
+        attempt += 1;
+
+        if outcome.is_conclusive() || attempt >= MAX_LOOKUP_ATTEMPTS {
+            return outcome
+        }
+
+        let delay = match &outcome {
+            InviteOutcome::RateLimited { retry_after } => (*retry_after).min(MAX_RETRY_AFTER),
+            _ => Duration::from_millis(500 * 2u64.pow(attempt - 1))
+        };
+
+        time::sleep(delay).await;
+    }
+}
+
+pub async fn extract_codes_from_category(guild_id: Id<GuildMarker>, category_id: Id<ChannelMarker>, context: Arc<Context>) -> Result<IngestReport, StorageError> {
     if let Some(guild_channel_ids) = context.cache.guild_channels(guild_id) {
         let mut codes: HashSet<String> = HashSet::new();
+        let scan_depth = context.database.read_setting(guild_id).await?.map_or(DEFAULT_SCAN_DEPTH, |setting| setting.scan_depth);
+        let scan_depths = context.database.read_channel_scan_depths(guild_id).await?;
 
         for guild_channel_id in guild_channel_ids.iter() {
             let guild_channel_reference = match context.cache.guild_channel(*guild_channel_id) {
@@ -27,22 +111,10 @@ pub async fn extract_codes_from_category(guild_id: Id<GuildMarker>, category_id:
                 }
                 _ => continue
             };
-            let mut request = context.client.channel_messages(channel_id_to_search).limit(15).unwrap().exec();
-            let context_clone = context.clone();
-
-            request.set_pre_flight(Box::new(move || {
-                match context_clone.cache.channel_messages(channel_id_to_search) {
-                    Some(message_ids) => message_ids.count() < 15,
-                    None => true,
-                }
-            }));
-
-            let messages = match request.await {
-                Ok(response) => match response.models().await {
-                    Ok(messages) => messages,
-                    Err(_) => vec![],
-                },
-                Err(_) => continue,
+            let scan_depth = scan_depths.get(&channel_id_to_search).copied().unwrap_or(scan_depth);
+            let messages = match read_messages(&context, channel_id_to_search, scan_depth).await {
+                Ok(Some(messages)) => messages,
+                _ => continue,
             };
 
             for message in messages {
@@ -51,10 +123,12 @@ pub async fn extract_codes_from_category(guild_id: Id<GuildMarker>, category_id:
             }
         }
 
-        if codes.len() > 0 {
-            context.database.create_invites(guild_id, codes).await;
+        if !codes.is_empty() {
+            return context.database.create_invites(guild_id, codes).await
         }
     }
+
+    Ok(IngestReport::default())
 }
 
 pub fn extract_codes_from_message(message: Message) -> HashSet<String> {
@@ -68,4 +142,67 @@ pub fn extract_codes_from_message(message: Message) -> HashSet<String> {
     }
 
     codes
-}
\ No newline at end of file
+}
+
+// Discord returns at most 100 messages per request, so deeper scans page back from the oldest message seen so far
+// None means the messages couldn't be read, and the channel needs a manual check
+pub async fn read_messages(context: &Context, channel_id: Id<ChannelMarker>, scan_depth: u16) -> Result<Option<Vec<Message>>, CommandError> {
+    let scan_depth = scan_depth as usize;
+    let mut messages: Vec<Message> = vec![];
+
+    while messages.len() < scan_depth {
+        let limit = cmp::min(scan_depth - messages.len(), MESSAGES_PER_REQUEST);
+        let request = match messages.last() {
+            Some(oldest) => context.client.channel_messages(channel_id).before(oldest.id).limit(limit as u64)?.exec(),
+            None => context.client.channel_messages(channel_id).limit(limit as u64)?.exec()
+        };
+        // Missing access or a Discord outage shouldn't fail the whole check, only this channel
+        let page = match request.await {
+            Ok(response) => match response.models().await {
+                Ok(page) => page,
+                _ => return Ok(None)
+            },
+            _ => return Ok(None)
+        };
+        let is_last_page = page.len() < limit;
+
+        messages.extend(page);
+
+        if is_last_page {
+            break
+        }
+    }
+
+    Ok(Some(messages))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{self, discord::MockDiscord, CATEGORY_ID, GUILD_ID, PARTNER_CHANNEL_IDS};
+    use super::extract_codes_from_category;
+
+    #[tokio::test]
+    async fn category_ingestion_reads_back_to_each_channels_scan_depth() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+        let mut history = vec!["nothing to see here"; 150];
+
+        history[19] = "discord.gg/shallow";
+        history[119] = "discord.gg/deep";
+        history[120] = "discord.gg/deeper";
+        context.database.create_setting(GUILD_ID).await.unwrap();
+        context.database.update_scan_depth(GUILD_ID, 20).await.unwrap();
+        context.database.update_channel_scan_depth(GUILD_ID, PARTNER_CHANNEL_IDS[0], Some(120)).await.unwrap();
+        mock.set_messages(PARTNER_CHANNEL_IDS[0], &history);
+        history[119] = "nothing to see here";
+        mock.set_messages(PARTNER_CHANNEL_IDS[1], &history);
+
+        let report = extract_codes_from_category(GUILD_ID, CATEGORY_ID, context.clone()).await.unwrap();
+        let invites = context.database.read_guild_invites(GUILD_ID).await.unwrap();
+
+        assert_eq!(report.new, 2);
+        assert!(invites.contains_key("shallow"));
+        assert!(invites.contains_key("deep"));
+        assert!(!invites.contains_key("deeper"));
+    }
+}
@@ -0,0 +1,16 @@
+use crate::config::{Config, LogFormat};
+use tracing_subscriber::EnvFilter;
+
+// Config::load already rejected invalid filters, so this can't fail on a loaded config
+pub fn init(config: &Config) {
+    let subscriber = tracing_subscriber::fmt().with_env_filter(EnvFilter::new(&config.log_filter));
+
+    match config.log_format {
+        LogFormat::Json => subscriber
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .init(),
+        LogFormat::Pretty => subscriber.init()
+    }
+}
@@ -0,0 +1,83 @@
+use prometheus::{
+    Encoder,
+    Histogram,
+    HistogramOpts,
+    HistogramVec,
+    IntCounterVec,
+    IntGauge,
+    IntGaugeVec,
+    Opts,
+    Registry,
+    TextEncoder
+};
+
+// Each Context owns its own registry, so tests running side by side don't share counts
+pub struct Metrics {
+    registry: Registry,
+    pub check_duration: Histogram,
+    pub checks: IntCounterVec,
+    pub command_duration: HistogramVec,
+    pub commands: IntCounterVec,
+    pub database_connections: IntGaugeVec,
+    pub gateway_events: IntCounterVec,
+    pub invite_backlog: IntGauge,
+    pub invite_lookups: IntCounterVec,
+    pub task_batch_size: HistogramVec
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("sakura".to_string()), None).unwrap();
+        let metrics = Self {
+            check_duration: Histogram::with_opts(
+                HistogramOpts::new("check_duration_seconds", "How long invite checks take")
+                    .buckets(vec![1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0])
+            ).unwrap(),
+            checks: IntCounterVec::new(Opts::new("checks_total", "Invite checks run, by outcome"), &["outcome"]).unwrap(),
+            command_duration: HistogramVec::new(
+                HistogramOpts::new("command_duration_seconds", "How long commands take to run"),
+                &["command"]
+            ).unwrap(),
+            commands: IntCounterVec::new(Opts::new("commands_total", "Commands executed, by outcome"), &["command", "outcome"]).unwrap(),
+            database_connections: IntGaugeVec::new(
+                Opts::new("database_connections", "Database pool connections, by state"),
+                &["state"]
+            ).unwrap(),
+            gateway_events: IntCounterVec::new(Opts::new("gateway_events_total", "Gateway events received"), &["shard", "kind"]).unwrap(),
+            invite_backlog: IntGauge::new("invite_backlog", "Invites that have never been checked").unwrap(),
+            invite_lookups: IntCounterVec::new(Opts::new("invite_lookups_total", "Invite lookups sent to Discord, by outcome"), &["outcome"]).unwrap(),
+            task_batch_size: HistogramVec::new(
+                HistogramOpts::new("task_batch_size", "Codes handled per background task batch")
+                    .buckets(vec![0.0, 1.0, 2.0, 4.0, 8.0, 16.0, 64.0, 256.0, 1024.0]),
+                &["task"]
+            ).unwrap(),
+            registry
+        };
+
+        metrics.registry.register(Box::new(metrics.check_duration.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.checks.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.command_duration.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.commands.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.database_connections.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.gateway_events.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.invite_backlog.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.invite_lookups.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.task_batch_size.clone())).unwrap();
+
+        metrics
+    }
+
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer).unwrap();
+
+        String::from_utf8(buffer).unwrap()
+    }
+}
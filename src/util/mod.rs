@@ -1,3 +1,10 @@
+pub mod buffer;
 pub mod context;
+pub mod export;
+pub mod heartbeat;
 pub mod invite;
-pub mod random;
\ No newline at end of file
+pub mod logging;
+pub mod metrics;
+pub mod random;
+pub mod resolver;
+pub mod shutdown;
\ No newline at end of file
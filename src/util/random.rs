@@ -29,22 +29,27 @@ pub fn get_shard_id(guild_id: Id<GuildMarker>, total_shards: u64) -> u64 {
 
 pub fn humanize(mut milliseconds: u64, show_ms: bool) -> String {
     let days = milliseconds / 86_400_000;
-    milliseconds = milliseconds % 86_400_000;
+    milliseconds %= 86_400_000;
     let hours = milliseconds / 3_600_000;
-    milliseconds = milliseconds % 3_600_000;
+    milliseconds %= 3_600_000;
     let minutes = milliseconds / 60_000;
-    milliseconds = milliseconds % 60_000;
+    milliseconds %= 60_000;
     let seconds = milliseconds / 1_000;
-    milliseconds = milliseconds % 1_000;
+    milliseconds %= 1_000;
 
 
-    let parts = vec![(days, "d"), (hours, "h"), (minutes, "m"), (seconds, "s"), (milliseconds, "ms")];
+    let parts = [(days, "d"), (hours, "h"), (minutes, "m"), (seconds, "s"), (milliseconds, "ms")];
     let duration: String = parts.iter().filter_map(|(value, unit)| match *unit {
            "ms" if *value > 0 && show_ms => Some(format!("{value}{unit}")),
            _ if *value > 0 => Some(format!("{value}{unit}")),
            _ => None
     }).collect::<Vec<String>>().join(" ");
 
+    // Embed fields can't be empty, so a zero duration still needs a unit
+    if duration.is_empty() {
+        return if show_ms { "0ms" } else { "0s" }.to_string()
+    }
+
     duration
 }
 
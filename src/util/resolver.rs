@@ -0,0 +1,125 @@
+use crate::{
+    database::{Storage, StorageError},
+    util::{invite::{resolve_invite, InviteOutcome}, metrics::Metrics}
+};
+use dashmap::DashMap;
+use std::{sync::Arc, time::{Duration, Instant}};
+use tokio::sync::{OnceCell, Semaphore};
+use twilight_http::Client;
+use twilight_model::id::{Id, marker::GuildMarker};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Priority {
+    Interactive,
+    Background
+}
+
+pub struct InviteResolver {
+    cache: DashMap<String, (Instant, InviteOutcome)>,
+    cache_ttl: Duration,
+    client: Arc<Client>,
+    in_flight: DashMap<String, Arc<OnceCell<InviteOutcome>>>,
+    // Interactive lookups get one permit less than the total so background revalidation can always make progress
+    interactive_permits: Semaphore,
+    metrics: Arc<Metrics>,
+    permits: Semaphore
+}
+
+impl InviteResolver {
+    pub fn new(client: Arc<Client>, concurrency: usize, cache_ttl: Duration, metrics: Arc<Metrics>) -> Self {
+        let concurrency = concurrency.max(2);
+
+        Self {
+            cache: DashMap::new(),
+            cache_ttl,
+            client,
+            in_flight: DashMap::new(),
+            interactive_permits: Semaphore::new(concurrency - 1),
+            metrics,
+            permits: Semaphore::new(concurrency)
+        }
+    }
+
+    fn cached(&self, code: &str) -> Option<InviteOutcome> {
+        let entry = self.cache.get(code)?;
+        let (resolved_at, outcome) = entry.value();
+
+        if resolved_at.elapsed() < self.cache_ttl {
+            Some(outcome.clone())
+        } else {
+            None
+        }
+    }
+
+    pub async fn resolve(&self, code: &str, priority: Priority) -> InviteOutcome {
+        if let Some(outcome) = self.cached(code) {
+            return outcome
+        }
+
+        let cell = self.in_flight.entry(code.to_string()).or_default().clone();
+        let outcome = cell.get_or_init(|| async {
+            let _interactive_permit = match priority {
+                Priority::Interactive => Some(self.interactive_permits.acquire().await.unwrap()),
+                Priority::Background => None
+            };
+            let _permit = self.permits.acquire().await.unwrap();
+            let outcome = resolve_invite(&self.client, code).await;
+
+            self.metrics.invite_lookups.with_label_values(&[outcome.label()]).inc();
+
+            outcome
+        }).await.clone();
+
+        self.in_flight.remove_if(code, |_, in_flight| Arc::ptr_eq(in_flight, &cell));
+
+        if outcome.is_conclusive() {
+            self.cache.insert(code.to_string(), (Instant::now(), outcome.clone()));
+        }
+
+        outcome
+    }
+
+    pub async fn check(&self, database: &dyn Storage, guild_id: Id<GuildMarker>, code: String, priority: Priority) -> Result<InviteOutcome, StorageError> {
+        let outcome = self.resolve(&code, priority).await;
+
+        match outcome {
+            InviteOutcome::Valid { expires_at, is_permanent } => database.upsert_code(guild_id, code, expires_at, is_permanent, true).await?,
+            InviteOutcome::Unknown => database.upsert_code(guild_id, code, None, false, false).await?,
+            // A failed lookup says nothing about the invite, so keep its last known state
+            InviteOutcome::RateLimited { .. } | InviteOutcome::Transient => {}
+        }
+
+        Ok(outcome)
+    }
+
+    pub fn prune(&self) {
+        self.cache.retain(|_, (resolved_at, _)| resolved_at.elapsed() < self.cache_ttl);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{self, discord::{InviteState, MockDiscord}};
+    use futures_util::future;
+    use super::Priority;
+
+    #[tokio::test]
+    async fn concurrent_lookups_of_one_code_hit_discord_once() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+
+        mock.set_invite("shared", InviteState::permanent());
+
+        let outcomes = future::join_all((0..8).map(|i| {
+            let priority = if i % 2 == 0 { Priority::Interactive } else { Priority::Background };
+
+            context.resolver.resolve("shared", priority)
+        })).await;
+
+        assert!(outcomes.iter().all(|outcome| outcome.is_conclusive()));
+        assert_eq!(mock.invite_lookups("shared"), 1);
+
+        context.resolver.resolve("shared", Priority::Background).await;
+        assert_eq!(mock.invite_lookups("shared"), 1);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{commands::error::CommandError, util::context::Context};
+use dashmap::DashMap;
+use std::{
+    sync::{atomic::{AtomicBool, Ordering}, Arc},
+    time::Duration
+};
+use tokio::{sync::Notify, time};
+use tracing::{error, info, warn};
+use twilight_embed_builder::EmbedBuilder;
+use twilight_model::id::{Id, marker::{ChannelMarker, GuildMarker}};
+
+// How often the drain looks at the remaining checks, in case a notification was missed
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+struct ActiveCheck {
+    results_channel_id: Id<ChannelMarker>,
+    // Set by /cancel-check, the check stops after the channel it's on
+    is_cancelled: bool
+}
+
+#[derive(Default)]
+pub struct Shutdown {
+    // Guilds with a check in progress
+    active_checks: DashMap<Id<GuildMarker>, ActiveCheck>,
+    check_finished: Notify,
+    is_started: AtomicBool
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.is_started.load(Ordering::SeqCst)
+    }
+
+    pub fn track_check(&self, guild_id: Id<GuildMarker>, results_channel_id: Id<ChannelMarker>) {
+        self.active_checks.insert(guild_id, ActiveCheck { results_channel_id, is_cancelled: false });
+    }
+
+    pub fn is_check_active(&self, guild_id: Id<GuildMarker>) -> bool {
+        self.active_checks.contains_key(&guild_id)
+    }
+
+    // False when the guild has no check in progress
+    pub fn cancel_check(&self, guild_id: Id<GuildMarker>) -> bool {
+        match self.active_checks.get_mut(&guild_id) {
+            Some(mut check) => {
+                check.is_cancelled = true;
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn is_check_cancelled(&self, guild_id: Id<GuildMarker>) -> bool {
+        self.active_checks.get(&guild_id).is_some_and(|check| check.is_cancelled)
+    }
+
+    pub fn finish_check(&self, guild_id: Id<GuildMarker>) {
+        if self.active_checks.remove(&guild_id).is_some() {
+            self.check_finished.notify_waiters();
+        }
+    }
+
+    async fn drain(&self, grace_period: Duration) {
+        let deadline = time::Instant::now() + grace_period;
+
+        while !self.active_checks.is_empty() {
+            let wait = time::timeout(DRAIN_POLL_INTERVAL, self.check_finished.notified());
+
+            if time::timeout_at(deadline, wait).await.is_err() {
+                return
+            }
+        }
+    }
+}
+
+// Resolves on Ctrl+C, or on SIGTERM where there is one
+pub async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => tokio::select! {
+                _ = tokio::signal::ctrl_c() => {},
+                _ = terminate.recv() => {}
+            },
+            Err(error) => {
+                warn!(%error, "could not listen for SIGTERM");
+                tokio::signal::ctrl_c().await.ok();
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await.ok();
+}
+
+async fn post_aborted_notice(context: &Context, channel_id: Id<ChannelMarker>) -> Result<(), CommandError> {
+    let embed = EmbedBuilder::new()
+        .color(0xF8F8FF)
+        .description("Sakura restarted before this invite check could finish. Your cooldown was not used, so you can run the check again once Sakura is back.")
+        .build()?;
+
+    context.client.create_message(channel_id).embeds(&[embed])?.exec().await?;
+
+    Ok(())
+}
+
+pub async fn run(context: Arc<Context>) {
+    let shutdown = &context.shutdown;
+
+    shutdown.is_started.store(true, Ordering::SeqCst);
+    info!(active_checks = shutdown.active_checks.len(), "shutting down");
+    shutdown.drain(context.config.shutdown_grace_period).await;
+
+    // Checks that outlived the grace period never reach update_last_check, so their cooldown stays unused
+    let unfinished = shutdown.active_checks
+        .iter()
+        .map(|entry| (*entry.key(), entry.value().results_channel_id))
+        .collect::<Vec<_>>();
+
+    for (guild_id, results_channel_id) in unfinished {
+        shutdown.active_checks.remove(&guild_id);
+        warn!(%guild_id, "aborting invite check");
+
+        if let Err(error) = context.database.update_in_check(guild_id, false).await {
+            error!(%guild_id, %error, "could not reset in_check");
+        }
+
+        if let Err(error) = context.database.abort_check_runs(guild_id).await {
+            error!(%guild_id, %error, "could not mark check runs as aborted");
+        }
+
+        if let Err(error) = post_aborted_notice(&context, results_channel_id).await {
+            warn!(%guild_id, %error, "could not post the aborted check notice");
+        }
+    }
+
+    if let Err(error) = context.invite_buffer.flush(context.database.as_ref()).await {
+        error!(%error, "could not flush the invite buffer");
+    }
+
+    context.cluster.down();
+    info!("shut down");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        database::check::CheckStatus,
+        testing::{self, discord::MockDiscord, GUILD_ID, OWNER_ID, RESULTS_CHANNEL_ID}
+    };
+    use std::{collections::HashSet, time::Duration};
+    use twilight_model::id::Id;
+    use super::run;
+
+    #[tokio::test]
+    async fn shutdown_waits_for_checks_then_aborts_the_rest() {
+        let mock = MockDiscord::start().await;
+        let context = testing::context(&mock).await;
+        let finishing_guild_id = Id::new(101);
+
+        for guild_id in [GUILD_ID, finishing_guild_id] {
+            context.database.create_setting(guild_id).await.unwrap();
+            context.database.update_in_check(guild_id, true).await.unwrap();
+        }
+        let run_id = context.database.create_check_run(GUILD_ID, Id::new(OWNER_ID)).await.unwrap();
+        context.shutdown.track_check(GUILD_ID, RESULTS_CHANNEL_ID);
+        context.shutdown.track_check(finishing_guild_id, Id::new(201));
+        context.invite_buffer.push(GUILD_ID, HashSet::from(["pending".to_string()]));
+
+        let finishing_context = context.clone();
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            finishing_context.database.update_in_check(finishing_guild_id, false).await.unwrap();
+            finishing_context.shutdown.finish_check(finishing_guild_id);
+        });
+        run(context.clone()).await;
+
+        assert!(context.shutdown.is_started());
+        assert!(!context.database.read_setting(GUILD_ID).await.unwrap().unwrap().in_check);
+        assert!(context.database.read_setting(GUILD_ID).await.unwrap().unwrap().last_check.is_none());
+        assert_eq!(context.database.read_check_run(GUILD_ID, run_id).await.unwrap().unwrap().status, CheckStatus::Aborted);
+        assert_eq!(mock.created_messages(RESULTS_CHANNEL_ID).len(), 1);
+        assert!(mock.created_messages(Id::new(201)).is_empty());
+        assert!(context.database.read_guild_invites(GUILD_ID).await.unwrap().contains_key("pending"));
+    }
+}